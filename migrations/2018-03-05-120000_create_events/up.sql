@@ -0,0 +1,12 @@
+CREATE TABLE events (
+    id UUID PRIMARY KEY,
+    owner_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    start_date TIMESTAMPTZ NOT NULL,
+    end_date TIMESTAMPTZ NOT NULL,
+    CONSTRAINT end_after_start CHECK (end_date >= start_date)
+);
+
+CREATE INDEX events_start_date_idx ON events (start_date);
+CREATE INDEX events_owner_id_idx ON events (owner_id);
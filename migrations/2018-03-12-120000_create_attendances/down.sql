@@ -0,0 +1 @@
+DROP TABLE attendances;
\ No newline at end of file
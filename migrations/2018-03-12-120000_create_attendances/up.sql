@@ -0,0 +1,10 @@
+CREATE TABLE attendances (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    event_id UUID NOT NULL REFERENCES events(id) ON DELETE CASCADE,
+    status VARCHAR NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (user_id, event_id),
+    CONSTRAINT status_valid CHECK (status IN ('going', 'maybe', 'not_going'))
+);
+
+CREATE INDEX attendances_event_id_idx ON attendances (event_id);
@@ -0,0 +1,3 @@
+DROP INDEX attendances_waitlist_idx;
+ALTER TABLE attendances DROP COLUMN waitlisted_at;
+ALTER TABLE events DROP COLUMN max_attendees;
\ No newline at end of file
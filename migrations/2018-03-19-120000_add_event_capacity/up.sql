@@ -0,0 +1,9 @@
+ALTER TABLE events
+    ADD COLUMN max_attendees INTEGER,
+    ADD CONSTRAINT max_attendees_positive CHECK (max_attendees > 0);
+
+-- A `going` RSVP with a `waitlisted_at` is waiting for a spot, the waitlist is ordered by it
+ALTER TABLE attendances ADD COLUMN waitlisted_at TIMESTAMPTZ;
+
+CREATE INDEX attendances_waitlist_idx ON attendances (event_id, waitlisted_at)
+    WHERE waitlisted_at IS NOT NULL;
@@ -0,0 +1,2 @@
+DROP TABLE event_occurrences;
+ALTER TABLE events DROP COLUMN exdates, DROP COLUMN rrule;
\ No newline at end of file
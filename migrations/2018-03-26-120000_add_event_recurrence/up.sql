@@ -0,0 +1,17 @@
+-- `rrule` is an RFC 5545 RRULE that repeats the event, `start_date` and `end_date` are the
+-- first occurrence
+ALTER TABLE events
+    ADD COLUMN rrule VARCHAR,
+    ADD COLUMN exdates TIMESTAMPTZ[] NOT NULL DEFAULT '{}';
+
+-- Changes to single occurrences of a recurring event, keyed by the start the rule gave them
+CREATE TABLE event_occurrences (
+    event_id UUID NOT NULL REFERENCES events(id) ON DELETE CASCADE,
+    original_start_date TIMESTAMPTZ NOT NULL,
+    cancelled BOOLEAN NOT NULL DEFAULT 'f',
+    name VARCHAR,
+    description TEXT,
+    start_date TIMESTAMPTZ,
+    end_date TIMESTAMPTZ,
+    PRIMARY KEY (event_id, original_start_date)
+);
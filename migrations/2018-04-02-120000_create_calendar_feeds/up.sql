@@ -0,0 +1,8 @@
+-- Each row backs a calendar subscription token, deleting the row revokes the token
+CREATE TABLE calendar_feeds (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX calendar_feeds_user_id_idx ON calendar_feeds (user_id);
@@ -0,0 +1,2 @@
+DROP INDEX events_owner_id_uid;
+ALTER TABLE events DROP COLUMN uid;
\ No newline at end of file
@@ -0,0 +1,4 @@
+-- `uid` is the iCalendar UID of an imported event, it is unique per owner so re-imports update
+-- the same event
+ALTER TABLE events ADD COLUMN uid VARCHAR;
+CREATE UNIQUE INDEX events_owner_id_uid ON events (owner_id, uid);
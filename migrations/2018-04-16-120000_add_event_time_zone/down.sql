@@ -0,0 +1 @@
+ALTER TABLE events DROP COLUMN local_end_date, DROP COLUMN local_start_date, DROP COLUMN time_zone;
\ No newline at end of file
@@ -0,0 +1,14 @@
+-- `time_zone` is the IANA time zone of the event, `local_start_date` and `local_end_date` are the
+-- wall clock times in it.  Recurring events repeat on the wall clock so they follow DST changes.
+ALTER TABLE events
+    ADD COLUMN time_zone VARCHAR NOT NULL DEFAULT 'UTC',
+    ADD COLUMN local_start_date TIMESTAMP,
+    ADD COLUMN local_end_date TIMESTAMP;
+
+UPDATE events SET
+    local_start_date = start_date AT TIME ZONE 'UTC',
+    local_end_date = end_date AT TIME ZONE 'UTC';
+
+ALTER TABLE events
+    ALTER COLUMN local_start_date SET NOT NULL,
+    ALTER COLUMN local_end_date SET NOT NULL;
@@ -0,0 +1,3 @@
+DROP INDEX events_tags;
+DROP INDEX events_search;
+ALTER TABLE events DROP COLUMN tags, DROP COLUMN longitude, DROP COLUMN latitude;
\ No newline at end of file
@@ -0,0 +1,11 @@
+-- `latitude` and `longitude` locate the event, `tags` are free form labels to find it by
+ALTER TABLE events
+    ADD COLUMN latitude DOUBLE PRECISION,
+    ADD COLUMN longitude DOUBLE PRECISION,
+    ADD COLUMN tags VARCHAR[] NOT NULL DEFAULT '{}',
+    ADD CONSTRAINT events_location CHECK ((latitude IS NULL) = (longitude IS NULL));
+
+-- The full text search over the name and description
+CREATE INDEX events_search ON events
+    USING GIN (to_tsvector('english', name || ' ' || description));
+CREATE INDEX events_tags ON events USING GIN (tags);
@@ -0,0 +1,18 @@
+-- The places that events happen at, many events can share a venue
+CREATE TABLE venues (
+    id UUID PRIMARY KEY,
+    owner_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR NOT NULL,
+    address TEXT NOT NULL DEFAULT '',
+    latitude DOUBLE PRECISION,
+    longitude DOUBLE PRECISION,
+    capacity INTEGER CHECK (capacity > 0),
+    accessibility_notes TEXT NOT NULL DEFAULT '',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    CONSTRAINT venues_location CHECK ((latitude IS NULL) = (longitude IS NULL))
+);
+
+-- Deleting a venue leaves its events without one
+ALTER TABLE events ADD COLUMN venue_id UUID REFERENCES venues(id) ON DELETE SET NULL;
+
+CREATE INDEX events_venue_id_idx ON events (venue_id);
@@ -0,0 +1,2 @@
+DROP TABLE invitations;
+ALTER TABLE events DROP COLUMN visibility;
\ No newline at end of file
@@ -0,0 +1,18 @@
+-- Unlisted events are left out of lists and searches, invite only events are only seen by the
+-- people that were invited to them
+ALTER TABLE events
+    ADD COLUMN visibility VARCHAR NOT NULL DEFAULT 'public',
+    ADD CONSTRAINT visibility_valid CHECK (visibility IN ('public', 'unlisted', 'invite_only'));
+
+CREATE TABLE invitations (
+    event_id UUID NOT NULL REFERENCES events(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    invited_by UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    status VARCHAR NOT NULL DEFAULT 'pending',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    responded_at TIMESTAMPTZ,
+    PRIMARY KEY (event_id, user_id),
+    CONSTRAINT status_valid CHECK (status IN ('pending', 'accepted', 'declined'))
+);
+
+CREATE INDEX invitations_user_id_idx ON invitations (user_id);
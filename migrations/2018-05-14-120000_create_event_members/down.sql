@@ -0,0 +1 @@
+DROP TABLE event_members;
\ No newline at end of file
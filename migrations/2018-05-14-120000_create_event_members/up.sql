@@ -0,0 +1,13 @@
+-- The people with a role in an event, the owner of every event has an `owner` row
+CREATE TABLE event_members (
+    event_id UUID NOT NULL REFERENCES events(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    role VARCHAR NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (event_id, user_id),
+    CONSTRAINT role_valid CHECK (role IN ('owner', 'co_organizer', 'checkin_staff', 'attendee'))
+);
+
+CREATE INDEX event_members_user_id_idx ON event_members (user_id);
+
+INSERT INTO event_members (event_id, user_id, role) SELECT id, owner_id, 'owner' FROM events;
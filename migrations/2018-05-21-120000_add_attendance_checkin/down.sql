@@ -0,0 +1 @@
+ALTER TABLE attendances DROP COLUMN checked_in_by, DROP COLUMN checked_in_at;
\ No newline at end of file
@@ -0,0 +1,4 @@
+-- Set when the attendee's ticket is scanned at the door, a ticket can only be used once
+ALTER TABLE attendances
+    ADD COLUMN checked_in_at TIMESTAMPTZ,
+    ADD COLUMN checked_in_by UUID REFERENCES users(id) ON DELETE SET NULL;
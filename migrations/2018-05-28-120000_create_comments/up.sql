@@ -0,0 +1,16 @@
+-- The discussion on event pages, replies point at the comment they answer.  Deleted comments
+-- keep their row with an empty body so the replies under them stay in place
+CREATE TABLE comments (
+    id UUID PRIMARY KEY,
+    event_id UUID NOT NULL REFERENCES events(id) ON DELETE CASCADE,
+    parent_id UUID REFERENCES comments(id) ON DELETE CASCADE,
+    author_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    body TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    edited_at TIMESTAMPTZ,
+    deleted_at TIMESTAMPTZ,
+    deleted_by UUID REFERENCES users(id) ON DELETE SET NULL
+);
+
+CREATE INDEX comments_event_id_idx ON comments (event_id, created_at, id);
+CREATE INDEX comments_parent_id_idx ON comments (parent_id);
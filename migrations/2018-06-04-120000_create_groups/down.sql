@@ -0,0 +1,4 @@
+ALTER TABLE events DROP COLUMN group_id;
+DROP TABLE group_followers;
+DROP TABLE group_members;
+DROP TABLE groups;
\ No newline at end of file
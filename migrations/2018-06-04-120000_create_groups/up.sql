@@ -0,0 +1,33 @@
+-- The groups that run series of events, the admins manage the group and all of its events
+CREATE TABLE groups (
+    id UUID PRIMARY KEY,
+    name VARCHAR NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TABLE group_members (
+    group_id UUID NOT NULL REFERENCES groups(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    role VARCHAR NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (group_id, user_id),
+    CONSTRAINT role_valid CHECK (role IN ('admin', 'member'))
+);
+
+CREATE INDEX group_members_user_id_idx ON group_members (user_id);
+
+-- Following a group puts its upcoming events in the follower's feed
+CREATE TABLE group_followers (
+    group_id UUID NOT NULL REFERENCES groups(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (group_id, user_id)
+);
+
+CREATE INDEX group_followers_user_id_idx ON group_followers (user_id);
+
+-- Deleting a group leaves its events on their own
+ALTER TABLE events ADD COLUMN group_id UUID REFERENCES groups(id) ON DELETE SET NULL;
+
+CREATE INDEX events_group_id_idx ON events (group_id);
@@ -0,0 +1 @@
+DROP TABLE reminders_sent;
\ No newline at end of file
@@ -0,0 +1,11 @@
+-- The reminders that went out, one row per attendee, occurrence and kind of reminder.  The row is
+-- written before the reminder is sent so that it is only sent once.
+CREATE TABLE reminders_sent (
+    event_id UUID NOT NULL REFERENCES events(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    occurrence_start TIMESTAMPTZ NOT NULL,
+    kind VARCHAR NOT NULL,
+    sent_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (event_id, user_id, occurrence_start, kind),
+    CONSTRAINT kind_valid CHECK (kind IN ('1h', '24h'))
+);
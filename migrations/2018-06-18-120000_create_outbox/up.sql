@@ -0,0 +1,16 @@
+-- The messages that are waiting to go out.  They are written in the same transaction as the
+-- change that caused them and a worker delivers them, retrying the failures until they are dead.
+CREATE TABLE outbox (
+    id UUID PRIMARY KEY,
+    kind VARCHAR NOT NULL,
+    payload TEXT NOT NULL,
+    status VARCHAR NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    last_error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    sent_at TIMESTAMPTZ,
+    CONSTRAINT status_valid CHECK (status IN ('pending', 'sent', 'dead'))
+);
+
+CREATE INDEX outbox_pending_idx ON outbox (next_attempt_at, id) WHERE status = 'pending';
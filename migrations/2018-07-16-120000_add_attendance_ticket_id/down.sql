@@ -0,0 +1 @@
+ALTER TABLE attendances DROP COLUMN ticket_id;
\ No newline at end of file
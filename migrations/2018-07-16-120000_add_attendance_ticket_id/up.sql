@@ -0,0 +1,5 @@
+-- The id of the attendee's current ticket, a new one is issued whenever they take a spot so the
+-- tickets of an earlier RSVP stop working
+ALTER TABLE attendances ADD COLUMN ticket_id UUID;
+UPDATE attendances SET ticket_id = md5(random()::text || user_id::text || event_id::text)::uuid;
+ALTER TABLE attendances ALTER COLUMN ticket_id SET NOT NULL;
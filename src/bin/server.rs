@@ -1,6 +1,13 @@
 extern crate rs_events;
+use rs_events::config::Config;
 use rs_events::web;
+use std::process;
 
 fn main() {
-    web::run();
+    let config = Config::load().unwrap_or_else(|e| {
+        eprintln!("Invalid configuration: {}", e);
+        process::exit(1)
+    });
+
+    web::run(config);
 }
@@ -1,8 +1,14 @@
 extern crate rs_events;
+use rs_events::config::Config;
 use rs_events::web;
-use std::process::Command;
+use std::process::{self, Command};
 
 fn main() {
+    let config = Config::load().unwrap_or_else(|e| {
+        eprintln!("Invalid configuration: {}", e);
+        process::exit(1)
+    });
+
     // Run the migrations
     Command::new("diesel")
         .arg("setup")
@@ -15,5 +21,5 @@ fn main() {
         .expect("Unable to run migrations");
 
     // Then start the web server
-    web::run();
+    web::run(config);
 }
@@ -0,0 +1,471 @@
+//! The settings of the server
+//!
+//! They are read from an optional TOML file named by `CONFIG_FILE` and then from the
+//! environment, which is also loaded from a `.env` file.  An environment variable wins over the
+//! file.  A file looks like:
+//!
+//! ```toml
+//! bind_address = "0.0.0.0:8080"
+//! public_url = "https://events.example.com"
+//! secret_file = "/run/secrets/jwt"
+//! signing_key_file = "/run/secrets/jwt-2024.pem"
+//! verification_key_files = ["/run/secrets/jwt-2023.pem"]
+//! webhook_admins = ["7b5d1c4e-0d8a-4f51-9f5e-1d2b3c4d5e6f"]
+//!
+//! [database]
+//! url = "postgres://events@localhost/events"
+//! pool_size = 10
+//!
+//! [tokens]
+//! access_ttl = 3600
+//! refresh_ttl = 2592000
+//!
+//! [notifier]
+//! kind = "smtp"
+//! smtp_address = "localhost:25"
+//! smtp_from = "events@example.com"
+//! ```
+use chrono::Duration;
+use dotenv::dotenv;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::str::FromStr;
+#[cfg(test)]
+use token::keys::Algorithm;
+use token::keys::Key;
+use token::Lifetimes;
+use toml;
+use uuid::Uuid;
+
+/// The shortest secret that is accepted, HS256 needs a key at least as long as its hash
+pub const MIN_SECRET_LEN: usize = 32;
+
+/// errors that keep the server from starting
+#[derive(Debug, Fail)]
+pub enum ConfigError {
+    /// A file could not be read, the path and the reason
+    Unreadable(String, String),
+    InvalidFile(String),
+    /// A setting has a value that can't be used, the name and the reason
+    InvalidValue(String, String),
+    /// A required setting, by the name of its environment variable
+    Missing(&'static str),
+    MissingSecret,
+    WeakSecret,
+    /// A key file could not be used, the path and the reason
+    InvalidKey(String, String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConfigError::Unreadable(ref path, ref e) => write!(f, "could not read {}: {}", path, e),
+            ConfigError::InvalidFile(ref e) => write!(f, "invalid config file: {}", e),
+            ConfigError::InvalidValue(ref name, ref e) => write!(f, "invalid {}: {}", name, e),
+            ConfigError::Missing(name) => write!(f, "{} must be set", name),
+            ConfigError::MissingSecret => write!(
+                f,
+                "SECRET_KEY, SECRET_KEY_FILE or SIGNING_KEY_FILE must be set"
+            ),
+            ConfigError::WeakSecret => write!(
+                f,
+                "the secret key must be at least {} bytes of random data",
+                MIN_SECRET_LEN
+            ),
+            ConfigError::InvalidKey(ref path, ref e) => write!(f, "invalid key {}: {}", path, e),
+        }
+    }
+}
+
+/// The settings of the server
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// The `host:port` to listen on, `BIND_ADDRESS`
+    pub bind_address: String,
+    /// The URL the API is served at, it is used in links and as the issuer of the tokens,
+    /// `PUBLIC_URL`
+    pub public_url: String,
+    /// The key the tokens are signed with, `SECRET_KEY`
+    secret: Option<String>,
+    /// A file holding the key the tokens are signed with, `SECRET_KEY_FILE`
+    secret_file: Option<String>,
+    /// The resolved HMAC key, from `secret` or `secret_file`, it is empty when neither is set
+    #[serde(skip)]
+    pub secret_key: Vec<u8>,
+    /// A PEM file holding the RSA private key the tokens are signed with instead of the secret,
+    /// `SIGNING_KEY_FILE`
+    signing_key_file: Option<String>,
+    /// PEM files holding the old keys, which only check the tokens they signed before they were
+    /// rotated out, `VERIFICATION_KEY_FILES` as a comma separated list
+    verification_key_files: Vec<String>,
+    /// The resolved keys, from `signing_key_file` and `verification_key_files`
+    #[serde(skip)]
+    pub signing_key: Option<Key>,
+    #[serde(skip)]
+    pub verification_keys: Vec<Key>,
+    /// The users that may subscribe webhooks, `WEBHOOK_ADMINS` as a comma separated list
+    pub webhook_admins: Vec<Uuid>,
+    pub database: DatabaseConfig,
+    pub tokens: TokenConfig,
+    pub notifier: NotifierConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            bind_address: "0.0.0.0:8080".to_string(),
+            public_url: "http://localhost:8080".to_string(),
+            secret: None,
+            secret_file: None,
+            secret_key: Vec::new(),
+            signing_key_file: None,
+            verification_key_files: Vec::new(),
+            signing_key: None,
+            verification_keys: Vec::new(),
+            webhook_admins: Vec::new(),
+            database: DatabaseConfig::default(),
+            tokens: TokenConfig::default(),
+            notifier: NotifierConfig::default(),
+        }
+    }
+}
+
+/// the `[database]` section
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct DatabaseConfig {
+    /// `DATABASE_URL`
+    pub url: String,
+    /// The most connections the web server keeps open, `DATABASE_POOL_SIZE`
+    pub pool_size: usize,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        DatabaseConfig {
+            url: String::new(),
+            pool_size: 10,
+        }
+    }
+}
+
+/// the `[tokens]` section, the lifetimes are in seconds
+///
+/// The environment variables are `ACCESS_TOKEN_TTL`, `REFRESH_TOKEN_TTL`, `CONFIRM_TOKEN_TTL` and
+/// `TICKET_TTL`, feed tokens don't expire
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct TokenConfig {
+    pub access_ttl: i64,
+    pub refresh_ttl: i64,
+    pub confirm_ttl: i64,
+    pub ticket_ttl: i64,
+}
+
+impl TokenConfig {
+    pub fn lifetimes(&self) -> Lifetimes {
+        Lifetimes {
+            access: Duration::seconds(self.access_ttl),
+            refresh: Duration::seconds(self.refresh_ttl),
+            confirm: Duration::seconds(self.confirm_ttl),
+            ticket: Duration::seconds(self.ticket_ttl),
+        }
+    }
+}
+
+impl Default for TokenConfig {
+    fn default() -> Self {
+        let defaults = Lifetimes::default();
+        TokenConfig {
+            access_ttl: defaults.access.num_seconds(),
+            refresh_ttl: defaults.refresh.num_seconds(),
+            confirm_ttl: defaults.confirm.num_seconds(),
+            ticket_ttl: defaults.ticket.num_seconds(),
+        }
+    }
+}
+
+/// the `[notifier]` section
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct NotifierConfig {
+    /// `log`, `smtp` or `webhook`, `NOTIFIER`
+    pub kind: String,
+    /// The `host:port` of the SMTP server, `SMTP_ADDRESS`
+    pub smtp_address: String,
+    /// `SMTP_FROM`, required by the `smtp` notifier
+    pub smtp_from: Option<String>,
+    /// `SMTP_USERNAME` and `SMTP_PASSWORD`, for servers that need them
+    pub smtp_username: Option<String>,
+    pub smtp_password: Option<String>,
+    /// `NOTIFY_WEBHOOK_URL`, required by the `webhook` notifier
+    pub webhook_url: Option<String>,
+}
+
+impl Default for NotifierConfig {
+    fn default() -> Self {
+        NotifierConfig {
+            kind: "log".to_string(),
+            smtp_address: "localhost:25".to_string(),
+            smtp_from: None,
+            smtp_username: None,
+            smtp_password: None,
+            webhook_url: None,
+        }
+    }
+}
+
+impl Config {
+    /// read the settings from the `CONFIG_FILE` and the environment
+    pub fn load() -> Result<Config, ConfigError> {
+        dotenv().ok();
+        let file = match env::var("CONFIG_FILE") {
+            Ok(path) => Some(read_file(&path)?),
+            Err(_) => None,
+        };
+
+        Config::from_sources(file.as_deref(), |x| env::var(x).ok())
+    }
+
+    /// the settings from the contents of a TOML file and a lookup of environment variables
+    pub fn from_sources<F>(file: Option<&str>, var: F) -> Result<Config, ConfigError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let mut config: Config = match file {
+            Some(x) => toml::from_str(x).map_err(|e| ConfigError::InvalidFile(e.to_string()))?,
+            None => Config::default(),
+        };
+
+        override_with(&mut config.bind_address, &var, "BIND_ADDRESS")?;
+        override_with(&mut config.public_url, &var, "PUBLIC_URL")?;
+        override_option(&mut config.secret, &var, "SECRET_KEY");
+        override_option(&mut config.secret_file, &var, "SECRET_KEY_FILE");
+        override_option(&mut config.signing_key_file, &var, "SIGNING_KEY_FILE");
+        if let Some(x) = var("VERIFICATION_KEY_FILES") {
+            config.verification_key_files = x.split(',')
+                .map(str::trim)
+                .filter(|x| !x.is_empty())
+                .map(str::to_string)
+                .collect();
+        }
+        if let Some(x) = var("WEBHOOK_ADMINS") {
+            config.webhook_admins = x.split(',')
+                .map(str::trim)
+                .filter(|x| !x.is_empty())
+                .map(Uuid::from_str)
+                .collect::<Result<_, _>>()
+                .map_err(|e| ConfigError::InvalidValue("WEBHOOK_ADMINS".into(), e.to_string()))?;
+        }
+        override_with(&mut config.database.url, &var, "DATABASE_URL")?;
+        override_with(&mut config.database.pool_size, &var, "DATABASE_POOL_SIZE")?;
+        override_with(&mut config.tokens.access_ttl, &var, "ACCESS_TOKEN_TTL")?;
+        override_with(&mut config.tokens.refresh_ttl, &var, "REFRESH_TOKEN_TTL")?;
+        override_with(&mut config.tokens.confirm_ttl, &var, "CONFIRM_TOKEN_TTL")?;
+        override_with(&mut config.tokens.ticket_ttl, &var, "TICKET_TTL")?;
+        let notifier = &mut config.notifier;
+        override_with(&mut notifier.kind, &var, "NOTIFIER")?;
+        override_with(&mut notifier.smtp_address, &var, "SMTP_ADDRESS")?;
+        override_option(&mut notifier.smtp_from, &var, "SMTP_FROM");
+        override_option(&mut notifier.smtp_username, &var, "SMTP_USERNAME");
+        override_option(&mut notifier.smtp_password, &var, "SMTP_PASSWORD");
+        override_option(&mut notifier.webhook_url, &var, "NOTIFY_WEBHOOK_URL");
+
+        config.secret_key = match (config.secret.as_ref(), config.secret_file.as_ref()) {
+            (Some(x), _) => x.as_bytes().to_vec(),
+            (None, Some(path)) => read_file(path)?.trim_end().as_bytes().to_vec(),
+            (None, None) if config.signing_key_file.is_some() => Vec::new(),
+            (None, None) => return Err(ConfigError::MissingSecret),
+        };
+        if let Some(ref path) = config.signing_key_file {
+            let key = read_key(path)?;
+            if !key.can_sign() {
+                return Err(ConfigError::InvalidKey(
+                    path.clone(),
+                    "only private keys can sign tokens".to_string(),
+                ));
+            }
+            config.signing_key = Some(key);
+        }
+        config.verification_keys = config
+            .verification_key_files
+            .iter()
+            .map(|x| read_key(x))
+            .collect::<Result<_, _>>()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// the key that signs the tokens and the keys that only check them
+    ///
+    /// When there is a signing key the secret only checks the tokens it signed before.
+    pub fn token_keys(&self) -> (Key, Vec<Key>) {
+        let mut old = self.verification_keys.clone();
+        match self.signing_key {
+            Some(ref key) => {
+                if !self.secret_key.is_empty() {
+                    old.push(Key::hmac(&self.secret_key));
+                }
+                (key.clone(), old)
+            }
+            None => (Key::hmac(&self.secret_key), old),
+        }
+    }
+
+    // Internal
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if (self.signing_key.is_none() || !self.secret_key.is_empty())
+            && is_weak_secret(&self.secret_key)
+        {
+            return Err(ConfigError::WeakSecret);
+        }
+        if self.database.url.is_empty() {
+            return Err(ConfigError::Missing("DATABASE_URL"));
+        }
+        if self.database.pool_size == 0 {
+            return Err(invalid("DATABASE_POOL_SIZE", "it must be at least 1"));
+        }
+        let tokens = &self.tokens;
+        for &(name, ttl) in &[
+            ("ACCESS_TOKEN_TTL", tokens.access_ttl),
+            ("REFRESH_TOKEN_TTL", tokens.refresh_ttl),
+            ("CONFIRM_TOKEN_TTL", tokens.confirm_ttl),
+            ("TICKET_TTL", tokens.ticket_ttl),
+        ] {
+            if ttl <= 0 {
+                return Err(invalid(name, "it must be a positive number of seconds"));
+            }
+        }
+        match self.notifier.kind.as_str() {
+            "log" => Ok(()),
+            "smtp" if self.notifier.smtp_from.is_none() => Err(ConfigError::Missing("SMTP_FROM")),
+            "smtp" => Ok(()),
+            "webhook" if self.notifier.webhook_url.is_none() => {
+                Err(ConfigError::Missing("NOTIFY_WEBHOOK_URL"))
+            }
+            "webhook" => Ok(()),
+            x => Err(invalid("NOTIFIER", &format!("unknown notifier {}", x))),
+        }
+    }
+}
+#[test]
+fn test_from_sources() {
+    let secret = "a2Vg8Rz1YqP4mT7wXc0LbN5sJd3HfK6u";
+    let file = r#"
+        bind_address = "127.0.0.1:9000"
+        webhook_admins = ["7b5d1c4e-0d8a-4f51-9f5e-1d2b3c4d5e6f"]
+
+        [database]
+        url = "postgres://file"
+
+        [tokens]
+        access_ttl = 600
+    "#;
+    let env = |vars: &[(&str, &str)]| {
+        let vars = vars.iter()
+            .map(|&(k, v)| (k.to_string(), v.to_string()))
+            .collect::<Vec<_>>();
+        move |name: &str| vars.iter().find(|x| x.0 == name).map(|x| x.1.clone())
+    };
+
+    let config = Config::from_sources(
+        Some(file),
+        env(&[("SECRET_KEY", secret), ("DATABASE_URL", "postgres://env")]),
+    ).unwrap();
+    assert_eq!(config.bind_address, "127.0.0.1:9000");
+    assert_eq!(config.database.url, "postgres://env");
+    assert_eq!(config.database.pool_size, 10);
+    assert_eq!(config.tokens.lifetimes().access, Duration::minutes(10));
+    assert_eq!(config.secret_key, secret.as_bytes());
+    assert_eq!(config.webhook_admins.len(), 1);
+
+    let config = Config::from_sources(None, env(&[("DATABASE_URL", "postgres://env")]));
+    assert!(matches!(config, Err(ConfigError::MissingSecret)));
+    let config = Config::from_sources(
+        None,
+        env(&[("SECRET_KEY", "...."), ("DATABASE_URL", "postgres://env")]),
+    );
+    assert!(matches!(config, Err(ConfigError::WeakSecret)));
+    let config = Config::from_sources(
+        None,
+        env(&[("SECRET_KEY", secret), ("DATABASE_URL", "x"), ("NOTIFIER", "smtp")]),
+    );
+    assert!(matches!(config, Err(ConfigError::Missing("SMTP_FROM"))));
+
+    // A private key replaces the secret, a public key can only check tokens
+    let key = concat!(env!("CARGO_MANIFEST_DIR"), "/src/token/testdata/rsa.pem");
+    let config = Config::from_sources(
+        None,
+        env(&[("SIGNING_KEY_FILE", key), ("DATABASE_URL", "postgres://env")]),
+    ).unwrap();
+    let (signing, old) = config.token_keys();
+    assert_eq!(signing.alg, Algorithm::RS256);
+    assert!(old.is_empty());
+    let key = concat!(env!("CARGO_MANIFEST_DIR"), "/src/token/testdata/ec.pem");
+    let config = Config::from_sources(
+        None,
+        env(&[("SIGNING_KEY_FILE", key), ("DATABASE_URL", "postgres://env")]),
+    ).unwrap();
+    assert_eq!(config.token_keys().0.alg, Algorithm::ES256);
+    let key = concat!(env!("CARGO_MANIFEST_DIR"), "/src/token/testdata/rsa_public.pem");
+    let config = Config::from_sources(
+        None,
+        env(&[("SIGNING_KEY_FILE", key), ("DATABASE_URL", "postgres://env")]),
+    );
+    assert!(matches!(config, Err(ConfigError::InvalidKey(..))));
+}
+
+// Internal
+
+fn read_file(path: &str) -> Result<String, ConfigError> {
+    fs::read_to_string(path).map_err(|e| ConfigError::Unreadable(path.to_string(), e.to_string()))
+}
+
+fn read_key(path: &str) -> Result<Key, ConfigError> {
+    Key::from_pem(&read_file(path)?).map_err(|e| ConfigError::InvalidKey(path.to_string(), e.0))
+}
+
+fn invalid(name: &str, reason: &str) -> ConfigError {
+    ConfigError::InvalidValue(name.to_string(), reason.to_string())
+}
+
+/// replace a setting with the parsed value of an environment variable that is set
+fn override_with<T, F>(setting: &mut T, var: &F, name: &str) -> Result<(), ConfigError>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+    F: Fn(&str) -> Option<String>,
+{
+    if let Some(x) = var(name) {
+        *setting = x.parse().map_err(|e: T::Err| invalid(name, &e.to_string()))?;
+    }
+    Ok(())
+}
+
+fn override_option<F>(setting: &mut Option<String>, var: &F, name: &str)
+where
+    F: Fn(&str) -> Option<String>,
+{
+    if let Some(x) = var(name) {
+        *setting = Some(x);
+    }
+}
+
+/// true for a secret that is too short or that is mostly the same few characters, like `....`
+fn is_weak_secret(secret: &[u8]) -> bool {
+    let mut distinct = secret.to_vec();
+    distinct.sort();
+    distinct.dedup();
+
+    secret.len() < MIN_SECRET_LEN || distinct.len() < MIN_SECRET_LEN / 4
+}
+#[test]
+fn test_is_weak_secret() {
+    assert!(is_weak_secret(b"...."));
+    assert!(is_weak_secret(&[b'a'; 64]));
+    assert!(is_weak_secret(b"secretsecretsecretsecretsecretsecret"));
+    assert!(!is_weak_secret(b"a2Vg8Rz1YqP4mT7wXc0LbN5sJd3HfK6u"));
+}
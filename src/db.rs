@@ -1,12 +1,100 @@
 //! DB utils
 use diesel::pg::PgConnection;
-use dotenv::dotenv;
-use std::env;
 use diesel::prelude::*;
+use std::ops::Deref;
+use std::sync::{Condvar, Mutex};
 
-/// uses the `DATABASE_URL` env var to connect to a postgres DB
-pub fn connection() -> PgConnection {
-    dotenv().ok();
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    PgConnection::establish(&database_url).expect(&format!("Error connecting to {}", database_url))
+/// connects to a postgres DB, the errors are returned so the caller can try again later
+pub fn try_connection(database_url: &str) -> ConnectionResult<PgConnection> {
+    PgConnection::establish(database_url)
+}
+
+/// a pool of connections that are shared by the web server's threads
+///
+/// At most `size` connections are open at once, the threads that need one wait for one to be
+/// given back
+pub struct Pool {
+    database_url: String,
+    size: usize,
+    /// The connections that are not being used and the number that are open
+    state: Mutex<(Vec<PgConnection>, usize)>,
+    given_back: Condvar,
+}
+
+impl Pool {
+    pub fn new(database_url: &str, size: usize) -> Self {
+        Pool {
+            database_url: database_url.to_string(),
+            size,
+            state: Mutex::new((Vec::new(), 0)),
+            given_back: Condvar::new(),
+        }
+    }
+
+    /// take a connection, it is given back to the pool when it is dropped
+    pub fn get(&self) -> ConnectionResult<PooledConnection<'_>> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            if let Some(conn) = state.0.pop() {
+                // The DB may have closed it while it sat in the pool
+                if conn.execute("SELECT 1").is_ok() {
+                    return Ok(PooledConnection {
+                        pool: self,
+                        conn: Some(conn),
+                    });
+                }
+                state.1 -= 1;
+            } else if state.1 < self.size {
+                state.1 += 1;
+                drop(state);
+                return match try_connection(&self.database_url) {
+                    Ok(conn) => Ok(PooledConnection {
+                        pool: self,
+                        conn: Some(conn),
+                    }),
+                    Err(e) => {
+                        self.forget();
+                        Err(e)
+                    }
+                };
+            } else {
+                state = self.given_back
+                    .wait(state)
+                    .unwrap_or_else(|e| e.into_inner());
+            }
+        }
+    }
+
+    // Internal
+
+    /// make room for a new connection in place of one that was closed or never opened
+    fn forget(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.1 -= 1;
+        self.given_back.notify_one();
+    }
+}
+
+/// a connection that was taken from a `Pool`
+pub struct PooledConnection<'a> {
+    pool: &'a Pool,
+    conn: Option<PgConnection>,
+}
+
+impl<'a> Deref for PooledConnection<'a> {
+    type Target = PgConnection;
+
+    fn deref(&self) -> &PgConnection {
+        self.conn.as_ref().expect("the connection is only taken when it is dropped")
+    }
+}
+
+impl<'a> Drop for PooledConnection<'a> {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            let mut state = self.pool.state.lock().unwrap_or_else(|e| e.into_inner());
+            state.0.push(conn);
+            self.pool.given_back.notify_one();
+        }
+    }
 }
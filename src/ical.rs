@@ -0,0 +1,959 @@
+//! RFC 5545 iCalendar documents
+//!
+//! This writes the `VCALENDAR` and `VEVENT` components that calendar clients like Google
+//! Calendar and Outlook subscribe to, and reads the `VEVENT`s out of the calendars they export.
+use chrono::{Datelike, DateTime, Duration, FixedOffset, NaiveDate, NaiveDateTime, Offset, TimeZone,
+             Utc};
+use chrono_tz::{OffsetComponents, OffsetName, Tz};
+use recurrence::RRule;
+use std::fmt;
+use std::str::FromStr;
+
+/// The `PRODID` of every calendar we write
+const PRODID: &str = "-//rs-events//rs-events//EN";
+
+/// Content lines longer than this many octets are folded
+const MAX_LINE_OCTETS: usize = 75;
+
+/// How many years past the last event in a time zone its `VTIMEZONE` goes on, so that the later
+/// occurrences of recurring events still find their offsets
+const TIME_ZONE_YEARS_AHEAD: i32 = 10;
+
+/// A `VCALENDAR` component
+#[derive(Debug, PartialEq)]
+pub struct Calendar {
+    /// The display name of the calendar, written as `X-WR-CALNAME`
+    pub name: String,
+    pub events: Vec<VEvent>,
+}
+
+/// A `VEVENT` component
+#[derive(Debug, Clone, PartialEq)]
+pub struct VEvent {
+    pub uid: String,
+    pub dtstamp: DateTime<Utc>,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub summary: String,
+    pub description: String,
+    pub rrule: Option<String>,
+    pub exdates: Vec<DateTime<Utc>>,
+    /// Set when this component overrides one occurrence of a recurring `VEVENT` with the same `uid`
+    pub recurrence_id: Option<DateTime<Utc>>,
+    /// Set by `STATUS:CANCELLED`
+    pub cancelled: bool,
+    /// Set by `CLASS:PRIVATE` or `CLASS:CONFIDENTIAL`
+    pub private: bool,
+    /// The IANA time zone the dates are written in, so recurring events follow its DST changes
+    pub time_zone: Option<String>,
+}
+
+/// A `VTIMEZONE` component, the offsets of `tz` from the start of `from_year` to the end of
+/// `to_year`
+#[derive(Debug, PartialEq)]
+struct VTimeZone {
+    tz: Tz,
+    from_year: i32,
+    to_year: i32,
+}
+
+/// errors that make a whole document unreadable
+#[derive(Debug, PartialEq)]
+pub enum ParseError {
+    MissingCalendar,
+    UnbalancedComponent(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// a `VEVENT` that could not be read, the rest of the document is still usable
+#[derive(Debug, PartialEq)]
+pub struct InvalidEvent {
+    pub uid: Option<String>,
+    pub reason: String,
+}
+
+impl fmt::Display for Calendar {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_line(f, "BEGIN:VCALENDAR")?;
+        write_line(f, "VERSION:2.0")?;
+        write_line(f, &format!("PRODID:{}", PRODID))?;
+        write_line(f, "CALSCALE:GREGORIAN")?;
+        write_line(f, "METHOD:PUBLISH")?;
+        write_line(f, &format!("X-WR-CALNAME:{}", escape_text(&self.name)))?;
+        for time_zone in self.time_zones() {
+            write!(f, "{}", time_zone)?;
+        }
+        for event in &self.events {
+            write!(f, "{}", event)?;
+        }
+        write_line(f, "END:VCALENDAR")
+    }
+}
+
+impl Calendar {
+    /// the `VTIMEZONE`s of the `TZID`s the events are written in, which RFC 5545 requires
+    ///
+    /// See: [rfc-5545 section-3.2.19](https://tools.ietf.org/html/rfc5545#section-3.2.19)
+    fn time_zones(&self) -> Vec<VTimeZone> {
+        let mut time_zones: Vec<VTimeZone> = Vec::new();
+        for event in &self.events {
+            let tz = match event.tz() {
+                Some(tz) => tz,
+                None => continue,
+            };
+            let dates = Some(event.start).into_iter().chain(event.recurrence_id);
+            for year in dates.map(|x| x.with_timezone(&tz).year()) {
+                match time_zones.iter_mut().find(|x| x.tz == tz) {
+                    Some(time_zone) => {
+                        time_zone.from_year = time_zone.from_year.min(year);
+                        time_zone.to_year = time_zone.to_year.max(year + TIME_ZONE_YEARS_AHEAD);
+                    }
+                    None => time_zones.push(VTimeZone {
+                        tz,
+                        from_year: year,
+                        to_year: year + TIME_ZONE_YEARS_AHEAD,
+                    }),
+                }
+            }
+        }
+        time_zones
+    }
+}
+
+impl VEvent {
+    /// the time zone the dates are written in, `None` when they are written in UTC
+    fn tz(&self) -> Option<Tz> {
+        self.time_zone
+            .as_ref()
+            .and_then(|x| parse_tzid(x))
+            .filter(|x| *x != Tz::UTC)
+    }
+}
+
+impl fmt::Display for VTimeZone {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let start = self.start_of_year(self.from_year);
+        let end = self.start_of_year(self.to_year + 1);
+
+        write_line(f, "BEGIN:VTIMEZONE")?;
+        write_line(f, &format!("TZID:{}", self.tz.name()))?;
+        let mut offset = self.tz.offset_from_utc_datetime(&start.naive_utc());
+        write_observance(f, &start, &offset, &offset)?;
+        for change in offset_changes(self.tz, start, end) {
+            let next = self.tz.offset_from_utc_datetime(&change.naive_utc());
+            write_observance(f, &change, &offset, &next)?;
+            offset = next;
+        }
+        write_line(f, "END:VTIMEZONE")
+    }
+}
+
+impl VTimeZone {
+    /// the instant the year starts at in the time zone
+    fn start_of_year(&self, year: i32) -> DateTime<Utc> {
+        let midnight = NaiveDate::from_ymd(year, 1, 1).and_hms(0, 0, 0);
+        self.tz
+            .from_local_datetime(&midnight)
+            .earliest()
+            .map(|x| x.with_timezone(&Utc))
+            .unwrap_or_else(|| Utc.from_utc_datetime(&midnight))
+    }
+}
+
+/// writes the `STANDARD` or `DAYLIGHT` observance that starts `at` with the change from the
+/// `from` offset to the `to` offset
+fn write_observance(
+    f: &mut fmt::Formatter,
+    at: &DateTime<Utc>,
+    from: &<Tz as TimeZone>::Offset,
+    to: &<Tz as TimeZone>::Offset,
+) -> fmt::Result {
+    let kind = if to.dst_offset() == Duration::zero() {
+        "STANDARD"
+    } else {
+        "DAYLIGHT"
+    };
+    // The start is a local time in the offset that is being changed from
+    let start = at.naive_utc() + Duration::seconds(i64::from(from.fix().local_minus_utc()));
+
+    write_line(f, &format!("BEGIN:{}", kind))?;
+    write_line(f, &format!("DTSTART:{}", start.format("%Y%m%dT%H%M%S")))?;
+    write_line(f, &format!("TZOFFSETFROM:{}", format_utc_offset(&from.fix())))?;
+    write_line(f, &format!("TZOFFSETTO:{}", format_utc_offset(&to.fix())))?;
+    write_line(f, &format!("TZNAME:{}", escape_text(to.abbreviation())))?;
+    write_line(f, &format!("END:{}", kind))
+}
+
+/// the instants between `start` and `end` the offset of `tz` changes at
+///
+/// chrono-tz doesn't expose its transitions, so this looks for the days the offset changes on and
+/// bisects them down to the second.
+fn offset_changes(tz: Tz, start: DateTime<Utc>, end: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+    let offset = |x: DateTime<Utc>| {
+        let offset = tz.offset_from_utc_datetime(&x.naive_utc());
+        (
+            offset.fix(),
+            offset.dst_offset(),
+            offset.abbreviation().to_string(),
+        )
+    };
+
+    let mut changes = Vec::new();
+    let mut day = start;
+    let mut current = offset(day);
+    while day < end {
+        let next_day = day + Duration::days(1);
+        let next = offset(next_day);
+        if next != current {
+            let (mut before, mut after) = (day, next_day);
+            while after - before > Duration::seconds(1) {
+                let middle = before + (after - before) / 2;
+                if offset(middle) == current {
+                    before = middle;
+                } else {
+                    after = middle;
+                }
+            }
+            changes.push(after);
+        }
+        day = next_day;
+        current = next;
+    }
+    changes
+}
+
+/// formats a UTC-OFFSET value, like `-0500`
+fn format_utc_offset(offset: &FixedOffset) -> String {
+    let seconds = offset.local_minus_utc();
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let seconds = seconds.abs();
+    let mut formatted = format!("{}{:02}{:02}", sign, seconds / 3600, seconds / 60 % 60);
+    if seconds % 60 != 0 {
+        formatted.push_str(&format!("{:02}", seconds % 60));
+    }
+    formatted
+}
+
+impl fmt::Display for VEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let tz = self.tz();
+
+        write_line(f, "BEGIN:VEVENT")?;
+        write_line(f, &format!("UID:{}", escape_text(&self.uid)))?;
+        write_line(f, &format!("DTSTAMP:{}", format_date_time(&self.dtstamp)))?;
+        if let Some(ref recurrence_id) = self.recurrence_id {
+            write_line(f, &date_property("RECURRENCE-ID", &[*recurrence_id], tz))?;
+        }
+        write_line(f, &date_property("DTSTART", &[self.start], tz))?;
+        write_line(f, &date_property("DTEND", &[self.end], tz))?;
+        if let Some(ref rrule) = self.rrule {
+            write_line(f, &format!("RRULE:{}", rrule))?;
+        }
+        if !self.exdates.is_empty() {
+            write_line(f, &date_property("EXDATE", &self.exdates, tz))?;
+        }
+        if self.cancelled {
+            write_line(f, "STATUS:CANCELLED")?;
+        }
+        if self.private {
+            write_line(f, "CLASS:PRIVATE")?;
+        }
+        write_line(f, &format!("SUMMARY:{}", escape_text(&self.summary)))?;
+        if !self.description.is_empty() {
+            write_line(
+                f,
+                &format!("DESCRIPTION:{}", escape_text(&self.description)),
+            )?;
+        }
+        write_line(f, "END:VEVENT")
+    }
+}
+#[test]
+fn test_calendar_display() {
+    use chrono::TimeZone;
+
+    let calendar = Calendar {
+        name: "Rust Meetups".into(),
+        events: vec![VEvent {
+            uid: "42@rs-events".into(),
+            dtstamp: Utc.ymd(2018, 3, 1).and_hms(12, 0, 0),
+            start: Utc.ymd(2018, 3, 5).and_hms(18, 0, 0),
+            end: Utc.ymd(2018, 3, 5).and_hms(20, 0, 0),
+            summary: "Rust Meetup; monthly".into(),
+            description: "".into(),
+            rrule: Some("FREQ=MONTHLY;BYDAY=1MO".into()),
+            exdates: vec![
+                Utc.ymd(2018, 4, 2).and_hms(18, 0, 0),
+                Utc.ymd(2018, 5, 7).and_hms(18, 0, 0),
+            ],
+            recurrence_id: None,
+            cancelled: false,
+            private: false,
+            time_zone: None,
+        }],
+    };
+
+    assert_eq!(
+        calendar.to_string(),
+        [
+            "BEGIN:VCALENDAR",
+            "VERSION:2.0",
+            "PRODID:-//rs-events//rs-events//EN",
+            "CALSCALE:GREGORIAN",
+            "METHOD:PUBLISH",
+            "X-WR-CALNAME:Rust Meetups",
+            "BEGIN:VEVENT",
+            "UID:42@rs-events",
+            "DTSTAMP:20180301T120000Z",
+            "DTSTART:20180305T180000Z",
+            "DTEND:20180305T200000Z",
+            "RRULE:FREQ=MONTHLY;BYDAY=1MO",
+            "EXDATE:20180402T180000Z,20180507T180000Z",
+            "SUMMARY:Rust Meetup\\; monthly",
+            "END:VEVENT",
+            "END:VCALENDAR",
+            "",
+        ].join("\r\n")
+    );
+}
+#[test]
+fn test_vevent_display_time_zone() {
+    let event = VEvent {
+        uid: "42@rs-events".into(),
+        dtstamp: Utc.ymd(2018, 3, 1).and_hms(12, 0, 0),
+        start: Utc.ymd(2018, 3, 5).and_hms(14, 0, 0),
+        end: Utc.ymd(2018, 3, 5).and_hms(15, 0, 0),
+        summary: "Stand up".into(),
+        description: "".into(),
+        rrule: Some("FREQ=WEEKLY".into()),
+        exdates: vec![Utc.ymd(2018, 3, 12).and_hms(13, 0, 0)],
+        recurrence_id: None,
+        cancelled: false,
+        private: true,
+        time_zone: Some("America/New_York".into()),
+    };
+
+    assert_eq!(
+        event.to_string(),
+        [
+            "BEGIN:VEVENT",
+            "UID:42@rs-events",
+            "DTSTAMP:20180301T120000Z",
+            "DTSTART;TZID=America/New_York:20180305T090000",
+            "DTEND;TZID=America/New_York:20180305T100000",
+            "RRULE:FREQ=WEEKLY",
+            "EXDATE;TZID=America/New_York:20180312T090000",
+            "CLASS:PRIVATE",
+            "SUMMARY:Stand up",
+            "END:VEVENT",
+            "",
+        ].join("\r\n")
+    );
+}
+#[test]
+fn test_calendar_display_time_zones() {
+    let event = VEvent {
+        uid: "42@rs-events".into(),
+        dtstamp: Utc.ymd(2018, 3, 1).and_hms(12, 0, 0),
+        start: Utc.ymd(2018, 3, 5).and_hms(14, 0, 0),
+        end: Utc.ymd(2018, 3, 5).and_hms(15, 0, 0),
+        summary: "Stand up".into(),
+        description: "".into(),
+        rrule: Some("FREQ=WEEKLY".into()),
+        exdates: vec![],
+        recurrence_id: None,
+        cancelled: false,
+        private: false,
+        time_zone: Some("America/New_York".into()),
+    };
+    let calendar = Calendar {
+        name: "Stand ups".into(),
+        events: vec![
+            VEvent {
+                uid: "43@rs-events".into(),
+                start: Utc.ymd(2016, 6, 6).and_hms(13, 0, 0),
+                end: Utc.ymd(2016, 6, 6).and_hms(14, 0, 0),
+                rrule: None,
+                ..event.clone()
+            },
+            VEvent {
+                uid: "44@rs-events".into(),
+                time_zone: Some("UTC".into()),
+                ..event.clone()
+            },
+            event,
+        ],
+    };
+
+    assert_eq!(
+        calendar.time_zones(),
+        vec![VTimeZone {
+            tz: Tz::America__New_York,
+            from_year: 2016,
+            to_year: 2018 + TIME_ZONE_YEARS_AHEAD,
+        }]
+    );
+    let text = calendar.to_string();
+    assert_eq!(text.matches("BEGIN:VTIMEZONE").count(), 1);
+    assert!(text.find("END:VTIMEZONE") < text.find("BEGIN:VEVENT"));
+}
+#[test]
+fn test_vtimezone_display() {
+    let time_zone = VTimeZone {
+        tz: Tz::America__New_York,
+        from_year: 2018,
+        to_year: 2018,
+    };
+
+    assert_eq!(
+        time_zone.to_string(),
+        [
+            "BEGIN:VTIMEZONE",
+            "TZID:America/New_York",
+            "BEGIN:STANDARD",
+            "DTSTART:20180101T000000",
+            "TZOFFSETFROM:-0500",
+            "TZOFFSETTO:-0500",
+            "TZNAME:EST",
+            "END:STANDARD",
+            "BEGIN:DAYLIGHT",
+            "DTSTART:20180311T020000",
+            "TZOFFSETFROM:-0500",
+            "TZOFFSETTO:-0400",
+            "TZNAME:EDT",
+            "END:DAYLIGHT",
+            "BEGIN:STANDARD",
+            "DTSTART:20181104T020000",
+            "TZOFFSETFROM:-0400",
+            "TZOFFSETTO:-0500",
+            "TZNAME:EST",
+            "END:STANDARD",
+            "END:VTIMEZONE",
+            "",
+        ].join("\r\n")
+    );
+
+    let time_zone = VTimeZone {
+        tz: Tz::Asia__Kolkata,
+        from_year: 2018,
+        to_year: 2020,
+    };
+    assert_eq!(
+        time_zone.to_string(),
+        [
+            "BEGIN:VTIMEZONE",
+            "TZID:Asia/Kolkata",
+            "BEGIN:STANDARD",
+            "DTSTART:20180101T000000",
+            "TZOFFSETFROM:+0530",
+            "TZOFFSETTO:+0530",
+            "TZNAME:IST",
+            "END:STANDARD",
+            "END:VTIMEZONE",
+            "",
+        ].join("\r\n")
+    );
+}
+
+/// reads the `VEVENT`s out of an iCalendar document
+///
+/// Times are converted to UTC using their `TZID`, which must be an IANA time zone name.  Floating
+/// times and dates are treated as UTC.
+pub fn parse_events(text: &str) -> Result<Vec<Result<VEvent, InvalidEvent>>, ParseError> {
+    let mut stack: Vec<String> = vec![];
+    let mut found_calendar = false;
+    let mut properties = vec![];
+    let mut events = vec![];
+
+    for line in unfold(text) {
+        let line = match parse_content_line(&line) {
+            Some(x) => x,
+            None => continue,
+        };
+        match line.name.as_str() {
+            "BEGIN" => {
+                let component = line.value.to_uppercase();
+                if component == "VCALENDAR" {
+                    found_calendar = true;
+                } else if component == "VEVENT" {
+                    properties.clear();
+                }
+                stack.push(component);
+            }
+            "END" => {
+                let component = line.value.to_uppercase();
+                if stack.pop().as_ref() != Some(&component) {
+                    return Err(ParseError::UnbalancedComponent(component));
+                }
+                if component == "VEVENT" {
+                    events.push(vevent_from_properties(&properties));
+                }
+            }
+            // Properties of components inside of a VEVENT, like VALARM, are not collected
+            _ => if stack.last().map(String::as_str) == Some("VEVENT") {
+                properties.push(line);
+            },
+        }
+    }
+
+    if let Some(component) = stack.pop() {
+        return Err(ParseError::UnbalancedComponent(component));
+    }
+    if !found_calendar {
+        return Err(ParseError::MissingCalendar);
+    }
+    Ok(events)
+}
+#[test]
+fn test_parse_events() {
+    let text = [
+        "BEGIN:VCALENDAR",
+        "VERSION:2.0",
+        "BEGIN:VTIMEZONE",
+        "TZID:America/New_York",
+        "END:VTIMEZONE",
+        "BEGIN:VEVENT",
+        "UID:weekly@example.com",
+        "DTSTAMP:20180301T120000Z",
+        "DTSTART;TZID=America/New_York:20180305T090000",
+        "DURATION:PT1H30M",
+        "RRULE:FREQ=WEEKLY;BYDAY=MO",
+        "EXDATE;TZID=America/New_York:20180312T090000,20180319T090000",
+        "SUMMARY:Stand\\, up\\; with a very long summary that has to be folded by the cale",
+        " ndar client",
+        "DESCRIPTION:line one\\nline two",
+        "CLASS:CONFIDENTIAL",
+        "BEGIN:VALARM",
+        "DESCRIPTION:not the event description",
+        "END:VALARM",
+        "END:VEVENT",
+        "BEGIN:VEVENT",
+        "UID:weekly@example.com",
+        "RECURRENCE-ID;TZID=America/New_York:20180326T090000",
+        "DTSTART;VALUE=DATE:20180326",
+        "STATUS:CANCELLED",
+        "END:VEVENT",
+        "BEGIN:VEVENT",
+        "UID:broken@example.com",
+        "DTSTART;TZID=Mars/Olympus_Mons:20180305T090000",
+        "END:VEVENT",
+        "END:VCALENDAR",
+    ].join("\r\n");
+
+    let events = parse_events(&text).unwrap();
+    assert_eq!(events.len(), 3);
+
+    let weekly = events[0].as_ref().unwrap();
+    assert_eq!(weekly.uid, "weekly@example.com");
+    assert_eq!(weekly.time_zone, Some("America/New_York".into()));
+    assert_eq!(weekly.start, Utc.ymd(2018, 3, 5).and_hms(14, 0, 0));
+    assert_eq!(weekly.end, Utc.ymd(2018, 3, 5).and_hms(15, 30, 0));
+    assert_eq!(weekly.rrule, Some("FREQ=WEEKLY;BYDAY=MO".into()));
+    // Daylight saving time started on March 11th
+    assert_eq!(
+        weekly.exdates,
+        vec![
+            Utc.ymd(2018, 3, 12).and_hms(13, 0, 0),
+            Utc.ymd(2018, 3, 19).and_hms(13, 0, 0),
+        ]
+    );
+    assert_eq!(
+        weekly.summary,
+        "Stand, up; with a very long summary that has to be folded by the calendar client"
+    );
+    assert_eq!(weekly.description, "line one\nline two");
+    assert!(!weekly.cancelled);
+    assert!(weekly.private);
+
+    let cancelled = events[1].as_ref().unwrap();
+    assert_eq!(
+        cancelled.recurrence_id,
+        Some(Utc.ymd(2018, 3, 26).and_hms(13, 0, 0))
+    );
+    assert_eq!(cancelled.start, Utc.ymd(2018, 3, 26).and_hms(0, 0, 0));
+    assert_eq!(cancelled.end, Utc.ymd(2018, 3, 27).and_hms(0, 0, 0));
+    assert!(cancelled.cancelled);
+    assert!(!cancelled.private);
+
+    assert_eq!(
+        events[2],
+        Err(InvalidEvent {
+            uid: Some("broken@example.com".into()),
+            reason: "unknown time zone Mars/Olympus_Mons".into(),
+        })
+    );
+
+    assert_eq!(
+        parse_events("BEGIN:VEVENT\r\nEND:VEVENT\r\n").unwrap_err(),
+        ParseError::MissingCalendar
+    );
+    assert_eq!(
+        parse_events("BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nEND:VCALENDAR").unwrap_err(),
+        ParseError::UnbalancedComponent("VCALENDAR".into())
+    );
+}
+
+// Internal
+
+/// A property of a component, like `DTSTART;TZID=America/New_York:20180305T090000`
+#[derive(Debug, PartialEq)]
+struct ContentLine {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl ContentLine {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|x| x.0 == name)
+            .map(|x| x.1.as_str())
+    }
+}
+
+/// joins folded lines back together
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    for line in text.split('\n') {
+        let line = line.trim_end_matches('\r');
+        if line.starts_with(' ') || line.starts_with('\t') {
+            if let Some(last) = lines.last_mut() {
+                last.push_str(&line[1..]);
+                continue;
+            }
+        }
+        if !line.is_empty() {
+            lines.push(line.into());
+        }
+    }
+    lines
+}
+
+fn parse_content_line(line: &str) -> Option<ContentLine> {
+    let mut parts = vec![String::new()];
+    let mut in_quotes = false;
+    let mut value_start = None;
+
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ';' if !in_quotes => parts.push(String::new()),
+            ':' if !in_quotes => {
+                value_start = Some(i + 1);
+                break;
+            }
+            c => parts.last_mut()?.push(c),
+        }
+    }
+
+    let value = line[value_start?..].to_string();
+    let name = parts.remove(0).to_uppercase();
+    let params = parts
+        .into_iter()
+        .filter_map(|x| {
+            let mut kv = x.splitn(2, '=');
+            let key = kv.next()?.to_uppercase();
+            Some((key, kv.next()?.to_string()))
+        })
+        .collect();
+
+    Some(ContentLine {
+        name,
+        params,
+        value,
+    })
+}
+#[test]
+fn test_parse_content_line() {
+    assert_eq!(
+        parse_content_line("DTSTART;TZID=\"America/New_York\";VALUE=DATE-TIME:20180305T090000"),
+        Some(ContentLine {
+            name: "DTSTART".into(),
+            params: vec![
+                ("TZID".into(), "America/New_York".into()),
+                ("VALUE".into(), "DATE-TIME".into()),
+            ],
+            value: "20180305T090000".into(),
+        })
+    );
+    assert_eq!(
+        parse_content_line("DESCRIPTION:see: the notes"),
+        Some(ContentLine {
+            name: "DESCRIPTION".into(),
+            params: vec![],
+            value: "see: the notes".into(),
+        })
+    );
+    assert_eq!(parse_content_line("not a property"), None);
+}
+
+fn vevent_from_properties(properties: &[ContentLine]) -> Result<VEvent, InvalidEvent> {
+    let find = |name: &str| properties.iter().find(|x| x.name == name);
+    let uid = find("UID").map(|x| unescape_text(&x.value));
+    let invalid = |reason: String| InvalidEvent {
+        uid: uid.clone(),
+        reason,
+    };
+
+    let dtstart = find("DTSTART").ok_or_else(|| invalid("missing DTSTART".into()))?;
+    let (start, is_date) = parse_date_property(dtstart).map_err(&invalid)?;
+    let end = match (find("DTEND"), find("DURATION")) {
+        (Some(dtend), _) => parse_date_property(dtend).map_err(&invalid)?.0,
+        (None, Some(duration)) => {
+            start + parse_duration(&duration.value)
+                .ok_or_else(|| invalid(format!("invalid DURATION {}", duration.value)))?
+        }
+        (None, None) if is_date => start + Duration::days(1),
+        (None, None) => start,
+    };
+
+    let rrule = match find("RRULE") {
+        Some(rrule) => Some(RRule::from_str(&rrule.value)
+            .map_err(|_| invalid(format!("invalid RRULE {}", rrule.value)))?
+            .to_string()),
+        None => None,
+    };
+
+    let mut exdates = vec![];
+    for exdate in properties.iter().filter(|x| x.name == "EXDATE") {
+        for value in exdate.value.split(',') {
+            exdates.push(parse_date_value(value, exdate.param("TZID"), exdate.param("VALUE"))
+                .map_err(&invalid)?);
+        }
+    }
+
+    let recurrence_id = match find("RECURRENCE-ID") {
+        Some(x) => Some(parse_date_property(x).map_err(&invalid)?.0),
+        None => None,
+    };
+    let dtstamp = match find("DTSTAMP") {
+        Some(x) => parse_date_property(x).map_err(&invalid)?.0,
+        None => Utc::now(),
+    };
+
+    Ok(VEvent {
+        uid: uid.clone().ok_or_else(|| invalid("missing UID".into()))?,
+        dtstamp,
+        start,
+        end,
+        summary: find("SUMMARY")
+            .map(|x| unescape_text(&x.value))
+            .unwrap_or_default(),
+        description: find("DESCRIPTION")
+            .map(|x| unescape_text(&x.value))
+            .unwrap_or_default(),
+        rrule,
+        exdates,
+        recurrence_id,
+        cancelled: find("STATUS").is_some_and(|x| x.value.eq_ignore_ascii_case("CANCELLED")),
+        private: find("CLASS").is_some_and(|x| {
+            x.value.eq_ignore_ascii_case("PRIVATE") || x.value.eq_ignore_ascii_case("CONFIDENTIAL")
+        }),
+        time_zone: dtstart
+            .param("TZID")
+            .and_then(parse_tzid)
+            .map(|x| x.name().to_string()),
+    })
+}
+
+/// parses a DATE or DATE-TIME property, the flag is true for a DATE
+fn parse_date_property(property: &ContentLine) -> Result<(DateTime<Utc>, bool), String> {
+    let is_date = property.param("VALUE") == Some("DATE") || property.value.len() == 8;
+    let date = parse_date_value(
+        &property.value,
+        property.param("TZID"),
+        property.param("VALUE"),
+    )?;
+    Ok((date, is_date))
+}
+
+fn parse_date_value(value: &str, tzid: Option<&str>, kind: Option<&str>) -> Result<DateTime<Utc>, String> {
+    let invalid = || format!("invalid date {}", value);
+    let value = value.trim();
+
+    if value.ends_with('Z') {
+        return NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%SZ")
+            .map(|x| DateTime::from_utc(x, Utc))
+            .map_err(|_| invalid());
+    }
+    let local = if kind == Some("DATE") || value.len() == 8 {
+        NaiveDate::parse_from_str(value, "%Y%m%d").map(|x| x.and_hms(0, 0, 0))
+    } else {
+        NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+    }.map_err(|_| invalid())?;
+
+    match tzid {
+        Some(tzid) => {
+            let tz = parse_tzid(tzid).ok_or_else(|| format!("unknown time zone {}", tzid))?;
+            tz.from_local_datetime(&local)
+                .earliest()
+                .map(|x| x.with_timezone(&Utc))
+                .ok_or_else(invalid)
+        }
+        None => Ok(DateTime::from_utc(local, Utc)),
+    }
+}
+
+/// parses a DURATION value, like `PT1H30M` or `-P1W`
+///
+/// See: [rfc-5545 section-3.3.6](https://tools.ietf.org/html/rfc5545#section-3.3.6)
+fn parse_duration(value: &str) -> Option<Duration> {
+    let (sign, value) = match value.chars().next()? {
+        '-' => (-1, &value[1..]),
+        '+' => (1, &value[1..]),
+        _ => (1, value),
+    };
+    if !value.starts_with('P') {
+        return None;
+    }
+
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    let mut in_time = false;
+    for c in value[1..].chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => in_time = true,
+            unit => {
+                let n: i64 = number.parse().ok()?;
+                number.clear();
+                total = total + match (unit, in_time) {
+                    ('W', false) => Duration::weeks(n),
+                    ('D', false) => Duration::days(n),
+                    ('H', true) => Duration::hours(n),
+                    ('M', true) => Duration::minutes(n),
+                    ('S', true) => Duration::seconds(n),
+                    _ => return None,
+                };
+            }
+        }
+    }
+    if number.is_empty() {
+        Some(total * sign)
+    } else {
+        None
+    }
+}
+#[test]
+fn test_parse_duration() {
+    assert_eq!(parse_duration("PT1H30M"), Some(Duration::minutes(90)));
+    assert_eq!(parse_duration("P1DT12H"), Some(Duration::hours(36)));
+    assert_eq!(parse_duration("-P2W"), Some(Duration::weeks(-2)));
+    assert_eq!(parse_duration("P1H"), None);
+    assert_eq!(parse_duration("1H"), None);
+}
+
+/// undoes `escape_text`
+fn unescape_text(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') | Some('N') => unescaped.push('\n'),
+                Some(c) => unescaped.push(c),
+                None => unescaped.push('\\'),
+            },
+            c => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+#[test]
+fn test_unescape_text() {
+    let text = "a\\b;c,d\ne";
+    assert_eq!(unescape_text(&escape_text(text)), text);
+}
+
+/// parses a `TZID`, which must be an IANA time zone name
+fn parse_tzid(tzid: &str) -> Option<Tz> {
+    // Some clients put a leading slash on the names from the Olson database
+    Tz::from_str(tzid.trim_start_matches('/')).ok()
+}
+
+/// formats a DATE-TIME property, on the wall clock of `tz` when there is one
+fn date_property(name: &str, dates: &[DateTime<Utc>], tz: Option<Tz>) -> String {
+    match tz {
+        Some(tz) => {
+            let values: Vec<String> = dates
+                .iter()
+                .map(|x| x.with_timezone(&tz).format("%Y%m%dT%H%M%S").to_string())
+                .collect();
+            format!("{};TZID={}:{}", name, tz.name(), values.join(","))
+        }
+        None => {
+            let values: Vec<String> = dates.iter().map(format_date_time).collect();
+            format!("{}:{}", name, values.join(","))
+        }
+    }
+}
+
+/// formats a UTC date-time value, like `19980119T070000Z`
+fn format_date_time(date: &DateTime<Utc>) -> String {
+    date.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// escapes a TEXT value
+///
+/// See: [rfc-5545 section-3.3.11](https://tools.ietf.org/html/rfc5545#section-3.3.11)
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => (),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+#[test]
+fn test_escape_text() {
+    assert_eq!(
+        escape_text("a\\b;c,d\r\ne"),
+        "a\\\\b\\;c\\,d\\ne".to_string()
+    );
+}
+
+/// writes a content line, folding it so no line is longer than 75 octets
+///
+/// See: [rfc-5545 section-3.1](https://tools.ietf.org/html/rfc5545#section-3.1)
+fn write_line(f: &mut fmt::Formatter, line: &str) -> fmt::Result {
+    let mut octets = 0;
+    for c in line.chars() {
+        if octets + c.len_utf8() > MAX_LINE_OCTETS {
+            f.write_str("\r\n ")?;
+            // The leading space of a continuation line counts against its length
+            octets = 1;
+        }
+        octets += c.len_utf8();
+        write!(f, "{}", c)?;
+    }
+    f.write_str("\r\n")
+}
+#[test]
+fn test_write_line_folds() {
+    struct Line(String);
+    impl fmt::Display for Line {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write_line(f, &self.0)
+        }
+    }
+
+    let folded = Line(format!("SUMMARY:{}", "é".repeat(40))).to_string();
+    let lines: Vec<&str> = folded.trim_end_matches("\r\n").split("\r\n").collect();
+
+    assert_eq!(lines.len(), 2);
+    assert!(lines.iter().all(|x| x.len() <= MAX_LINE_OCTETS));
+    assert!(lines[1].starts_with(' '));
+    assert_eq!(lines.concat().replacen(" ", "", 1).len(), "SUMMARY:".len() + 80);
+}
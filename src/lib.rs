@@ -7,16 +7,31 @@ extern crate rouille;
 #[macro_use]
 extern crate serde_derive;
 
+extern crate base64;
 extern crate chrono;
+extern crate chrono_tz;
 extern crate crypto;
 extern crate dotenv;
 extern crate jsonwebtoken;
 extern crate libpasta;
+extern crate pem;
+extern crate ring;
 extern crate serde;
+extern crate serde_json;
+extern crate simple_asn1;
+extern crate toml;
+extern crate ureq;
+extern crate url;
 extern crate uuid;
 
+pub mod config;
 pub mod services;
 pub mod models;
 pub mod schema;
 pub mod db;
+pub mod ical;
+pub mod notifier;
+pub mod recurrence;
+pub mod scheduler;
+pub mod token;
 pub mod web;
@@ -0,0 +1,163 @@
+//! Diesel model for the Attendance table
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use models::event::Event;
+use models::outbox::Outgoing;
+use models::page::{Page, Paged};
+use models::user::User;
+use schema::attendances;
+use std::fmt;
+use std::str::FromStr;
+use uuid::Uuid;
+
+//# Modules
+
+pub mod pg;
+
+//# Enums
+
+/// The answer a user gave when they RSVP'd to an event
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RsvpStatus {
+    Going,
+    Maybe,
+    NotGoing,
+}
+
+impl RsvpStatus {
+    /// the value that is stored in the `status` column
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            RsvpStatus::Going => "going",
+            RsvpStatus::Maybe => "maybe",
+            RsvpStatus::NotGoing => "not_going",
+        }
+    }
+}
+
+impl FromStr for RsvpStatus {
+    type Err = InvalidRsvpStatus;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "going" => Ok(RsvpStatus::Going),
+            "maybe" => Ok(RsvpStatus::Maybe),
+            "not_going" => Ok(RsvpStatus::NotGoing),
+            _ => Err(InvalidRsvpStatus),
+        }
+    }
+}
+#[test]
+fn test_rsvp_status_from_str() {
+    for status in &[RsvpStatus::Going, RsvpStatus::Maybe, RsvpStatus::NotGoing] {
+        assert_eq!(RsvpStatus::from_str(status.as_str()).unwrap(), *status);
+    }
+    assert_eq!(RsvpStatus::from_str("yes").unwrap_err(), InvalidRsvpStatus);
+}
+
+/// returned when a string is not one of the `RsvpStatus` values
+#[derive(Debug, PartialEq)]
+pub struct InvalidRsvpStatus;
+
+impl fmt::Display for InvalidRsvpStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid rsvp status")
+    }
+}
+
+//# Structs
+
+/// `NewAttendance` is the struct that is used for storing a user's RSVP
+#[derive(Insertable)]
+#[table_name = "attendances"]
+pub struct NewAttendance<'a> {
+    pub user_id: &'a Uuid,
+    pub event_id: &'a Uuid,
+    pub status: &'a str,
+}
+
+/// Attendance is the struct that represents an Attendance record
+#[derive(Queryable)]
+pub struct Attendance {
+    pub user_id: Uuid,
+    pub event_id: Uuid,
+    pub status: String,
+    pub updated_at: DateTime<Utc>,
+    /// Set when the user is going but the event was full, this orders the waitlist
+    pub waitlisted_at: Option<DateTime<Utc>>,
+    /// Set when the user's ticket was scanned at the door
+    pub checked_in_at: Option<DateTime<Utc>>,
+    /// The staff member that scanned the ticket
+    pub checked_in_by: Option<Uuid>,
+    /// The `jti` of the current ticket, it changes whenever the user takes a spot
+    pub ticket_id: Uuid,
+}
+
+impl Attendance {
+    /// the parsed `status` column
+    pub fn rsvp_status(&self) -> RsvpStatus {
+        // The status_valid constraint keeps anything else out of the table
+        RsvpStatus::from_str(&self.status).unwrap_or(RsvpStatus::NotGoing)
+    }
+
+    /// true when the user is going and is not waiting on the waitlist
+    pub fn has_spot(&self) -> bool {
+        self.rsvp_status() == RsvpStatus::Going && self.waitlisted_at.is_none()
+    }
+}
+
+/// The key of the attendee list, the `waitlisted_at`, `updated_at` and user id of an attendance
+pub type AttendeeKey = (Option<DateTime<Utc>>, DateTime<Utc>, Uuid);
+
+//# Traits
+
+/// This trait is the IO interface
+pub trait IOModel {
+    /// Find a user's RSVP for an event
+    fn find(&self, user_id: &Uuid, event_id: &Uuid) -> QueryResult<Option<Attendance>>;
+
+    /// Create or replace a user's RSVP for an event
+    ///
+    /// A `going` RSVP for an event that is at its `max_attendees` is put on the waitlist, and
+    /// giving up a spot promotes the first waitlisted user.  Taking a spot issues a new
+    /// `ticket_id`, so the tickets of an earlier spot stop working.  The messages that `publish` gives for
+    /// the RSVP are put in the outbox when it is stored.
+    fn rsvp<F>(&self, new_attendance: &NewAttendance, publish: F) -> QueryResult<Attendance>
+    where
+        F: FnOnce(&Attendance) -> Vec<Outgoing>;
+
+    /// Remove a user's RSVP for an event, promoting the first waitlisted user into a freed spot
+    ///
+    /// The messages that `publish` gives for the removed RSVP are put in the outbox
+    fn cancel<F>(&self, user_id: &Uuid, event_id: &Uuid, publish: F) -> QueryResult<usize>
+    where
+        F: FnOnce(&Attendance) -> Vec<Outgoing>;
+
+    /// List everyone that RSVP'd to an event, along with their user record
+    ///
+    /// The people with a spot come first, followed by the waitlist in order.  A `status` only
+    /// lists the people with that RSVP.
+    fn attendees(
+        &self,
+        event_id: &Uuid,
+        status: Option<RsvpStatus>,
+        page: &Page<AttendeeKey>,
+    ) -> QueryResult<Paged<(Attendance, User), AttendeeKey>>;
+
+    /// Check a user in to an event with the ticket of their attendance, `None` when they don't
+    /// have a spot, the ticket was replaced or they are already checked in
+    fn check_in(
+        &self,
+        user_id: &Uuid,
+        event_id: &Uuid,
+        ticket_id: &Uuid,
+        checked_in_by: &Uuid,
+    ) -> QueryResult<Option<Attendance>>;
+
+    /// List the people that are going to an event and have a spot
+    fn confirmed(&self, event_id: &Uuid) -> QueryResult<Vec<User>>;
+
+    /// List the events a user is going or maybe going to, ordered by their start date
+    fn events_for_user(&self, user_id: &Uuid) -> QueryResult<Vec<(Attendance, Event)>>;
+}
@@ -0,0 +1,268 @@
+//! implements an `IOModel` for Postgres
+use super::{Attendance, AttendeeKey, IOModel, NewAttendance, RsvpStatus};
+use chrono::{DateTime, Utc};
+use diesel;
+use diesel::prelude::*;
+use models::event::Event;
+use models::outbox::Outgoing;
+use models::outbox::pg::enqueue;
+use models::page::{after_date_id, Page, Paged};
+use models::user::User;
+use schema::{events, users};
+use uuid::Uuid;
+
+pub struct PgModel<'a> {
+    conn: &'a PgConnection,
+}
+impl<'a> PgModel<'a> {
+    pub fn new(conn: &'a PgConnection) -> Self {
+        PgModel { conn }
+    }
+}
+impl<'a> IOModel for PgModel<'a> {
+    fn find(&self, a_user_id: &Uuid, an_event_id: &Uuid) -> QueryResult<Option<Attendance>> {
+        use schema::attendances::dsl::*;
+
+        attendances
+            .find((a_user_id, an_event_id))
+            .get_result(self.conn)
+            .optional()
+    }
+
+    fn rsvp<F>(&self, new_attendance: &NewAttendance, publish: F) -> QueryResult<Attendance>
+    where
+        F: FnOnce(&Attendance) -> Vec<Outgoing>,
+    {
+        use schema::attendances::dsl::*;
+
+        self.conn.transaction(|| {
+            // Locking the event serializes the RSVPs for it so two people can't take the last spot
+            let capacity = lock_event(self.conn, new_attendance.event_id)?;
+            let existing = attendances
+                .find((new_attendance.user_id, new_attendance.event_id))
+                .get_result::<Attendance>(self.conn)
+                .optional()?;
+
+            let now = Utc::now();
+            let waitlisted = if new_attendance.status != RsvpStatus::Going.as_str() {
+                None
+            } else {
+                match existing {
+                    // Keep their spot, or their place in line
+                    Some(ref x) if x.rsvp_status() == RsvpStatus::Going => x.waitlisted_at,
+                    _ => if is_full(self.conn, new_attendance.event_id, capacity)? {
+                        Some(now)
+                    } else {
+                        None
+                    },
+                }
+            };
+
+            let ticket = match existing {
+                Some(ref x) if x.has_spot() => x.ticket_id,
+                _ => Uuid::new_v4(),
+            };
+
+            let attendance = diesel::insert_into(attendances)
+                .values((new_attendance, waitlisted_at.eq(waitlisted), ticket_id.eq(ticket)))
+                .on_conflict((user_id, event_id))
+                .do_update()
+                .set((
+                    status.eq(new_attendance.status),
+                    updated_at.eq(now),
+                    waitlisted_at.eq(waitlisted),
+                    ticket_id.eq(ticket),
+                ))
+                .get_result::<Attendance>(self.conn)?;
+
+            let had_spot = existing.as_ref().map(Attendance::has_spot) == Some(true);
+            if had_spot && !attendance.has_spot() {
+                promote_waitlisted(self.conn, new_attendance.event_id)?;
+            }
+            enqueue(self.conn, &publish(&attendance))?;
+            Ok(attendance)
+        })
+    }
+
+    fn cancel<F>(&self, a_user_id: &Uuid, an_event_id: &Uuid, publish: F) -> QueryResult<usize>
+    where
+        F: FnOnce(&Attendance) -> Vec<Outgoing>,
+    {
+        use schema::attendances::dsl::*;
+
+        self.conn.transaction(|| {
+            lock_event(self.conn, an_event_id)?;
+            let removed = diesel::delete(
+                attendances
+                    .filter(user_id.eq(a_user_id))
+                    .filter(event_id.eq(an_event_id)),
+            ).get_results::<Attendance>(self.conn)?;
+
+            if removed.iter().any(Attendance::has_spot) {
+                promote_waitlisted(self.conn, an_event_id)?;
+            }
+            if let Some(attendance) = removed.first() {
+                enqueue(self.conn, &publish(attendance))?;
+            }
+            Ok(removed.len())
+        })
+    }
+
+    fn attendees(
+        &self,
+        an_event_id: &Uuid,
+        a_status: Option<RsvpStatus>,
+        page: &Page<AttendeeKey>,
+    ) -> QueryResult<Paged<(Attendance, User), AttendeeKey>> {
+        use schema::attendances::dsl::*;
+
+        let mut query = attendances
+            .inner_join(users::table)
+            .filter(event_id.eq(an_event_id))
+            .order((
+                waitlisted_at.is_not_null(),
+                waitlisted_at.asc(),
+                updated_at.asc(),
+                user_id.asc(),
+            ))
+            .limit(page.load_limit())
+            .into_boxed();
+        if let Some(a_status) = a_status {
+            query = query.filter(status.eq(a_status.as_str()));
+        }
+        match page.after {
+            // The people with a spot have no `waitlisted_at` and come before the waitlist
+            Some((None, ref an_updated_at, ref a_user_id)) => {
+                query = query.filter(waitlisted_at.is_not_null().or(waitlisted_at
+                    .is_null()
+                    .and(after_date_id(updated_at, user_id, &(*an_updated_at, *a_user_id)))));
+            }
+            Some((Some(ref a_waitlisted_at), ref an_updated_at, ref a_user_id)) => {
+                query = query.filter(waitlisted_at.gt(a_waitlisted_at).or(waitlisted_at
+                    .eq(a_waitlisted_at)
+                    .and(after_date_id(updated_at, user_id, &(*an_updated_at, *a_user_id)))));
+            }
+            None => (),
+        }
+
+        let rows = query.load(self.conn)?;
+        Ok(page.paged(rows, |x: &(Attendance, User)| {
+            (x.0.waitlisted_at, x.0.updated_at, x.0.user_id)
+        }))
+    }
+
+    fn check_in(
+        &self,
+        a_user_id: &Uuid,
+        an_event_id: &Uuid,
+        a_ticket_id: &Uuid,
+        a_checked_in_by: &Uuid,
+    ) -> QueryResult<Option<Attendance>> {
+        use schema::attendances::dsl::*;
+
+        // The conditions make a second scan of the same ticket update nothing
+        diesel::update(
+            attendances
+                .filter(user_id.eq(a_user_id))
+                .filter(event_id.eq(an_event_id))
+                .filter(ticket_id.eq(a_ticket_id))
+                .filter(status.eq(RsvpStatus::Going.as_str()))
+                .filter(waitlisted_at.is_null())
+                .filter(checked_in_at.is_null()),
+        ).set((
+            checked_in_at.eq(Utc::now()),
+            checked_in_by.eq(a_checked_in_by),
+        ))
+            .get_result(self.conn)
+            .optional()
+    }
+
+    fn confirmed(&self, an_event_id: &Uuid) -> QueryResult<Vec<User>> {
+        use schema::attendances::dsl::*;
+
+        attendances
+            .inner_join(users::table)
+            .select(users::all_columns)
+            .filter(event_id.eq(an_event_id))
+            .filter(status.eq(RsvpStatus::Going.as_str()))
+            .filter(waitlisted_at.is_null())
+            .order(user_id.asc())
+            .load(self.conn)
+    }
+
+    fn events_for_user(&self, a_user_id: &Uuid) -> QueryResult<Vec<(Attendance, Event)>> {
+        use schema::attendances::dsl::*;
+
+        attendances
+            .inner_join(events::table)
+            .filter(user_id.eq(a_user_id))
+            .filter(status.ne(RsvpStatus::NotGoing.as_str()))
+            .order((events::start_date.asc(), events::id.asc()))
+            .load(self.conn)
+    }
+}
+
+/// moves the first people on an event's waitlist into its open spots
+///
+/// This must be called inside of a transaction, it returns the number of promoted users
+pub fn promote_waitlisted(conn: &PgConnection, an_event_id: &Uuid) -> QueryResult<usize> {
+    use schema::attendances::dsl::*;
+
+    let capacity = lock_event(conn, an_event_id)?;
+    let mut waitlist = attendances
+        .select(user_id)
+        .filter(event_id.eq(an_event_id))
+        .filter(status.eq(RsvpStatus::Going.as_str()))
+        .filter(waitlisted_at.is_not_null())
+        .order((waitlisted_at.asc(), user_id.asc()))
+        .into_boxed();
+
+    if let Some(capacity) = capacity {
+        let open_spots = i64::from(capacity) - confirmed_count(conn, an_event_id)?;
+        if open_spots <= 0 {
+            return Ok(0);
+        }
+        waitlist = waitlist.limit(open_spots);
+    }
+    let promoted: Vec<Uuid> = waitlist.load(conn)?;
+
+    diesel::update(
+        attendances
+            .filter(event_id.eq(an_event_id))
+            .filter(user_id.eq_any(promoted)),
+    ).set(waitlisted_at.eq(None::<DateTime<Utc>>))
+        .execute(conn)
+}
+
+// Internal
+
+/// locks the event's row until the end of the transaction and returns its `max_attendees`
+fn lock_event(conn: &PgConnection, event_id: &Uuid) -> QueryResult<Option<i32>> {
+    events::table
+        .select(events::max_attendees)
+        .filter(events::id.eq(event_id))
+        .for_update()
+        .get_result::<Option<i32>>(conn)
+        .optional()
+        .map(Option::flatten)
+}
+
+/// the number of people that are going to an event and are not on the waitlist
+fn confirmed_count(conn: &PgConnection, an_event_id: &Uuid) -> QueryResult<i64> {
+    use diesel::dsl::count_star;
+    use schema::attendances::dsl::*;
+
+    attendances
+        .select(count_star())
+        .filter(event_id.eq(an_event_id))
+        .filter(status.eq(RsvpStatus::Going.as_str()))
+        .filter(waitlisted_at.is_null())
+        .get_result(conn)
+}
+
+fn is_full(conn: &PgConnection, event_id: &Uuid, capacity: Option<i32>) -> QueryResult<bool> {
+    match capacity {
+        Some(capacity) => Ok(confirmed_count(conn, event_id)? >= i64::from(capacity)),
+        None => Ok(false),
+    }
+}
@@ -0,0 +1,49 @@
+//! Diesel model for the CalendarFeed table
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use models::page::{DateIdKey, Page, Paged};
+use schema::calendar_feeds;
+use uuid::Uuid;
+
+//# Modules
+
+pub mod pg;
+
+//# Structs
+
+/// `NewCalendarFeed` is the struct that is used for storing a new feed
+#[derive(Insertable)]
+#[table_name = "calendar_feeds"]
+pub struct NewCalendarFeed<'a> {
+    pub id: &'a Uuid,
+    pub user_id: &'a Uuid,
+}
+
+/// CalendarFeed is the struct that represents a CalendarFeed record
+#[derive(Queryable)]
+pub struct CalendarFeed {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+//# Traits
+
+/// This trait is the IO interface
+pub trait IOModel {
+    /// Find a feed that has not been revoked
+    fn find(&self, feed_id: &Uuid) -> QueryResult<Option<CalendarFeed>>;
+
+    /// List a user's feeds, the oldest first
+    fn list(
+        &self,
+        user_id: &Uuid,
+        page: &Page<DateIdKey>,
+    ) -> QueryResult<Paged<CalendarFeed, DateIdKey>>;
+
+    /// Create a new feed
+    fn create(&self, new_feed: &NewCalendarFeed) -> QueryResult<CalendarFeed>;
+
+    /// Revoke one of a user's feeds
+    fn revoke(&self, user_id: &Uuid, feed_id: &Uuid) -> QueryResult<usize>;
+}
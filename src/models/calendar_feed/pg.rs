@@ -0,0 +1,63 @@
+//! implements an `IOModel` for Postgres
+use super::{CalendarFeed, IOModel, NewCalendarFeed};
+use diesel;
+use diesel::prelude::*;
+use models::page::{after_date_id, DateIdKey, Page, Paged};
+use uuid::Uuid;
+
+pub struct PgModel<'a> {
+    conn: &'a PgConnection,
+}
+impl<'a> PgModel<'a> {
+    pub fn new(conn: &'a PgConnection) -> Self {
+        PgModel { conn }
+    }
+}
+impl<'a> IOModel for PgModel<'a> {
+    fn find(&self, feed_id: &Uuid) -> QueryResult<Option<CalendarFeed>> {
+        use schema::calendar_feeds::dsl::*;
+
+        calendar_feeds
+            .filter(id.eq(feed_id))
+            .get_result(self.conn)
+            .optional()
+    }
+
+    fn list(
+        &self,
+        a_user_id: &Uuid,
+        page: &Page<DateIdKey>,
+    ) -> QueryResult<Paged<CalendarFeed, DateIdKey>> {
+        use schema::calendar_feeds::dsl::*;
+
+        let mut query = calendar_feeds
+            .filter(user_id.eq(a_user_id))
+            .order((created_at.asc(), id.asc()))
+            .limit(page.load_limit())
+            .into_boxed();
+        if let Some(ref key) = page.after {
+            query = query.filter(after_date_id(created_at, id, key));
+        }
+
+        let rows = query.load(self.conn)?;
+        Ok(page.paged(rows, |x: &CalendarFeed| (x.created_at, x.id)))
+    }
+
+    fn create(&self, new_feed: &NewCalendarFeed) -> QueryResult<CalendarFeed> {
+        use schema::calendar_feeds::dsl::*;
+
+        diesel::insert_into(calendar_feeds)
+            .values(new_feed)
+            .get_result(self.conn)
+    }
+
+    fn revoke(&self, a_user_id: &Uuid, feed_id: &Uuid) -> QueryResult<usize> {
+        use schema::calendar_feeds::dsl::*;
+
+        diesel::delete(
+            calendar_feeds
+                .filter(id.eq(feed_id))
+                .filter(user_id.eq(a_user_id)),
+        ).execute(self.conn)
+    }
+}
@@ -0,0 +1,71 @@
+//! Diesel model for the Comment table
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use models::page::{DateIdKey, Page, Paged};
+use schema::comments;
+use uuid::Uuid;
+
+//# Modules
+
+pub mod pg;
+
+//# Structs
+
+/// `NewComment` is the struct that is used for storing a new comment
+#[derive(Insertable)]
+#[table_name = "comments"]
+pub struct NewComment<'a> {
+    pub id: &'a Uuid,
+    pub event_id: &'a Uuid,
+    pub parent_id: Option<&'a Uuid>,
+    pub author_id: &'a Uuid,
+    pub body: &'a str,
+}
+
+/// Comment is the struct that represents a Comment record
+#[derive(Queryable)]
+pub struct Comment {
+    pub id: Uuid,
+    pub event_id: Uuid,
+    /// The comment this is a reply to, `None` for the comments that start a thread
+    pub parent_id: Option<Uuid>,
+    pub author_id: Uuid,
+    /// The body is emptied when the comment is deleted
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+    pub edited_at: Option<DateTime<Utc>>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// The author or the organizer that deleted the comment
+    pub deleted_by: Option<Uuid>,
+}
+
+impl Comment {
+    /// true when the comment was deleted, it is kept to hold its replies in place
+    pub fn is_deleted(&self) -> bool {
+        self.deleted_at.is_some()
+    }
+}
+
+//# Traits
+
+/// This trait is the IO interface
+pub trait IOModel {
+    /// Find a comment
+    fn find(&self, comment_id: &Uuid) -> QueryResult<Option<Comment>>;
+
+    /// List the comments on an event, the oldest first so that replies come after their parents
+    fn list(
+        &self,
+        event_id: &Uuid,
+        page: &Page<DateIdKey>,
+    ) -> QueryResult<Paged<Comment, DateIdKey>>;
+
+    /// Create a new comment
+    fn create(&self, new_comment: &NewComment) -> QueryResult<Comment>;
+
+    /// Change the body of a comment that was not deleted
+    fn edit(&self, comment_id: &Uuid, body: &str) -> QueryResult<Option<Comment>>;
+
+    /// Delete a comment, leaving its row in place with an empty body
+    fn delete(&self, comment_id: &Uuid, deleted_by: &Uuid) -> QueryResult<Option<Comment>>;
+}
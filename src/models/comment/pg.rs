@@ -0,0 +1,82 @@
+//! implements an `IOModel` for Postgres
+use super::{Comment, IOModel, NewComment};
+use chrono::Utc;
+use diesel;
+use diesel::prelude::*;
+use models::page::{after_date_id, DateIdKey, Page, Paged};
+use uuid::Uuid;
+
+pub struct PgModel<'a> {
+    conn: &'a PgConnection,
+}
+impl<'a> PgModel<'a> {
+    pub fn new(conn: &'a PgConnection) -> Self {
+        PgModel { conn }
+    }
+}
+impl<'a> IOModel for PgModel<'a> {
+    fn find(&self, comment_id: &Uuid) -> QueryResult<Option<Comment>> {
+        use schema::comments::dsl::*;
+
+        comments
+            .filter(id.eq(comment_id))
+            .get_result(self.conn)
+            .optional()
+    }
+
+    fn list(
+        &self,
+        an_event_id: &Uuid,
+        page: &Page<DateIdKey>,
+    ) -> QueryResult<Paged<Comment, DateIdKey>> {
+        use schema::comments::dsl::*;
+
+        let mut query = comments
+            .filter(event_id.eq(an_event_id))
+            .order((created_at.asc(), id.asc()))
+            .limit(page.load_limit())
+            .into_boxed();
+        if let Some(ref key) = page.after {
+            query = query.filter(after_date_id(created_at, id, key));
+        }
+
+        let rows = query.load(self.conn)?;
+        Ok(page.paged(rows, |x: &Comment| (x.created_at, x.id)))
+    }
+
+    fn create(&self, new_comment: &NewComment) -> QueryResult<Comment> {
+        use schema::comments::dsl::*;
+
+        diesel::insert_into(comments)
+            .values(new_comment)
+            .get_result(self.conn)
+    }
+
+    fn edit(&self, comment_id: &Uuid, a_body: &str) -> QueryResult<Option<Comment>> {
+        use schema::comments::dsl::*;
+
+        diesel::update(
+            comments
+                .filter(id.eq(comment_id))
+                .filter(deleted_at.is_null()),
+        ).set((body.eq(a_body), edited_at.eq(Utc::now())))
+            .get_result(self.conn)
+            .optional()
+    }
+
+    fn delete(&self, comment_id: &Uuid, a_deleted_by: &Uuid) -> QueryResult<Option<Comment>> {
+        use schema::comments::dsl::*;
+
+        diesel::update(
+            comments
+                .filter(id.eq(comment_id))
+                .filter(deleted_at.is_null()),
+        ).set((
+            body.eq(""),
+            deleted_at.eq(Utc::now()),
+            deleted_by.eq(a_deleted_by),
+        ))
+            .get_result(self.conn)
+            .optional()
+    }
+}
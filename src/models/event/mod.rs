@@ -0,0 +1,449 @@
+//! Diesel model for the Event table
+use chrono::{DateTime, NaiveDateTime, Utc};
+use diesel;
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Float4, Text};
+use models::outbox::Outgoing;
+use models::page::{DateIdKey, Page, Paged};
+use schema::{event_occurrences, events};
+use std::fmt;
+use std::str::FromStr;
+use uuid::Uuid;
+
+//# Modules
+
+pub mod pg;
+
+//# Enums
+
+/// Who can see an event
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Visibility {
+    /// Everyone can see the event and it is listed
+    #[default]
+    Public,
+    /// Everyone with a link can see the event but it is not listed
+    Unlisted,
+    /// Only the members of the event and the people invited to it can see it
+    InviteOnly,
+}
+
+impl Visibility {
+    /// the value that is stored in the `visibility` column
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Visibility::Public => "public",
+            Visibility::Unlisted => "unlisted",
+            Visibility::InviteOnly => "invite_only",
+        }
+    }
+}
+
+impl FromStr for Visibility {
+    type Err = InvalidVisibility;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "public" => Ok(Visibility::Public),
+            "unlisted" => Ok(Visibility::Unlisted),
+            "invite_only" => Ok(Visibility::InviteOnly),
+            _ => Err(InvalidVisibility),
+        }
+    }
+}
+#[test]
+fn test_visibility_from_str() {
+    for visibility in &[
+        Visibility::Public,
+        Visibility::Unlisted,
+        Visibility::InviteOnly,
+    ] {
+        assert_eq!(Visibility::from_str(visibility.as_str()).unwrap(), *visibility);
+    }
+    assert_eq!(Visibility::from_str("private").unwrap_err(), InvalidVisibility);
+}
+
+/// returned when a string is not one of the `Visibility` values
+#[derive(Debug, PartialEq)]
+pub struct InvalidVisibility;
+
+impl fmt::Display for InvalidVisibility {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid visibility")
+    }
+}
+
+/// How the tags of a filter are combined
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TagMatch {
+    /// Matches the events that have every one of the tags
+    #[default]
+    All,
+    /// Matches the events that have at least one of the tags
+    Any,
+}
+
+impl TagMatch {
+    /// the value that is used in query strings
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            TagMatch::All => "all",
+            TagMatch::Any => "any",
+        }
+    }
+}
+
+impl FromStr for TagMatch {
+    type Err = InvalidTagMatch;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "all" => Ok(TagMatch::All),
+            "any" => Ok(TagMatch::Any),
+            _ => Err(InvalidTagMatch),
+        }
+    }
+}
+#[test]
+fn test_tag_match_from_str() {
+    for tag_match in &[TagMatch::All, TagMatch::Any] {
+        assert_eq!(TagMatch::from_str(tag_match.as_str()).unwrap(), *tag_match);
+    }
+    assert_eq!(TagMatch::from_str("none").unwrap_err(), InvalidTagMatch);
+}
+
+/// returned when a string is not one of the `TagMatch` values
+#[derive(Debug, PartialEq)]
+pub struct InvalidTagMatch;
+
+impl fmt::Display for InvalidTagMatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid tag match")
+    }
+}
+
+//# Structs
+
+/// `NewEvent` is the struct that is used for storing a new event
+#[derive(Insertable)]
+#[table_name = "events"]
+pub struct NewEvent<'a> {
+    pub id: &'a Uuid,
+    pub owner_id: &'a Uuid,
+    pub name: &'a str,
+    pub description: &'a str,
+    pub start_date: &'a DateTime<Utc>,
+    pub end_date: &'a DateTime<Utc>,
+    pub max_attendees: Option<i32>,
+    pub rrule: Option<&'a str>,
+    pub exdates: &'a [DateTime<Utc>],
+    pub uid: Option<&'a str>,
+    pub time_zone: &'a str,
+    pub local_start_date: &'a NaiveDateTime,
+    pub local_end_date: &'a NaiveDateTime,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub tags: &'a [String],
+    pub venue_id: Option<&'a Uuid>,
+    pub visibility: &'a str,
+    pub group_id: Option<&'a Uuid>,
+}
+
+/// `EventChanges` holds the fields of an event that can be updated, `None` fields are left alone
+#[derive(AsChangeset, Default)]
+#[table_name = "events"]
+pub struct EventChanges<'a> {
+    pub name: Option<&'a str>,
+    pub description: Option<&'a str>,
+    pub start_date: Option<&'a DateTime<Utc>>,
+    pub end_date: Option<&'a DateTime<Utc>>,
+    /// `Some(None)` removes the limit
+    pub max_attendees: Option<Option<i32>>,
+    /// `Some(None)` stops the event from repeating
+    pub rrule: Option<Option<&'a str>>,
+    pub exdates: Option<&'a [DateTime<Utc>]>,
+    pub time_zone: Option<&'a str>,
+    pub local_start_date: Option<&'a NaiveDateTime>,
+    pub local_end_date: Option<&'a NaiveDateTime>,
+    /// `Some(None)` removes the location
+    pub latitude: Option<Option<f64>>,
+    pub longitude: Option<Option<f64>>,
+    pub tags: Option<&'a [String]>,
+    /// `Some(None)` takes the event out of its venue
+    pub venue_id: Option<Option<&'a Uuid>>,
+    pub visibility: Option<&'a str>,
+    /// `Some(None)` takes the event out of its group
+    pub group_id: Option<Option<&'a Uuid>>,
+}
+
+/// Event is the struct that repesents an Event record
+#[derive(Queryable, QueryableByName, Clone)]
+#[table_name = "events"]
+pub struct Event {
+    pub id: Uuid,
+    pub owner_id: Uuid,
+    pub name: String,
+    pub description: String,
+    pub start_date: DateTime<Utc>,
+    pub end_date: DateTime<Utc>,
+    /// When set, RSVPs past this many people are put on the waitlist
+    pub max_attendees: Option<i32>,
+    /// The RFC 5545 RRULE that repeats the event, `start_date` and `end_date` are the first occurrence
+    pub rrule: Option<String>,
+    /// The starts of the occurrences that the RRULE skips
+    pub exdates: Vec<DateTime<Utc>>,
+    /// The iCalendar UID of an imported event
+    pub uid: Option<String>,
+    /// The IANA time zone of the event, recurring events repeat on its wall clock
+    pub time_zone: String,
+    /// `start_date` on the wall clock of `time_zone`
+    pub local_start_date: NaiveDateTime,
+    /// `end_date` on the wall clock of `time_zone`
+    pub local_end_date: NaiveDateTime,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub tags: Vec<String>,
+    /// The venue the event is at, its location is used when the event doesn't have one
+    pub venue_id: Option<Uuid>,
+    pub visibility: String,
+    /// The group that runs the event, its admins manage the event
+    pub group_id: Option<Uuid>,
+}
+
+impl Event {
+    /// the parsed `visibility` column
+    pub fn visibility(&self) -> Visibility {
+        // The visibility_valid constraint keeps anything else out of the table
+        Visibility::from_str(&self.visibility).unwrap_or(Visibility::InviteOnly)
+    }
+}
+
+/// a public event in UTC on 2018-03-05 from 18:00 to 20:00 that repeats by `rrule`, tests change
+/// the rest of it with struct update syntax
+#[cfg(test)]
+pub(crate) fn test_event(rrule: &str) -> Event {
+    use chrono::{NaiveDate, TimeZone};
+
+    Event {
+        id: Uuid::new_v4(),
+        owner_id: Uuid::new_v4(),
+        name: "Meetup".into(),
+        description: "".into(),
+        start_date: Utc.ymd(2018, 3, 5).and_hms(18, 0, 0),
+        end_date: Utc.ymd(2018, 3, 5).and_hms(20, 0, 0),
+        max_attendees: None,
+        rrule: Some(rrule.into()),
+        exdates: vec![],
+        uid: None,
+        time_zone: "UTC".into(),
+        local_start_date: NaiveDate::from_ymd(2018, 3, 5).and_hms(18, 0, 0),
+        local_end_date: NaiveDate::from_ymd(2018, 3, 5).and_hms(20, 0, 0),
+        latitude: None,
+        longitude: None,
+        tags: vec![],
+        venue_id: None,
+        visibility: "public".into(),
+        group_id: None,
+    }
+}
+
+/// `SearchHit` is an event found by a search and how well it matched the text
+#[derive(QueryableByName)]
+pub struct SearchHit {
+    #[diesel(embed)]
+    pub event: Event,
+    #[sql_type = "Float4"]
+    pub rank: f32,
+}
+
+/// `TagCount` is a tag and the number of events that have it
+#[derive(QueryableByName, Debug, PartialEq)]
+pub struct TagCount {
+    #[sql_type = "Text"]
+    pub tag: String,
+    #[sql_type = "BigInt"]
+    pub count: i64,
+}
+
+/// The key of the search results, they are ordered by their rank then start date then id
+pub type SearchKey = (f32, DateTime<Utc>, Uuid);
+
+/// `EventQuery` holds the filters of an event search, `None` filters match every event
+#[derive(Default)]
+pub struct EventQuery<'a> {
+    /// Full text search over the name and description
+    pub text: Option<&'a str>,
+    /// Matches the events that end after this, and the recurring events
+    pub from: Option<&'a DateTime<Utc>>,
+    /// Matches the events that start before this
+    pub to: Option<&'a DateTime<Utc>>,
+    /// Matches the events within `radius_km` of a latitude and longitude, an event without a
+    /// location of its own is where its venue is
+    pub near: Option<(f64, f64)>,
+    pub radius_km: Option<f64>,
+    /// Matches the events with the tags, they are combined by `tag_match`
+    pub tags: &'a [String],
+    pub tag_match: TagMatch,
+    /// Only matches the events this user may see listed, the public ones when `None`
+    pub viewer: Option<&'a Uuid>,
+    /// Never matches these events
+    pub excluded: &'a [Uuid],
+}
+
+/// `OccurrenceOverride` changes or cancels a single occurrence of a recurring event
+///
+/// The `None` fields are taken from the event
+#[derive(Queryable, Insertable, AsChangeset, PartialEq)]
+#[table_name = "event_occurrences"]
+#[primary_key(event_id, original_start_date)]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct OccurrenceOverride {
+    pub event_id: Uuid,
+    /// The start of the occurrence that the RRULE gives
+    pub original_start_date: DateTime<Utc>,
+    pub cancelled: bool,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub start_date: Option<DateTime<Utc>>,
+    pub end_date: Option<DateTime<Utc>>,
+}
+
+impl<'a> EventChanges<'a> {
+    /// true when there is nothing to change
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none() && self.description.is_none() && self.start_date.is_none()
+            && self.end_date.is_none() && self.max_attendees.is_none() && self.rrule.is_none()
+            && self.exdates.is_none() && self.time_zone.is_none()
+            && self.local_start_date.is_none() && self.local_end_date.is_none()
+            && self.latitude.is_none() && self.longitude.is_none() && self.tags.is_none()
+            && self.venue_id.is_none() && self.visibility.is_none() && self.group_id.is_none()
+    }
+}
+#[test]
+fn test_event_changes_is_empty() {
+    use chrono::NaiveDate;
+
+    assert!(EventChanges::default().is_empty());
+    let local_date = NaiveDate::from_ymd(2018, 3, 5).and_hms(9, 0, 0);
+    let changes = EventChanges {
+        local_start_date: Some(&local_date),
+        ..EventChanges::default()
+    };
+    assert!(!changes.is_empty());
+    let changes = EventChanges {
+        local_end_date: Some(&local_date),
+        ..EventChanges::default()
+    };
+    assert!(!changes.is_empty());
+}
+
+//# Traits
+
+/// This trait is the IO interface
+pub trait IOModel {
+    /// Find an event
+    fn find(&self, event_id: &Uuid) -> QueryResult<Option<Event>>;
+
+    /// Find an event that `viewer` may see, an anonymous viewer may only see the events that
+    /// aren't invite only
+    fn find_visible(&self, event_id: &Uuid, viewer: Option<&Uuid>) -> QueryResult<Option<Event>>;
+
+    /// Find an event by the iCalendar UID it was imported with
+    fn find_by_uid(&self, owner_id: &Uuid, uid: &str) -> QueryResult<Option<Event>>;
+
+    /// List the events that don't repeat and have not ended yet, ordered by their start date
+    ///
+    /// Only the public events and the ones `viewer` has a role in or is invited to are listed, and
+    /// only the ones with the `tags` when there are some
+    fn upcoming(
+        &self,
+        viewer: Option<&Uuid>,
+        tags: &[String],
+        tag_match: TagMatch,
+        page: &Page<DateIdKey>,
+    ) -> QueryResult<Paged<Event, DateIdKey>>;
+
+    /// List the upcoming events of some groups like `upcoming` does
+    fn group_upcoming(
+        &self,
+        group_ids: &[Uuid],
+        viewer: Option<&Uuid>,
+        page: &Page<DateIdKey>,
+    ) -> QueryResult<Paged<Event, DateIdKey>>;
+
+    /// List every recurring event that `viewer` may see listed, only the ones of the `group_ids`
+    /// when they are given and the ones with the `tags` when there are some
+    ///
+    /// Their next occurrences are worked out from their rules, so they can't be paged here
+    fn recurring(
+        &self,
+        viewer: Option<&Uuid>,
+        group_ids: Option<&[Uuid]>,
+        tags: &[String],
+        tag_match: TagMatch,
+    ) -> QueryResult<Vec<Event>>;
+
+    /// Search the events, the best text matches are first then they are ordered by their start date
+    fn search(
+        &self,
+        query: &EventQuery,
+        page: &Page<SearchKey>,
+    ) -> QueryResult<Paged<Event, SearchKey>>;
+
+    /// List every recurring event that matches a search, whether or not it has an occurrence in
+    /// the date range
+    fn search_recurring(&self, query: &EventQuery) -> QueryResult<Vec<Event>>;
+
+    /// Count the tags of every event that matches a search, the most used first
+    fn tag_counts(&self, query: &EventQuery, limit: i64) -> QueryResult<Vec<TagCount>>;
+
+    /// Create a new event, its owner is given the `owner` role
+    ///
+    /// The messages that `publish` gives for the event are put in the outbox when it is created
+    fn create<F>(&self, new_event: &NewEvent, publish: F) -> QueryResult<Event>
+    where
+        F: FnOnce(&Event) -> Vec<Outgoing>;
+
+    /// Update an event, waitlisted attendees are promoted when the capacity grows
+    ///
+    /// The messages that `publish` gives for the event are put in the outbox when it is updated
+    fn update<F>(
+        &self,
+        event_id: &Uuid,
+        changes: &EventChanges,
+        publish: F,
+    ) -> QueryResult<Option<Event>>
+    where
+        F: FnOnce(&Event) -> Vec<Outgoing>;
+
+    /// Delete an event
+    fn delete(&self, event_id: &Uuid) -> QueryResult<usize>;
+
+    /// List the events that start between `from` and `to` along with every repeating event that
+    /// started before `to`, the occurrences of the repeating ones are left to the caller
+    fn starting_between(
+        &self,
+        from: &DateTime<Utc>,
+        to: &DateTime<Utc>,
+    ) -> QueryResult<Vec<Event>>;
+
+    /// List the overridden and cancelled occurrences of a recurring event
+    fn occurrence_overrides(&self, event_id: &Uuid) -> QueryResult<Vec<OccurrenceOverride>>;
+
+    /// List the overridden and cancelled occurrences of several recurring events at once
+    fn occurrence_overrides_of(&self, event_ids: &[Uuid]) -> QueryResult<Vec<OccurrenceOverride>>;
+
+    /// Create or replace the override of a single occurrence
+    fn save_occurrence_override(
+        &self,
+        occurrence: &OccurrenceOverride,
+    ) -> QueryResult<OccurrenceOverride>;
+
+    /// Run `f` in a transaction, everything it changed is rolled back when it fails
+    fn transaction<T, E, F>(&self, f: F) -> Result<T, E>
+    where
+        F: FnOnce() -> Result<T, E>,
+        E: From<diesel::result::Error>;
+}
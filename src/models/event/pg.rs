@@ -0,0 +1,426 @@
+//! implements an `IOModel` for Postgres
+use super::{Event, EventChanges, EventQuery, IOModel, NewEvent, OccurrenceOverride, SearchHit,
+            SearchKey, TagCount, TagMatch, Visibility};
+use chrono::{DateTime, Utc};
+use diesel;
+use diesel::expression::BoxableExpression;
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::sql_types::{Array, BigInt, Bool, Float4, Float8, Nullable, Text, Timestamptz,
+                        Uuid as UuidType};
+use models::attendance::pg::promote_waitlisted;
+use models::event_member::{NewEventMember, Role};
+use models::invitation::InvitationStatus;
+use models::outbox::Outgoing;
+use models::outbox::pg::enqueue;
+use models::page::{after_date_id, DateIdKey, Page, Paged};
+use models::group::GroupRole;
+use schema::{event_members, events, group_members, groups, invitations};
+use uuid::Uuid;
+
+/// binds the fields of an `EventQuery` to the parameters of `matching_events!`
+macro_rules! bind_event_query {
+    ($sql_query:expr, $query:expr) => {
+        $sql_query
+            .bind::<Nullable<Text>, _>($query.text)
+            .bind::<Nullable<Timestamptz>, _>($query.from)
+            .bind::<Nullable<Timestamptz>, _>($query.to)
+            .bind::<Nullable<Float8>, _>($query.near.map(|x| x.0))
+            .bind::<Nullable<Float8>, _>($query.near.map(|x| x.1))
+            .bind::<Nullable<Float8>, _>($query.radius_km)
+            .bind::<Array<Text>, _>($query.tags)
+            .bind::<Bool, _>($query.tag_match == TagMatch::Any)
+            .bind::<Nullable<UuidType>, _>($query.viewer)
+            .bind::<Array<UuidType>, _>($query.excluded)
+    };
+}
+
+pub struct PgModel<'a> {
+    conn: &'a PgConnection,
+}
+impl<'a> PgModel<'a> {
+    pub fn new(conn: &'a PgConnection) -> Self {
+        PgModel { conn }
+    }
+}
+impl<'a> IOModel for PgModel<'a> {
+    fn find(&self, event_id: &Uuid) -> QueryResult<Option<Event>> {
+        use schema::events::dsl::*;
+
+        events
+            .filter(id.eq(event_id))
+            .get_result(self.conn)
+            .optional()
+    }
+
+    fn find_visible(&self, event_id: &Uuid, viewer: Option<&Uuid>) -> QueryResult<Option<Event>> {
+        use schema::events::dsl::*;
+
+        events
+            .filter(id.eq(event_id))
+            .filter(visible_to(viewer))
+            .get_result(self.conn)
+            .optional()
+    }
+
+    fn find_by_uid(&self, an_owner_id: &Uuid, an_uid: &str) -> QueryResult<Option<Event>> {
+        use schema::events::dsl::*;
+
+        events
+            .filter(owner_id.eq(an_owner_id))
+            .filter(uid.eq(an_uid))
+            .get_result(self.conn)
+            .optional()
+    }
+
+    fn upcoming(
+        &self,
+        viewer: Option<&Uuid>,
+        a_tags: &[String],
+        tag_match: TagMatch,
+        page: &Page<DateIdKey>,
+    ) -> QueryResult<Paged<Event, DateIdKey>> {
+        use schema::events::dsl::*;
+
+        let mut query = events
+            .filter(rrule.is_null())
+            .filter(end_date.ge(Utc::now()))
+            .filter(listed_to(viewer))
+            .order((start_date.asc(), id.asc()))
+            .limit(page.load_limit())
+            .into_boxed();
+        if !a_tags.is_empty() {
+            query = query.filter(tagged(a_tags, tag_match));
+        }
+        if let Some(ref key) = page.after {
+            query = query.filter(after_date_id(start_date, id, key));
+        }
+
+        let rows = query.load(self.conn)?;
+        Ok(page.paged(rows, |x: &Event| (x.start_date, x.id)))
+    }
+
+    fn group_upcoming(
+        &self,
+        group_ids: &[Uuid],
+        viewer: Option<&Uuid>,
+        page: &Page<DateIdKey>,
+    ) -> QueryResult<Paged<Event, DateIdKey>> {
+        use schema::events::dsl::*;
+
+        let mut query = events
+            .filter(group_id.eq_any(group_ids.iter().map(Some).collect::<Vec<_>>()))
+            .filter(rrule.is_null())
+            .filter(end_date.ge(Utc::now()))
+            .filter(listed_to(viewer))
+            .order((start_date.asc(), id.asc()))
+            .limit(page.load_limit())
+            .into_boxed();
+        if let Some(ref key) = page.after {
+            query = query.filter(after_date_id(start_date, id, key));
+        }
+
+        let rows = query.load(self.conn)?;
+        Ok(page.paged(rows, |x: &Event| (x.start_date, x.id)))
+    }
+
+    fn recurring(
+        &self,
+        viewer: Option<&Uuid>,
+        group_ids: Option<&[Uuid]>,
+        a_tags: &[String],
+        tag_match: TagMatch,
+    ) -> QueryResult<Vec<Event>> {
+        use schema::events::dsl::*;
+
+        let mut query = events
+            .filter(rrule.is_not_null())
+            .filter(listed_to(viewer))
+            .into_boxed();
+        if let Some(group_ids) = group_ids {
+            query = query.filter(group_id.eq_any(group_ids.iter().map(Some).collect::<Vec<_>>()));
+        }
+        if !a_tags.is_empty() {
+            query = query.filter(tagged(a_tags, tag_match));
+        }
+
+        query.load(self.conn)
+    }
+
+    fn search(
+        &self,
+        query: &EventQuery,
+        page: &Page<SearchKey>,
+    ) -> QueryResult<Paged<Event, SearchKey>> {
+        // Diesel doesn't know about tsvectors, so this is plain SQL with every filter bound
+        let rows: Vec<SearchHit> = bind_event_query!(diesel::sql_query(SEARCH_SQL), query)
+            .bind::<Nullable<Float4>, _>(page.after.map(|x| x.0))
+            .bind::<Nullable<Timestamptz>, _>(page.after.map(|x| x.1))
+            .bind::<Nullable<UuidType>, _>(page.after.map(|x| x.2))
+            .bind::<BigInt, _>(page.load_limit())
+            .load(self.conn)?;
+
+        Ok(page.paged(rows, |x| (x.rank, x.event.start_date, x.event.id))
+            .map(|x| x.event))
+    }
+
+    fn search_recurring(&self, query: &EventQuery) -> QueryResult<Vec<Event>> {
+        let rows: Vec<SearchHit> =
+            bind_event_query!(diesel::sql_query(SEARCH_RECURRING_SQL), query).load(self.conn)?;
+
+        Ok(rows.into_iter().map(|x| x.event).collect())
+    }
+
+    fn tag_counts(&self, query: &EventQuery, limit: i64) -> QueryResult<Vec<TagCount>> {
+        bind_event_query!(diesel::sql_query(TAG_COUNTS_SQL), query)
+            .bind::<BigInt, _>(limit)
+            .load(self.conn)
+    }
+
+    fn create<F>(&self, new_event: &NewEvent, publish: F) -> QueryResult<Event>
+    where
+        F: FnOnce(&Event) -> Vec<Outgoing>,
+    {
+        use schema::events::dsl::*;
+
+        self.conn.transaction(|| {
+            let event: Event = diesel::insert_into(events)
+                .values(new_event)
+                .get_result(self.conn)?;
+
+            diesel::insert_into(event_members::table)
+                .values(&NewEventMember {
+                    event_id: &event.id,
+                    user_id: &event.owner_id,
+                    role: Role::Owner.as_str(),
+                })
+                .execute(self.conn)?;
+            enqueue(self.conn, &publish(&event))?;
+            Ok(event)
+        })
+    }
+
+    fn update<F>(
+        &self,
+        event_id: &Uuid,
+        changes: &EventChanges,
+        publish: F,
+    ) -> QueryResult<Option<Event>>
+    where
+        F: FnOnce(&Event) -> Vec<Outgoing>,
+    {
+        use schema::events::dsl::*;
+
+        self.conn.transaction(|| {
+            let event = diesel::update(events.filter(id.eq(event_id)))
+                .set(changes)
+                .get_result(self.conn)
+                .optional()?;
+
+            if let Some(ref event) = event {
+                if changes.max_attendees.is_some() {
+                    promote_waitlisted(self.conn, event_id)?;
+                }
+                enqueue(self.conn, &publish(event))?;
+            }
+            Ok(event)
+        })
+    }
+
+    fn delete(&self, event_id: &Uuid) -> QueryResult<usize> {
+        use schema::events::dsl::*;
+
+        diesel::delete(events.filter(id.eq(event_id))).execute(self.conn)
+    }
+
+    fn starting_between(
+        &self,
+        from: &DateTime<Utc>,
+        to: &DateTime<Utc>,
+    ) -> QueryResult<Vec<Event>> {
+        use schema::events::dsl::*;
+
+        events
+            .filter(start_date.le(to))
+            .filter(start_date.gt(from).or(rrule.is_not_null()))
+            .order((start_date.asc(), id.asc()))
+            .load(self.conn)
+    }
+
+    fn occurrence_overrides(&self, an_event_id: &Uuid) -> QueryResult<Vec<OccurrenceOverride>> {
+        use schema::event_occurrences::dsl::*;
+
+        event_occurrences
+            .filter(event_id.eq(an_event_id))
+            .order(original_start_date.asc())
+            .load(self.conn)
+    }
+
+    fn occurrence_overrides_of(&self, event_ids: &[Uuid]) -> QueryResult<Vec<OccurrenceOverride>> {
+        use schema::event_occurrences::dsl::*;
+
+        event_occurrences
+            .filter(event_id.eq_any(event_ids))
+            .order((event_id.asc(), original_start_date.asc()))
+            .load(self.conn)
+    }
+
+    fn save_occurrence_override(
+        &self,
+        occurrence: &OccurrenceOverride,
+    ) -> QueryResult<OccurrenceOverride> {
+        use schema::event_occurrences::dsl::*;
+
+        diesel::insert_into(event_occurrences)
+            .values(occurrence)
+            .on_conflict((event_id, original_start_date))
+            .do_update()
+            .set(occurrence)
+            .get_result(self.conn)
+    }
+
+    fn transaction<T, E, F>(&self, f: F) -> Result<T, E>
+    where
+        F: FnOnce() -> Result<T, E>,
+        E: From<diesel::result::Error>,
+    {
+        self.conn.transaction(f)
+    }
+}
+
+/// A boolean expression over the events table
+pub type EventFilter = Box<dyn BoxableExpression<events::table, Pg, SqlType = Bool>>;
+
+/// the events that `viewer` may see, the ones that aren't invite only and the ones they have a
+/// role in or an invitation to that they haven't declined
+pub fn visible_to(viewer: Option<&Uuid>) -> EventFilter {
+    or_invited(
+        Box::new(events::visibility.ne(Visibility::InviteOnly.as_str())),
+        viewer,
+    )
+}
+
+/// the events that `viewer` may find in lists and searches, the public ones and the ones they
+/// have a role in or an invitation to that they haven't declined
+pub fn listed_to(viewer: Option<&Uuid>) -> EventFilter {
+    or_invited(
+        Box::new(events::visibility.eq(Visibility::Public.as_str())),
+        viewer,
+    )
+}
+
+/// the events with all or any of `tags`
+pub fn tagged(tags: &[String], tag_match: TagMatch) -> EventFilter {
+    match tag_match {
+        TagMatch::All => Box::new(events::tags.contains(tags.to_vec())),
+        TagMatch::Any => Box::new(events::tags.overlaps_with(tags.to_vec())),
+    }
+}
+
+/// widens a filter to the events that `viewer` has a role in or an invitation to that they
+/// haven't declined, and to the events of the groups they are an admin of.  An anonymous viewer is
+/// left with the filter
+fn or_invited(filter: EventFilter, viewer: Option<&Uuid>) -> EventFilter {
+    match viewer {
+        Some(viewer) => Box::new(
+            filter
+                .or(events::owner_id.eq(*viewer))
+                .or(events::id.eq_any(
+                    event_members::table
+                        .select(event_members::event_id)
+                        .filter(event_members::user_id.eq(*viewer)),
+                ))
+                .or(events::id.eq_any(
+                    invitations::table
+                        .select(invitations::event_id)
+                        .filter(invitations::user_id.eq(*viewer))
+                        .filter(invitations::status.ne(InvitationStatus::Declined.as_str())),
+                ))
+                .or(events::id.eq_any(
+                    events::table
+                        .inner_join(groups::table.inner_join(group_members::table))
+                        .select(events::id)
+                        .filter(group_members::user_id.eq(*viewer))
+                        .filter(group_members::role.eq(GroupRole::Admin.as_str())),
+                )),
+        ),
+        None => filter,
+    }
+}
+
+/// The events that match a search and their rank, the parameters are the fields of `EventQuery`
+///
+/// The text search matches the `events_search` index and the distance is the haversine formula.
+/// Like `listed_to`, only the public events, the ones the viewer has a role in or is invited to and
+/// the ones of the groups they are an admin of match.
+macro_rules! matching_events {
+    () => {
+        "
+        SELECT events.*, ts_rank(
+            to_tsvector('english', events.name || ' ' || events.description),
+            plainto_tsquery('english', coalesce($1, ''))
+        ) AS rank
+        FROM events
+        LEFT JOIN venues ON venues.id = events.venue_id
+        CROSS JOIN LATERAL (
+            SELECT
+                coalesce(events.latitude, venues.latitude) AS latitude,
+                coalesce(events.longitude, venues.longitude) AS longitude
+        ) AS location
+        WHERE ($1 IS NULL
+                OR to_tsvector('english', events.name || ' ' || events.description)
+                    @@ plainto_tsquery('english', $1))
+            AND ($2 IS NULL OR events.end_date >= $2 OR events.rrule IS NOT NULL)
+            AND ($3 IS NULL OR events.start_date < $3)
+            AND ($4 IS NULL OR $5 IS NULL OR 6371 * 2 * asin(sqrt(
+                power(sin(radians(location.latitude - $4) / 2), 2)
+                + cos(radians($4)) * cos(radians(location.latitude))
+                    * power(sin(radians(location.longitude - $5) / 2), 2)
+            )) <= coalesce($6, 'Infinity'))
+            AND (cardinality($7) = 0
+                OR (NOT $8 AND events.tags @> $7::varchar[])
+                OR ($8 AND events.tags && $7::varchar[]))
+            AND (events.visibility = 'public'
+                OR events.owner_id = $9
+                OR events.id IN (SELECT event_id FROM event_members WHERE user_id = $9)
+                OR events.id IN (
+                    SELECT event_id FROM invitations WHERE user_id = $9 AND status <> 'declined'
+                )
+                OR events.group_id IN (
+                    SELECT group_id FROM group_members WHERE user_id = $9 AND role = 'admin'
+                ))
+            AND NOT (events.id = ANY($10))
+        "
+    };
+}
+
+/// The event search, the parameters are the ones of `matching_events!` then the `SearchKey` to
+/// start after and the number of rows to load
+const SEARCH_SQL: &str = concat!(
+    "SELECT * FROM (",
+    matching_events!(),
+    ") AS hits
+    WHERE ($11 IS NULL OR (-rank, start_date, id) > (-$11, $12, $13))
+    ORDER BY rank DESC, start_date ASC, id ASC
+    LIMIT $14"
+);
+
+/// The recurring events that match a search, the parameters are the ones of `matching_events!`
+const SEARCH_RECURRING_SQL: &str = concat!(
+    "SELECT * FROM (",
+    matching_events!(),
+    ") AS hits
+    WHERE rrule IS NOT NULL"
+);
+
+/// The tags of the events that match a search, the parameters are the ones of `matching_events!`
+/// then the number of tags to load
+const TAG_COUNTS_SQL: &str = concat!(
+    "SELECT tag, count(*) AS count FROM (",
+    matching_events!(),
+    ") AS hits
+    CROSS JOIN LATERAL unnest(hits.tags) AS tag
+    GROUP BY tag
+    ORDER BY count DESC, tag ASC
+    LIMIT $11"
+);
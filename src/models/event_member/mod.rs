@@ -0,0 +1,191 @@
+//! Diesel model for the EventMember table
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use schema::event_members;
+use std::fmt;
+use std::str::FromStr;
+use uuid::Uuid;
+
+//# Modules
+
+pub mod pg;
+
+//# Enums
+
+/// The part a user plays in an event
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// The user that created the event, there is one per event
+    Owner,
+    /// Manages the event alongside the owner
+    CoOrganizer,
+    /// Checks people in at the door
+    CheckinStaff,
+    /// Can see the event, even when it is invite only
+    Attendee,
+    /// An admin of the event's group, this role comes from the group and isn't stored
+    GroupAdmin,
+}
+
+impl Role {
+    /// the value that is stored in the `role` column
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Role::Owner => "owner",
+            Role::CoOrganizer => "co_organizer",
+            Role::CheckinStaff => "checkin_staff",
+            Role::Attendee => "attendee",
+            Role::GroupAdmin => "group_admin",
+        }
+    }
+
+    /// true when the role may change the event, its occurrences and its invitations
+    pub fn can_edit_event(&self) -> bool {
+        match *self {
+            Role::Owner | Role::CoOrganizer | Role::GroupAdmin => true,
+            Role::CheckinStaff | Role::Attendee => false,
+        }
+    }
+
+    /// true when the role may delete the event
+    pub fn can_delete_event(&self) -> bool {
+        match *self {
+            Role::Owner | Role::GroupAdmin => true,
+            Role::CoOrganizer | Role::CheckinStaff | Role::Attendee => false,
+        }
+    }
+
+    /// true when the role may check people in to the event
+    pub fn can_check_in(&self) -> bool {
+        match *self {
+            Role::Owner | Role::CoOrganizer | Role::CheckinStaff | Role::GroupAdmin => true,
+            Role::Attendee => false,
+        }
+    }
+
+    /// true when the role may see the email addresses of the attendees
+    pub fn can_see_emails(&self) -> bool {
+        self.can_edit_event()
+    }
+
+    /// true when the role may give or take away `role`
+    ///
+    /// Only the owner manages the co-organizers, and nobody can give or take away ownership or
+    /// the role that comes from the group
+    pub fn can_assign(&self, role: Role) -> bool {
+        match (*self, role) {
+            (_, Role::Owner) | (_, Role::GroupAdmin) => false,
+            (Role::Owner, _) => true,
+            (Role::CoOrganizer, Role::CheckinStaff)
+            | (Role::CoOrganizer, Role::Attendee)
+            | (Role::GroupAdmin, Role::CheckinStaff)
+            | (Role::GroupAdmin, Role::Attendee) => true,
+            _ => false,
+        }
+    }
+}
+#[test]
+fn test_role_can_assign() {
+    assert!(Role::Owner.can_assign(Role::CoOrganizer));
+    assert!(Role::CoOrganizer.can_assign(Role::CheckinStaff));
+    assert!(!Role::CoOrganizer.can_assign(Role::CoOrganizer));
+    assert!(!Role::CheckinStaff.can_assign(Role::Attendee));
+    assert!(!Role::Owner.can_assign(Role::Owner));
+    assert!(!Role::Owner.can_assign(Role::GroupAdmin));
+    assert!(Role::GroupAdmin.can_assign(Role::CheckinStaff));
+}
+#[test]
+fn test_role_can_delete_event() {
+    assert!(Role::Owner.can_delete_event());
+    assert!(Role::GroupAdmin.can_delete_event());
+    assert!(!Role::CoOrganizer.can_delete_event());
+    assert!(!Role::CheckinStaff.can_delete_event());
+}
+
+impl FromStr for Role {
+    type Err = InvalidRole;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "owner" => Ok(Role::Owner),
+            "co_organizer" => Ok(Role::CoOrganizer),
+            "checkin_staff" => Ok(Role::CheckinStaff),
+            "attendee" => Ok(Role::Attendee),
+            "group_admin" => Ok(Role::GroupAdmin),
+            _ => Err(InvalidRole),
+        }
+    }
+}
+#[test]
+fn test_role_from_str() {
+    for role in &[
+        Role::Owner,
+        Role::CoOrganizer,
+        Role::CheckinStaff,
+        Role::Attendee,
+        Role::GroupAdmin,
+    ] {
+        assert_eq!(Role::from_str(role.as_str()).unwrap(), *role);
+    }
+    assert_eq!(Role::from_str("admin").unwrap_err(), InvalidRole);
+}
+
+/// returned when a string is not one of the `Role` values
+#[derive(Debug, PartialEq)]
+pub struct InvalidRole;
+
+impl fmt::Display for InvalidRole {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid role")
+    }
+}
+
+//# Structs
+
+/// `NewEventMember` is the struct that is used for giving a user a role in an event
+#[derive(Insertable)]
+#[table_name = "event_members"]
+pub struct NewEventMember<'a> {
+    pub event_id: &'a Uuid,
+    pub user_id: &'a Uuid,
+    pub role: &'a str,
+}
+
+/// EventMember is the struct that represents an EventMember record
+#[derive(Queryable)]
+pub struct EventMember {
+    pub event_id: Uuid,
+    pub user_id: Uuid,
+    pub role: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl EventMember {
+    /// the parsed `role` column
+    pub fn member_role(&self) -> Role {
+        // The role_valid constraint keeps anything else out of the table
+        Role::from_str(&self.role).unwrap_or(Role::Attendee)
+    }
+}
+
+//# Traits
+
+/// This trait is the IO interface
+pub trait IOModel {
+    /// The role a user has in an event, `None` when they don't have one
+    ///
+    /// The admins of the event's group have the `GroupAdmin` role, unless they are its owner
+    fn role(&self, event_id: &Uuid, user_id: &Uuid) -> QueryResult<Option<Role>>;
+
+    /// List the members of an event, the owner first then the oldest
+    fn list(&self, event_id: &Uuid) -> QueryResult<Vec<EventMember>>;
+
+    /// Give a user a role in an event, replacing the role they had
+    ///
+    /// The owner's role is never replaced, `None` is returned for them
+    fn save(&self, new_member: &NewEventMember) -> QueryResult<Option<EventMember>>;
+
+    /// Take away a user's role in an event, the owner's role is never taken away
+    fn remove(&self, event_id: &Uuid, user_id: &Uuid) -> QueryResult<usize>;
+}
@@ -0,0 +1,100 @@
+//! implements an `IOModel` for Postgres
+use super::{EventMember, IOModel, NewEventMember, Role};
+use diesel;
+use diesel::dsl::count_star;
+use diesel::prelude::*;
+use models::group::GroupRole;
+use schema::{events, group_members};
+use std::str::FromStr;
+use uuid::Uuid;
+
+pub struct PgModel<'a> {
+    conn: &'a PgConnection,
+}
+impl<'a> PgModel<'a> {
+    pub fn new(conn: &'a PgConnection) -> Self {
+        PgModel { conn }
+    }
+}
+impl<'a> IOModel for PgModel<'a> {
+    fn role(&self, an_event_id: &Uuid, a_user_id: &Uuid) -> QueryResult<Option<Role>> {
+        use schema::event_members::dsl::*;
+
+        let a_role = event_members
+            .select(role)
+            .find((an_event_id, a_user_id))
+            .get_result::<String>(self.conn)
+            .optional()?;
+        match a_role.and_then(|x| Role::from_str(&x).ok()) {
+            Some(Role::Owner) => Ok(Some(Role::Owner)),
+            x => if is_group_admin(self.conn, an_event_id, a_user_id)? {
+                Ok(Some(Role::GroupAdmin))
+            } else {
+                Ok(x)
+            },
+        }
+    }
+
+    fn list(&self, an_event_id: &Uuid) -> QueryResult<Vec<EventMember>> {
+        use schema::event_members::dsl::*;
+
+        event_members
+            .filter(event_id.eq(an_event_id))
+            .order((role.ne(Role::Owner.as_str()), created_at.asc(), user_id.asc()))
+            .load(self.conn)
+    }
+
+    fn save(&self, new_member: &NewEventMember) -> QueryResult<Option<EventMember>> {
+        use schema::event_members::dsl::*;
+
+        self.conn.transaction(|| {
+            let existing = event_members
+                .select(role)
+                .find((new_member.event_id, new_member.user_id))
+                .for_update()
+                .get_result::<String>(self.conn)
+                .optional()?;
+            if existing.as_deref() == Some(Role::Owner.as_str()) {
+                return Ok(None);
+            }
+
+            diesel::insert_into(event_members)
+                .values(new_member)
+                .on_conflict((event_id, user_id))
+                .do_update()
+                .set(role.eq(new_member.role))
+                .get_result(self.conn)
+                .map(Some)
+        })
+    }
+
+    fn remove(&self, an_event_id: &Uuid, a_user_id: &Uuid) -> QueryResult<usize> {
+        use schema::event_members::dsl::*;
+
+        diesel::delete(
+            event_members
+                .filter(event_id.eq(an_event_id))
+                .filter(user_id.eq(a_user_id))
+                .filter(role.ne(Role::Owner.as_str())),
+        ).execute(self.conn)
+    }
+}
+
+// Internal
+
+/// true when the user is an admin of the group that runs the event
+fn is_group_admin(conn: &PgConnection, event_id: &Uuid, user_id: &Uuid) -> QueryResult<bool> {
+    let admins: i64 = group_members::table
+        .select(count_star())
+        .filter(group_members::user_id.eq(user_id))
+        .filter(group_members::role.eq(GroupRole::Admin.as_str()))
+        .filter(
+            group_members::group_id.nullable().eq_any(
+                events::table
+                    .select(events::group_id)
+                    .filter(events::id.eq(event_id)),
+            ),
+        )
+        .get_result(conn)?;
+    Ok(admins > 0)
+}
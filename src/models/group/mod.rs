@@ -0,0 +1,166 @@
+//! Diesel model for the Group tables
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use models::page::{DateIdKey, Page, Paged};
+use schema::{group_members, groups};
+use std::fmt;
+use std::str::FromStr;
+use uuid::Uuid;
+
+//# Modules
+
+pub mod pg;
+
+//# Enums
+
+/// The part a user plays in a group
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupRole {
+    /// Manages the group, its members and all of its events
+    Admin,
+    Member,
+}
+
+impl GroupRole {
+    /// the value that is stored in the `role` column
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            GroupRole::Admin => "admin",
+            GroupRole::Member => "member",
+        }
+    }
+}
+
+impl FromStr for GroupRole {
+    type Err = InvalidGroupRole;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "admin" => Ok(GroupRole::Admin),
+            "member" => Ok(GroupRole::Member),
+            _ => Err(InvalidGroupRole),
+        }
+    }
+}
+#[test]
+fn test_group_role_from_str() {
+    for role in &[GroupRole::Admin, GroupRole::Member] {
+        assert_eq!(GroupRole::from_str(role.as_str()).unwrap(), *role);
+    }
+    assert_eq!(GroupRole::from_str("owner").unwrap_err(), InvalidGroupRole);
+}
+
+/// returned when a string is not one of the `GroupRole` values
+#[derive(Debug, PartialEq)]
+pub struct InvalidGroupRole;
+
+impl fmt::Display for InvalidGroupRole {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid group role")
+    }
+}
+
+//# Structs
+
+/// `NewGroup` is the struct that is used for storing a new group
+#[derive(Insertable)]
+#[table_name = "groups"]
+pub struct NewGroup<'a> {
+    pub id: &'a Uuid,
+    pub name: &'a str,
+    pub description: &'a str,
+}
+
+/// `GroupChanges` holds the fields of a group that can be updated, `None` fields are left alone
+#[derive(AsChangeset, Default)]
+#[table_name = "groups"]
+pub struct GroupChanges<'a> {
+    pub name: Option<&'a str>,
+    pub description: Option<&'a str>,
+}
+
+impl<'a> GroupChanges<'a> {
+    /// true when there is nothing to change
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none() && self.description.is_none()
+    }
+}
+
+/// Group is the struct that represents a Group record
+#[derive(Queryable)]
+pub struct Group {
+    pub id: Uuid,
+    pub name: String,
+    pub description: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// `NewGroupMember` is the struct that is used for giving a user a role in a group
+#[derive(Insertable)]
+#[table_name = "group_members"]
+pub struct NewGroupMember<'a> {
+    pub group_id: &'a Uuid,
+    pub user_id: &'a Uuid,
+    pub role: &'a str,
+}
+
+/// GroupMember is the struct that represents a GroupMember record
+#[derive(Queryable)]
+pub struct GroupMember {
+    pub group_id: Uuid,
+    pub user_id: Uuid,
+    pub role: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl GroupMember {
+    /// the parsed `role` column
+    pub fn member_role(&self) -> GroupRole {
+        // The role_valid constraint keeps anything else out of the table
+        GroupRole::from_str(&self.role).unwrap_or(GroupRole::Member)
+    }
+}
+
+//# Traits
+
+/// This trait is the IO interface
+pub trait IOModel {
+    /// Find a group
+    fn find(&self, group_id: &Uuid) -> QueryResult<Option<Group>>;
+
+    /// List the groups, the oldest first
+    fn list(&self, page: &Page<DateIdKey>) -> QueryResult<Paged<Group, DateIdKey>>;
+
+    /// Create a new group, `admin_id` is made its first admin
+    fn create(&self, new_group: &NewGroup, admin_id: &Uuid) -> QueryResult<Group>;
+
+    /// Update a group
+    fn update(&self, group_id: &Uuid, changes: &GroupChanges) -> QueryResult<Option<Group>>;
+
+    /// Delete a group, its events are left on their own
+    fn delete(&self, group_id: &Uuid) -> QueryResult<usize>;
+
+    /// The role a user has in a group, `None` when they aren't a member
+    fn role(&self, group_id: &Uuid, user_id: &Uuid) -> QueryResult<Option<GroupRole>>;
+
+    /// List the members of a group, the admins first then the oldest
+    fn members(&self, group_id: &Uuid) -> QueryResult<Vec<GroupMember>>;
+
+    /// Give a user a role in a group, replacing the role they had
+    ///
+    /// The last admin is never made a member, `None` is returned for them
+    fn save_member(&self, new_member: &NewGroupMember) -> QueryResult<Option<GroupMember>>;
+
+    /// Take a user out of a group, the last admin is never taken out
+    fn remove_member(&self, group_id: &Uuid, user_id: &Uuid) -> QueryResult<usize>;
+
+    /// Follow a group, following it again does nothing
+    fn follow(&self, group_id: &Uuid, user_id: &Uuid) -> QueryResult<usize>;
+
+    /// Stop following a group
+    fn unfollow(&self, group_id: &Uuid, user_id: &Uuid) -> QueryResult<usize>;
+
+    /// The groups a user follows
+    fn followed(&self, user_id: &Uuid) -> QueryResult<Vec<Uuid>>;
+}
@@ -0,0 +1,167 @@
+//! implements an `IOModel` for Postgres
+use super::{Group, GroupChanges, GroupMember, GroupRole, IOModel, NewGroup, NewGroupMember};
+use diesel;
+use diesel::prelude::*;
+use models::page::{after_date_id, DateIdKey, Page, Paged};
+use schema::{group_followers, group_members};
+use std::str::FromStr;
+use uuid::Uuid;
+
+pub struct PgModel<'a> {
+    conn: &'a PgConnection,
+}
+impl<'a> PgModel<'a> {
+    pub fn new(conn: &'a PgConnection) -> Self {
+        PgModel { conn }
+    }
+}
+impl<'a> IOModel for PgModel<'a> {
+    fn find(&self, group_id: &Uuid) -> QueryResult<Option<Group>> {
+        use schema::groups::dsl::*;
+
+        groups
+            .filter(id.eq(group_id))
+            .get_result(self.conn)
+            .optional()
+    }
+
+    fn list(&self, page: &Page<DateIdKey>) -> QueryResult<Paged<Group, DateIdKey>> {
+        use schema::groups::dsl::*;
+
+        let mut query = groups
+            .order((created_at.asc(), id.asc()))
+            .limit(page.load_limit())
+            .into_boxed();
+        if let Some(ref key) = page.after {
+            query = query.filter(after_date_id(created_at, id, key));
+        }
+
+        let rows = query.load(self.conn)?;
+        Ok(page.paged(rows, |x: &Group| (x.created_at, x.id)))
+    }
+
+    fn create(&self, new_group: &NewGroup, admin_id: &Uuid) -> QueryResult<Group> {
+        use schema::groups::dsl::*;
+
+        self.conn.transaction(|| {
+            let group: Group = diesel::insert_into(groups)
+                .values(new_group)
+                .get_result(self.conn)?;
+
+            diesel::insert_into(group_members::table)
+                .values(&NewGroupMember {
+                    group_id: &group.id,
+                    user_id: admin_id,
+                    role: GroupRole::Admin.as_str(),
+                })
+                .execute(self.conn)?;
+            Ok(group)
+        })
+    }
+
+    fn update(&self, group_id: &Uuid, changes: &GroupChanges) -> QueryResult<Option<Group>> {
+        use schema::groups::dsl::*;
+
+        diesel::update(groups.filter(id.eq(group_id)))
+            .set(changes)
+            .get_result(self.conn)
+            .optional()
+    }
+
+    fn delete(&self, group_id: &Uuid) -> QueryResult<usize> {
+        use schema::groups::dsl::*;
+
+        diesel::delete(groups.filter(id.eq(group_id))).execute(self.conn)
+    }
+
+    fn role(&self, a_group_id: &Uuid, a_user_id: &Uuid) -> QueryResult<Option<GroupRole>> {
+        use schema::group_members::dsl::*;
+
+        let a_role = group_members
+            .select(role)
+            .find((a_group_id, a_user_id))
+            .get_result::<String>(self.conn)
+            .optional()?;
+        Ok(a_role.and_then(|x| GroupRole::from_str(&x).ok()))
+    }
+
+    fn members(&self, a_group_id: &Uuid) -> QueryResult<Vec<GroupMember>> {
+        use schema::group_members::dsl::*;
+
+        group_members
+            .filter(group_id.eq(a_group_id))
+            .order((role.ne(GroupRole::Admin.as_str()), created_at.asc(), user_id.asc()))
+            .load(self.conn)
+    }
+
+    fn save_member(&self, new_member: &NewGroupMember) -> QueryResult<Option<GroupMember>> {
+        use schema::group_members::dsl::*;
+
+        self.conn.transaction(|| {
+            if new_member.role != GroupRole::Admin.as_str()
+                && is_last_admin(self.conn, new_member.group_id, new_member.user_id)?
+            {
+                return Ok(None);
+            }
+
+            diesel::insert_into(group_members)
+                .values(new_member)
+                .on_conflict((group_id, user_id))
+                .do_update()
+                .set(role.eq(new_member.role))
+                .get_result(self.conn)
+                .map(Some)
+        })
+    }
+
+    fn remove_member(&self, a_group_id: &Uuid, a_user_id: &Uuid) -> QueryResult<usize> {
+        use schema::group_members::dsl::*;
+
+        self.conn.transaction(|| {
+            if is_last_admin(self.conn, a_group_id, a_user_id)? {
+                return Ok(0);
+            }
+
+            diesel::delete(group_members.find((a_group_id, a_user_id))).execute(self.conn)
+        })
+    }
+
+    fn follow(&self, a_group_id: &Uuid, a_user_id: &Uuid) -> QueryResult<usize> {
+        use schema::group_followers::dsl::*;
+
+        diesel::insert_into(group_followers)
+            .values((group_id.eq(a_group_id), user_id.eq(a_user_id)))
+            .on_conflict_do_nothing()
+            .execute(self.conn)
+    }
+
+    fn unfollow(&self, a_group_id: &Uuid, a_user_id: &Uuid) -> QueryResult<usize> {
+        use schema::group_followers::dsl::*;
+
+        diesel::delete(group_followers.find((a_group_id, a_user_id))).execute(self.conn)
+    }
+
+    fn followed(&self, a_user_id: &Uuid) -> QueryResult<Vec<Uuid>> {
+        group_followers::table
+            .select(group_followers::group_id)
+            .filter(group_followers::user_id.eq(a_user_id))
+            .load(self.conn)
+    }
+}
+
+// Internal
+
+/// true when the user is the only admin of the group
+///
+/// This must be called inside of a transaction, it locks the admins' rows until the end of it
+fn is_last_admin(conn: &PgConnection, a_group_id: &Uuid, a_user_id: &Uuid) -> QueryResult<bool> {
+    use schema::group_members::dsl::*;
+
+    let admins: Vec<Uuid> = group_members
+        .select(user_id)
+        .filter(group_id.eq(a_group_id))
+        .filter(role.eq(GroupRole::Admin.as_str()))
+        .for_update()
+        .load(conn)?;
+    Ok(admins.len() == 1 && admins[0] == *a_user_id)
+}
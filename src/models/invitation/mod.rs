@@ -0,0 +1,131 @@
+//! Diesel model for the Invitation table
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use schema::invitations;
+use std::fmt;
+use std::str::FromStr;
+use uuid::Uuid;
+
+//# Modules
+
+pub mod pg;
+
+//# Enums
+
+/// Where an invitation stands, it is pending until the invitee answers it
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum InvitationStatus {
+    Pending,
+    Accepted,
+    Declined,
+}
+
+impl InvitationStatus {
+    /// the value that is stored in the `status` column
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            InvitationStatus::Pending => "pending",
+            InvitationStatus::Accepted => "accepted",
+            InvitationStatus::Declined => "declined",
+        }
+    }
+}
+
+impl FromStr for InvitationStatus {
+    type Err = InvalidInvitationStatus;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(InvitationStatus::Pending),
+            "accepted" => Ok(InvitationStatus::Accepted),
+            "declined" => Ok(InvitationStatus::Declined),
+            _ => Err(InvalidInvitationStatus),
+        }
+    }
+}
+#[test]
+fn test_invitation_status_from_str() {
+    for status in &[
+        InvitationStatus::Pending,
+        InvitationStatus::Accepted,
+        InvitationStatus::Declined,
+    ] {
+        assert_eq!(InvitationStatus::from_str(status.as_str()).unwrap(), *status);
+    }
+    assert_eq!(
+        InvitationStatus::from_str("maybe").unwrap_err(),
+        InvalidInvitationStatus
+    );
+}
+
+/// returned when a string is not one of the `InvitationStatus` values
+#[derive(Debug, PartialEq)]
+pub struct InvalidInvitationStatus;
+
+impl fmt::Display for InvalidInvitationStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid invitation status")
+    }
+}
+
+//# Structs
+
+/// `NewInvitation` is the struct that is used for storing a new invitation
+#[derive(Insertable)]
+#[table_name = "invitations"]
+pub struct NewInvitation<'a> {
+    pub event_id: &'a Uuid,
+    pub user_id: &'a Uuid,
+    pub invited_by: &'a Uuid,
+}
+
+/// Invitation is the struct that represents an Invitation record
+#[derive(Queryable)]
+pub struct Invitation {
+    pub event_id: Uuid,
+    /// The invited user
+    pub user_id: Uuid,
+    /// The organizer that sent the invitation
+    pub invited_by: Uuid,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    /// When the invitee accepted or declined
+    pub responded_at: Option<DateTime<Utc>>,
+}
+
+impl Invitation {
+    /// the parsed `status` column
+    pub fn invitation_status(&self) -> InvitationStatus {
+        // The status_valid constraint keeps anything else out of the table
+        InvitationStatus::from_str(&self.status).unwrap_or(InvitationStatus::Declined)
+    }
+}
+
+//# Traits
+
+/// This trait is the IO interface
+pub trait IOModel {
+    /// Find a user's invitation to an event
+    fn find(&self, event_id: &Uuid, user_id: &Uuid) -> QueryResult<Option<Invitation>>;
+
+    /// Invite a user to an event, inviting them again leaves their invitation as it is
+    fn invite(&self, new_invitation: &NewInvitation) -> QueryResult<Invitation>;
+
+    /// Accept or decline an invitation
+    fn respond(
+        &self,
+        event_id: &Uuid,
+        user_id: &Uuid,
+        status: InvitationStatus,
+    ) -> QueryResult<Option<Invitation>>;
+
+    /// Take back an invitation
+    fn revoke(&self, event_id: &Uuid, user_id: &Uuid) -> QueryResult<usize>;
+
+    /// List the invitations to an event, the oldest first
+    fn for_event(&self, event_id: &Uuid) -> QueryResult<Vec<Invitation>>;
+
+    /// List a user's invitations, ordered by the start dates of their events
+    fn for_user(&self, user_id: &Uuid) -> QueryResult<Vec<Invitation>>;
+}
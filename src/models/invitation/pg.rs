@@ -0,0 +1,82 @@
+//! implements an `IOModel` for Postgres
+use super::{IOModel, Invitation, InvitationStatus, NewInvitation};
+use chrono::Utc;
+use diesel;
+use diesel::prelude::*;
+use schema::events;
+use uuid::Uuid;
+
+pub struct PgModel<'a> {
+    conn: &'a PgConnection,
+}
+impl<'a> PgModel<'a> {
+    pub fn new(conn: &'a PgConnection) -> Self {
+        PgModel { conn }
+    }
+}
+impl<'a> IOModel for PgModel<'a> {
+    fn find(&self, an_event_id: &Uuid, a_user_id: &Uuid) -> QueryResult<Option<Invitation>> {
+        use schema::invitations::dsl::*;
+
+        invitations
+            .find((an_event_id, a_user_id))
+            .get_result(self.conn)
+            .optional()
+    }
+
+    fn invite(&self, new_invitation: &NewInvitation) -> QueryResult<Invitation> {
+        use schema::invitations::dsl::*;
+
+        self.conn.transaction(|| {
+            diesel::insert_into(invitations)
+                .values(new_invitation)
+                .on_conflict((event_id, user_id))
+                .do_nothing()
+                .execute(self.conn)?;
+
+            invitations
+                .find((new_invitation.event_id, new_invitation.user_id))
+                .get_result(self.conn)
+        })
+    }
+
+    fn respond(
+        &self,
+        an_event_id: &Uuid,
+        a_user_id: &Uuid,
+        a_status: InvitationStatus,
+    ) -> QueryResult<Option<Invitation>> {
+        use schema::invitations::dsl::*;
+
+        diesel::update(invitations.find((an_event_id, a_user_id)))
+            .set((status.eq(a_status.as_str()), responded_at.eq(Utc::now())))
+            .get_result(self.conn)
+            .optional()
+    }
+
+    fn revoke(&self, an_event_id: &Uuid, a_user_id: &Uuid) -> QueryResult<usize> {
+        use schema::invitations::dsl::*;
+
+        diesel::delete(invitations.find((an_event_id, a_user_id))).execute(self.conn)
+    }
+
+    fn for_event(&self, an_event_id: &Uuid) -> QueryResult<Vec<Invitation>> {
+        use schema::invitations::dsl::*;
+
+        invitations
+            .filter(event_id.eq(an_event_id))
+            .order((created_at.asc(), user_id.asc()))
+            .load(self.conn)
+    }
+
+    fn for_user(&self, a_user_id: &Uuid) -> QueryResult<Vec<Invitation>> {
+        use schema::invitations::dsl::*;
+
+        invitations
+            .inner_join(events::table)
+            .select(::schema::invitations::all_columns)
+            .filter(user_id.eq(a_user_id))
+            .order((events::start_date.asc(), events::id.asc()))
+            .load(self.conn)
+    }
+}
@@ -1,2 +1,16 @@
 //! Diesel models
+pub mod attendance;
+pub mod calendar_feed;
+pub mod comment;
+pub mod event;
+pub mod event_member;
+pub mod group;
+pub mod invitation;
+pub mod outbox;
+pub mod page;
+pub mod refresh_token;
+pub mod reminder;
+pub mod revoked_token;
 pub mod user;
+pub mod venue;
+pub mod webhook;
@@ -0,0 +1,198 @@
+//! Diesel model for the Outbox table
+use chrono::{DateTime, Duration, Utc};
+use diesel::prelude::*;
+use schema::outbox;
+use std::fmt;
+use std::str::FromStr;
+use uuid::Uuid;
+
+//# Modules
+
+pub mod pg;
+
+//# Enums
+
+/// What a message is, it says how the worker delivers it
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageKind {
+    /// A `Notification` for the notifier
+    Notification,
+    /// A `WebhookPayload` that is copied into a delivery for every matching subscription
+    Webhook,
+    /// The id of a webhook delivery that is POSTed to its subscription
+    WebhookDelivery,
+}
+
+impl MessageKind {
+    /// the value that is stored in the `kind` column
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            MessageKind::Notification => "notification",
+            MessageKind::Webhook => "webhook",
+            MessageKind::WebhookDelivery => "webhook_delivery",
+        }
+    }
+}
+
+impl FromStr for MessageKind {
+    type Err = InvalidMessageKind;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "notification" => Ok(MessageKind::Notification),
+            "webhook" => Ok(MessageKind::Webhook),
+            "webhook_delivery" => Ok(MessageKind::WebhookDelivery),
+            _ => Err(InvalidMessageKind),
+        }
+    }
+}
+#[test]
+fn test_message_kind_from_str() {
+    for kind in &[
+        MessageKind::Notification,
+        MessageKind::Webhook,
+        MessageKind::WebhookDelivery,
+    ] {
+        assert_eq!(MessageKind::from_str(kind.as_str()).unwrap(), *kind);
+    }
+    assert_eq!(MessageKind::from_str("carrier_pigeon").unwrap_err(), InvalidMessageKind);
+}
+
+/// returned when a string is not one of the `MessageKind` values
+#[derive(Debug, PartialEq)]
+pub struct InvalidMessageKind;
+
+impl fmt::Display for InvalidMessageKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid message kind")
+    }
+}
+
+/// Where a message is in its delivery
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageStatus {
+    /// It has not been delivered yet, it may have failed and be waiting for a retry
+    Pending,
+    Sent,
+    /// It failed too many times and won't be tried again
+    Dead,
+}
+
+impl MessageStatus {
+    /// the value that is stored in the `status` column
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            MessageStatus::Pending => "pending",
+            MessageStatus::Sent => "sent",
+            MessageStatus::Dead => "dead",
+        }
+    }
+}
+
+impl FromStr for MessageStatus {
+    type Err = InvalidMessageStatus;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(MessageStatus::Pending),
+            "sent" => Ok(MessageStatus::Sent),
+            "dead" => Ok(MessageStatus::Dead),
+            _ => Err(InvalidMessageStatus),
+        }
+    }
+}
+#[test]
+fn test_message_status_from_str() {
+    for status in &[MessageStatus::Pending, MessageStatus::Sent, MessageStatus::Dead] {
+        assert_eq!(MessageStatus::from_str(status.as_str()).unwrap(), *status);
+    }
+    assert_eq!(MessageStatus::from_str("lost").unwrap_err(), InvalidMessageStatus);
+}
+
+/// returned when a string is not one of the `MessageStatus` values
+#[derive(Debug, PartialEq)]
+pub struct InvalidMessageStatus;
+
+impl fmt::Display for InvalidMessageStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid message status")
+    }
+}
+
+//# Structs
+
+/// `NewMessage` is the struct that is used for putting a message in the outbox
+#[derive(Insertable)]
+#[table_name = "outbox"]
+pub struct NewMessage<'a> {
+    pub id: &'a Uuid,
+    pub kind: &'a str,
+    /// The JSON that the worker delivers, its shape depends on the `kind`
+    pub payload: &'a str,
+}
+
+/// `Outgoing` is a message that a model puts in the outbox along with the change that caused it
+#[derive(Debug)]
+pub struct Outgoing {
+    pub kind: MessageKind,
+    pub payload: String,
+}
+
+/// Message is the struct that represents an Outbox record
+#[derive(Queryable, QueryableByName, Debug)]
+#[table_name = "outbox"]
+pub struct Message {
+    pub id: Uuid,
+    pub kind: String,
+    pub payload: String,
+    pub status: String,
+    /// The number of times the delivery failed
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub sent_at: Option<DateTime<Utc>>,
+}
+
+impl Message {
+    /// the number of failed deliveries that makes a message dead
+    pub const MAX_ATTEMPTS: i32 = 10;
+
+    pub fn message_status(&self) -> MessageStatus {
+        MessageStatus::from_str(&self.status).unwrap_or(MessageStatus::Pending)
+    }
+
+    /// how long to wait before retrying a message that has failed `attempts` times
+    ///
+    /// The wait doubles with every failure, starting at 30 seconds, so the last retry comes a
+    /// little over four hours after the one before it
+    pub fn retry_delay(attempts: i32) -> Duration {
+        Duration::seconds(30 << (attempts.clamp(1, Message::MAX_ATTEMPTS) - 1))
+    }
+}
+#[test]
+fn test_retry_delay() {
+    assert_eq!(Message::retry_delay(1), Duration::seconds(30));
+    assert_eq!(Message::retry_delay(2), Duration::seconds(60));
+    assert_eq!(Message::retry_delay(4), Duration::minutes(4));
+    assert_eq!(Message::retry_delay(50), Message::retry_delay(Message::MAX_ATTEMPTS));
+}
+
+//# Traits
+
+/// This trait is the IO interface
+pub trait IOModel {
+    /// Put a message in the outbox, it is delivered as soon as the worker gets to it
+    fn enqueue(&self, message: &Outgoing) -> QueryResult<usize>;
+
+    /// Lock the next pending message that is due at `now` and hand it to `deliver`
+    ///
+    /// A message that is delivered is marked as sent, one that fails is retried later or marked as
+    /// dead when it has failed too many times.  The message is locked until `deliver` returns so
+    /// that two workers don't deliver it twice.  `None` means there was nothing to deliver.
+    fn deliver_next<F>(&self, now: &DateTime<Utc>, deliver: F) -> QueryResult<Option<Message>>
+    where
+        F: FnOnce(&Message) -> Result<(), String>;
+}
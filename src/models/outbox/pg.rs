@@ -0,0 +1,93 @@
+//! implements an `IOModel` for Postgres
+use super::{IOModel, Message, MessageStatus, NewMessage, Outgoing};
+use chrono::{DateTime, Utc};
+use diesel;
+use diesel::prelude::*;
+use diesel::sql_types::Timestamptz;
+use std::slice;
+use uuid::Uuid;
+
+pub struct PgModel<'a> {
+    conn: &'a PgConnection,
+}
+impl<'a> PgModel<'a> {
+    pub fn new(conn: &'a PgConnection) -> Self {
+        PgModel { conn }
+    }
+}
+impl<'a> IOModel for PgModel<'a> {
+    fn enqueue(&self, message: &Outgoing) -> QueryResult<usize> {
+        enqueue(self.conn, slice::from_ref(message))
+    }
+
+    fn deliver_next<F>(&self, now: &DateTime<Utc>, deliver: F) -> QueryResult<Option<Message>>
+    where
+        F: FnOnce(&Message) -> Result<(), String>,
+    {
+        use schema::outbox::dsl::*;
+
+        self.conn.transaction(|| {
+            // Skipping the locked messages lets other workers deliver the rest in the meantime
+            let message = diesel::sql_query(
+                "SELECT * FROM outbox WHERE status = 'pending' AND next_attempt_at <= $1 \
+                 ORDER BY next_attempt_at, id LIMIT 1 FOR UPDATE SKIP LOCKED",
+            ).bind::<Timestamptz, _>(now)
+                .get_result::<Message>(self.conn)
+                .optional()?;
+            let message = match message {
+                Some(x) => x,
+                None => return Ok(None),
+            };
+
+            let target = outbox.filter(id.eq(message.id));
+            match deliver(&message) {
+                Ok(()) => diesel::update(target)
+                    .set((status.eq(MessageStatus::Sent.as_str()), sent_at.eq(now)))
+                    .get_result(self.conn)
+                    .map(Some),
+                Err(error) => {
+                    let failures = message.attempts + 1;
+                    let next_status = if failures >= Message::MAX_ATTEMPTS {
+                        MessageStatus::Dead
+                    } else {
+                        MessageStatus::Pending
+                    };
+                    diesel::update(target)
+                        .set((
+                            status.eq(next_status.as_str()),
+                            attempts.eq(failures),
+                            next_attempt_at.eq(*now + Message::retry_delay(failures)),
+                            last_error.eq(error),
+                        ))
+                        .get_result(self.conn)
+                        .map(Some)
+                }
+            }
+        })
+    }
+}
+
+/// puts messages in the outbox
+///
+/// This is called by the other models inside of their transactions, so the messages only go out
+/// when the change that caused them is committed
+pub fn enqueue(conn: &PgConnection, messages: &[Outgoing]) -> QueryResult<usize> {
+    use schema::outbox::dsl::*;
+
+    if messages.is_empty() {
+        return Ok(0);
+    }
+    let ids = messages.iter().map(|_| Uuid::new_v4()).collect::<Vec<_>>();
+    let new_messages = messages
+        .iter()
+        .zip(&ids)
+        .map(|(x, an_id)| NewMessage {
+            id: an_id,
+            kind: x.kind.as_str(),
+            payload: &x.payload,
+        })
+        .collect::<Vec<_>>();
+    diesel::insert_into(outbox)
+        .values(&new_messages)
+        .execute(conn)
+}
@@ -0,0 +1,177 @@
+//! Keyset pagination
+//!
+//! A page is the rows that come after the key of the last row of the previous page, in the order
+//! of the list.  Unlike offsets, rows that are added or removed before the key don't shift the
+//! rows of the next page.
+use chrono::{DateTime, Utc};
+use diesel::dsl::{And, Eq, Gt, Or};
+use diesel::expression::{AsExpression, Expression};
+use diesel::prelude::*;
+use diesel::sql_types::{Bool, Timestamptz, Uuid as UuidType};
+use uuid::Uuid;
+
+/// `Page` asks for the rows after the `after` key, `limit` of them at most
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Page<K> {
+    pub after: Option<K>,
+    pub limit: i64,
+}
+
+/// `Paged` is a page of rows and the key to ask for the next page with, `None` on the last page
+#[derive(Debug)]
+pub struct Paged<T, K> {
+    pub rows: Vec<T>,
+    pub next: Option<K>,
+}
+
+/// The key of a list that is ordered by a date then an id
+pub type DateIdKey = (DateTime<Utc>, Uuid);
+
+impl<K> Page<K> {
+    /// the number of rows to load, one more than the limit to find out if there is a next page
+    pub fn load_limit(&self) -> i64 {
+        self.limit + 1
+    }
+
+    /// splits the extra row loaded by `load_limit` off, `key` gives the key of a row
+    pub fn paged<T, F>(&self, mut rows: Vec<T>, key: F) -> Paged<T, K>
+    where
+        F: Fn(&T) -> K,
+    {
+        let limit = self.limit.max(0) as usize;
+        if rows.len() <= limit {
+            return Paged { rows, next: None };
+        }
+
+        rows.truncate(limit);
+        let next = rows.last().map(key);
+        Paged { rows, next }
+    }
+}
+#[test]
+fn test_page_paged() {
+    let page: Page<i32> = Page {
+        after: None,
+        limit: 2,
+    };
+
+    let paged = page.paged(vec![1, 2, 3], |x| *x);
+    assert_eq!(paged.rows, vec![1, 2]);
+    assert_eq!(paged.next, Some(2));
+
+    let paged = page.paged(vec![1, 2], |x| *x);
+    assert_eq!(paged.rows, vec![1, 2]);
+    assert_eq!(paged.next, None);
+}
+
+impl<K: Ord + Clone> Page<K> {
+    /// merges a page that was `loaded` with `extra` rows that can't be paged by the database
+    ///
+    /// The `extra` rows come with their keys in any order, the ones that aren't after the `after`
+    /// key are dropped.  The page stops at the last loaded row when there are more to load, so
+    /// that the rows between it and the extra rows aren't skipped.
+    pub fn merged<T, F>(&self, loaded: Paged<T, K>, extra: Vec<(K, T)>, key: F) -> Paged<T, K>
+    where
+        F: Fn(&T) -> K,
+    {
+        let mut rows = loaded
+            .rows
+            .into_iter()
+            .map(|x| (key(&x), x))
+            .collect::<Vec<_>>();
+        rows.extend(
+            extra
+                .into_iter()
+                .filter(|x| self.after.as_ref().is_none_or(|after| x.0 > *after)),
+        );
+        if let Some(ref next) = loaded.next {
+            rows.retain(|x| x.0 <= *next);
+        }
+        rows.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let limit = self.limit.max(0) as usize;
+        let more = loaded.next.is_some() || rows.len() > limit;
+        rows.truncate(limit);
+        let next = if more {
+            rows.last().map(|x| x.0.clone())
+        } else {
+            None
+        };
+        Paged {
+            rows: rows.into_iter().map(|x| x.1).collect(),
+            next,
+        }
+    }
+}
+#[test]
+fn test_page_merged() {
+    let page: Page<i32> = Page {
+        after: Some(1),
+        limit: 3,
+    };
+
+    let paged = page.merged(
+        Paged {
+            rows: vec![2, 5],
+            next: None,
+        },
+        vec![(4, 4), (1, 1), (3, 3)],
+        |x| *x,
+    );
+    assert_eq!(paged.rows, vec![2, 3, 4]);
+    assert_eq!(paged.next, Some(4));
+
+    // The extra rows after the last loaded one wait for the next page
+    let paged = page.merged(
+        Paged {
+            rows: vec![2, 5, 6],
+            next: Some(6),
+        },
+        vec![(7, 7)],
+        |x| *x,
+    );
+    assert_eq!(paged.rows, vec![2, 5, 6]);
+    assert_eq!(paged.next, Some(6));
+
+    let paged = page.merged(
+        Paged {
+            rows: vec![2],
+            next: None,
+        },
+        vec![(3, 3)],
+        |x| *x,
+    );
+    assert_eq!(paged.rows, vec![2, 3]);
+    assert_eq!(paged.next, None);
+}
+
+impl<T, K> Paged<T, K> {
+    /// converts the rows, keeping the next key
+    pub fn map<U, F>(self, f: F) -> Paged<U, K>
+    where
+        F: FnMut(T) -> U,
+    {
+        Paged {
+            rows: self.rows.into_iter().map(f).collect(),
+            next: self.next,
+        }
+    }
+}
+
+/// The type of the `after_date_id` condition
+pub type AfterDateId<D, I> = Or<Gt<D, DateTime<Utc>>, And<Eq<D, DateTime<Utc>>, Gt<I, Uuid>>>;
+
+/// the keyset condition `(date, id) > key` for a list that is ordered by `date` then `id`
+pub fn after_date_id<D, I>(date: D, id: I, key: &DateIdKey) -> AfterDateId<D, I>
+where
+    D: Expression<SqlType = Timestamptz> + ExpressionMethods + Clone,
+    I: Expression<SqlType = UuidType> + ExpressionMethods,
+    Gt<D, DateTime<Utc>>: Expression<SqlType = Bool>,
+    Eq<D, DateTime<Utc>>: Expression<SqlType = Bool>,
+    Gt<I, Uuid>: AsExpression<Bool>,
+    And<Eq<D, DateTime<Utc>>, Gt<I, Uuid>>: AsExpression<Bool>,
+{
+    date.clone()
+        .gt(key.0)
+        .or(date.eq(key.0).and(id.gt(key.1)))
+}
@@ -0,0 +1,79 @@
+//! Diesel model for the RefreshToken table
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use schema::refresh_tokens;
+use uuid::Uuid;
+
+//# Modules
+
+pub mod pg;
+
+//# Structs
+
+/// `NewRefreshToken` is the struct that is used for storing a new token
+#[derive(Insertable)]
+#[table_name = "refresh_tokens"]
+pub struct NewRefreshToken<'a> {
+    pub jti: &'a Uuid,
+    pub family_id: &'a Uuid,
+    pub user_id: &'a Uuid,
+    pub expires_at: &'a DateTime<Utc>,
+}
+
+/// RefreshToken is the struct that represents a RefreshToken record
+#[derive(Queryable, Debug)]
+pub struct RefreshToken {
+    pub jti: Uuid,
+    /// The tokens that came from the same login share a family
+    pub family_id: Uuid,
+    pub user_id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    /// When the token was traded for the next one in its family
+    pub used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+//# Enums
+
+/// the outcome of trading a refresh token for the next one
+#[derive(Debug)]
+pub enum Rotation {
+    /// The token was used and this one took its place
+    Rotated(RefreshToken),
+    /// The token was used before, so it was stolen or replayed and its family was revoked
+    Reused,
+    /// The token is unknown, expired or revoked
+    Invalid,
+}
+
+//# Traits
+
+/// This trait is the IO interface
+pub trait IOModel {
+    /// Find a token by its jti
+    fn find(&self, jti: &Uuid) -> QueryResult<Option<RefreshToken>>;
+
+    /// Store the first token of a new family
+    fn create(&self, new_token: &NewRefreshToken) -> QueryResult<RefreshToken>;
+
+    /// Mark a token used and store the next token of its family, `next_jti` expires at
+    /// `expires_at`
+    ///
+    /// A token that was used already revokes its whole family.
+    fn rotate(
+        &self,
+        jti: &Uuid,
+        next_jti: &Uuid,
+        expires_at: &DateTime<Utc>,
+    ) -> QueryResult<Rotation>;
+
+    /// Revoke every token of a family
+    fn revoke_family(&self, family_id: &Uuid) -> QueryResult<usize>;
+
+    /// Revoke every token of a user, this logs them out everywhere
+    fn revoke_user(&self, user_id: &Uuid) -> QueryResult<usize>;
+
+    /// true when a family was revoked, the access tokens of its login are no longer valid
+    fn is_family_revoked(&self, family_id: &Uuid) -> QueryResult<bool>;
+}
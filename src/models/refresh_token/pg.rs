@@ -0,0 +1,106 @@
+//! implements an `IOModel` for Postgres
+use super::{IOModel, NewRefreshToken, RefreshToken, Rotation};
+use chrono::{DateTime, Utc};
+use diesel;
+use diesel::dsl::exists;
+use diesel::prelude::*;
+use uuid::Uuid;
+
+pub struct PgModel<'a> {
+    conn: &'a PgConnection,
+}
+impl<'a> PgModel<'a> {
+    pub fn new(conn: &'a PgConnection) -> Self {
+        PgModel { conn }
+    }
+}
+impl<'a> IOModel for PgModel<'a> {
+    fn find(&self, token_jti: &Uuid) -> QueryResult<Option<RefreshToken>> {
+        use schema::refresh_tokens::dsl::*;
+
+        refresh_tokens
+            .filter(jti.eq(token_jti))
+            .get_result(self.conn)
+            .optional()
+    }
+
+    fn create(&self, new_token: &NewRefreshToken) -> QueryResult<RefreshToken> {
+        use schema::refresh_tokens::dsl::*;
+
+        diesel::insert_into(refresh_tokens)
+            .values(new_token)
+            .get_result(self.conn)
+    }
+
+    fn rotate(
+        &self,
+        token_jti: &Uuid,
+        next_jti: &Uuid,
+        next_expires_at: &DateTime<Utc>,
+    ) -> QueryResult<Rotation> {
+        use schema::refresh_tokens::dsl::*;
+
+        self.conn.transaction(|| {
+            let now = Utc::now();
+            // Only one of two requests racing with the same token gets to use it
+            let used = diesel::update(refresh_tokens)
+                .filter(jti.eq(token_jti))
+                .filter(used_at.is_null())
+                .filter(revoked_at.is_null())
+                .filter(expires_at.gt(now))
+                .set(used_at.eq(now))
+                .get_result::<RefreshToken>(self.conn)
+                .optional()?;
+
+            if let Some(used) = used {
+                let next = diesel::insert_into(refresh_tokens)
+                    .values(&NewRefreshToken {
+                        jti: next_jti,
+                        family_id: &used.family_id,
+                        user_id: &used.user_id,
+                        expires_at: next_expires_at,
+                    })
+                    .get_result(self.conn)?;
+                return Ok(Rotation::Rotated(next));
+            }
+
+            match self.find(token_jti)? {
+                Some(ref x) if x.used_at.is_some() && x.revoked_at.is_none() => {
+                    self.revoke_family(&x.family_id)?;
+                    Ok(Rotation::Reused)
+                }
+                _ => Ok(Rotation::Invalid),
+            }
+        })
+    }
+
+    fn revoke_family(&self, a_family_id: &Uuid) -> QueryResult<usize> {
+        use schema::refresh_tokens::dsl::*;
+
+        diesel::update(refresh_tokens)
+            .filter(family_id.eq(a_family_id))
+            .filter(revoked_at.is_null())
+            .set(revoked_at.eq(Utc::now()))
+            .execute(self.conn)
+    }
+
+    fn revoke_user(&self, a_user_id: &Uuid) -> QueryResult<usize> {
+        use schema::refresh_tokens::dsl::*;
+
+        diesel::update(refresh_tokens)
+            .filter(user_id.eq(a_user_id))
+            .filter(revoked_at.is_null())
+            .set(revoked_at.eq(Utc::now()))
+            .execute(self.conn)
+    }
+
+    fn is_family_revoked(&self, a_family_id: &Uuid) -> QueryResult<bool> {
+        use schema::refresh_tokens::dsl::*;
+
+        diesel::select(exists(
+            refresh_tokens
+                .filter(family_id.eq(a_family_id))
+                .filter(revoked_at.is_not_null()),
+        )).get_result(self.conn)
+    }
+}
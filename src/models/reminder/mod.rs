@@ -0,0 +1,128 @@
+//! Diesel model for the RemindersSent table
+use chrono::{DateTime, Duration, Utc};
+use diesel::prelude::*;
+use models::outbox::Outgoing;
+use schema::reminders_sent;
+use std::fmt;
+use std::str::FromStr;
+use uuid::Uuid;
+
+//# Modules
+
+pub mod pg;
+
+//# Enums
+
+/// When a reminder goes out before an occurrence starts
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum ReminderKind {
+    #[serde(rename = "1h")]
+    HourBefore,
+    #[serde(rename = "24h")]
+    DayBefore,
+}
+
+impl ReminderKind {
+    /// every kind of reminder, the one that goes out last first
+    pub const ALL: [ReminderKind; 2] = [ReminderKind::HourBefore, ReminderKind::DayBefore];
+
+    /// the value that is stored in the `kind` column
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            ReminderKind::HourBefore => "1h",
+            ReminderKind::DayBefore => "24h",
+        }
+    }
+
+    /// how long before the start the reminder goes out
+    pub fn lead_time(&self) -> Duration {
+        match *self {
+            ReminderKind::HourBefore => Duration::hours(1),
+            ReminderKind::DayBefore => Duration::hours(24),
+        }
+    }
+
+    /// the reminder that is due at `now` for an occurrence that starts at `start`
+    ///
+    /// Only the latest reminder is due, so a server that was down for a while sends the hour
+    /// before reminder and skips the day before one instead of sending both
+    pub fn due(now: &DateTime<Utc>, start: &DateTime<Utc>) -> Option<ReminderKind> {
+        if start <= now {
+            return None;
+        }
+        ReminderKind::ALL
+            .iter()
+            .find(|x| *start <= *now + x.lead_time())
+            .cloned()
+    }
+}
+#[test]
+fn test_reminder_kind_due() {
+    let now = Utc::now();
+
+    assert_eq!(ReminderKind::due(&now, &(now - Duration::minutes(1))), None);
+    assert_eq!(
+        ReminderKind::due(&now, &(now + Duration::minutes(30))),
+        Some(ReminderKind::HourBefore)
+    );
+    assert_eq!(
+        ReminderKind::due(&now, &(now + Duration::hours(1))),
+        Some(ReminderKind::HourBefore)
+    );
+    assert_eq!(
+        ReminderKind::due(&now, &(now + Duration::hours(5))),
+        Some(ReminderKind::DayBefore)
+    );
+    assert_eq!(ReminderKind::due(&now, &(now + Duration::hours(25))), None);
+}
+
+impl FromStr for ReminderKind {
+    type Err = InvalidReminderKind;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "1h" => Ok(ReminderKind::HourBefore),
+            "24h" => Ok(ReminderKind::DayBefore),
+            _ => Err(InvalidReminderKind),
+        }
+    }
+}
+#[test]
+fn test_reminder_kind_from_str() {
+    for kind in &ReminderKind::ALL {
+        assert_eq!(ReminderKind::from_str(kind.as_str()).unwrap(), *kind);
+    }
+    assert_eq!(ReminderKind::from_str("1w").unwrap_err(), InvalidReminderKind);
+}
+
+/// returned when a string is not one of the `ReminderKind` values
+#[derive(Debug, PartialEq)]
+pub struct InvalidReminderKind;
+
+impl fmt::Display for InvalidReminderKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid reminder kind")
+    }
+}
+
+//# Structs
+
+/// `NewReminder` is the struct that is used for recording a reminder
+#[derive(Insertable)]
+#[table_name = "reminders_sent"]
+pub struct NewReminder<'a> {
+    pub event_id: &'a Uuid,
+    pub user_id: &'a Uuid,
+    /// The start of the occurrence that the RRULE gives, it is the event's start when it doesn't
+    /// repeat
+    pub occurrence_start: &'a DateTime<Utc>,
+    pub kind: &'a str,
+}
+
+//# Traits
+
+/// This trait is the IO interface
+pub trait IOModel {
+    /// Record a reminder and put its message in the outbox, false when it was already recorded
+    fn record(&self, reminder: &NewReminder, message: &Outgoing) -> QueryResult<bool>;
+}
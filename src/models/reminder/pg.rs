@@ -0,0 +1,32 @@
+//! implements an `IOModel` for Postgres
+use super::{IOModel, NewReminder};
+use diesel;
+use diesel::prelude::*;
+use models::outbox::Outgoing;
+use models::outbox::pg::enqueue;
+use std::slice;
+
+pub struct PgModel<'a> {
+    conn: &'a PgConnection,
+}
+impl<'a> PgModel<'a> {
+    pub fn new(conn: &'a PgConnection) -> Self {
+        PgModel { conn }
+    }
+}
+impl<'a> IOModel for PgModel<'a> {
+    fn record(&self, reminder: &NewReminder, message: &Outgoing) -> QueryResult<bool> {
+        use schema::reminders_sent::dsl::*;
+
+        self.conn.transaction(|| {
+            let recorded = diesel::insert_into(reminders_sent)
+                .values(reminder)
+                .on_conflict_do_nothing()
+                .execute(self.conn)? > 0;
+            if recorded {
+                enqueue(self.conn, slice::from_ref(message))?;
+            }
+            Ok(recorded)
+        })
+    }
+}
@@ -0,0 +1,31 @@
+//! Diesel model for the RevokedToken table
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use schema::revoked_tokens;
+use uuid::Uuid;
+
+//# Modules
+
+pub mod pg;
+
+//# Structs
+
+/// `NewRevokedToken` is the struct that is used for adding a token to the denylist
+#[derive(Insertable)]
+#[table_name = "revoked_tokens"]
+pub struct NewRevokedToken<'a> {
+    pub jti: &'a Uuid,
+    /// When the token expires, the row is dropped after that
+    pub expires_at: &'a DateTime<Utc>,
+}
+
+//# Traits
+
+/// This trait is the IO interface
+pub trait IOModel {
+    /// Add a token to the denylist, the tokens that have expired are dropped from it
+    fn revoke(&self, new_token: &NewRevokedToken) -> QueryResult<usize>;
+
+    /// true when a token is on the denylist
+    fn is_revoked(&self, jti: &Uuid) -> QueryResult<bool>;
+}
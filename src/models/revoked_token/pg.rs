@@ -0,0 +1,35 @@
+//! implements an `IOModel` for Postgres
+use super::{IOModel, NewRevokedToken};
+use chrono::Utc;
+use diesel;
+use diesel::dsl::exists;
+use diesel::prelude::*;
+use uuid::Uuid;
+
+pub struct PgModel<'a> {
+    conn: &'a PgConnection,
+}
+impl<'a> PgModel<'a> {
+    pub fn new(conn: &'a PgConnection) -> Self {
+        PgModel { conn }
+    }
+}
+impl<'a> IOModel for PgModel<'a> {
+    fn revoke(&self, new_token: &NewRevokedToken) -> QueryResult<usize> {
+        use schema::revoked_tokens::dsl::*;
+
+        self.conn.transaction(|| {
+            diesel::delete(revoked_tokens.filter(expires_at.lt(Utc::now()))).execute(self.conn)?;
+            diesel::insert_into(revoked_tokens)
+                .values(new_token)
+                .on_conflict_do_nothing()
+                .execute(self.conn)
+        })
+    }
+
+    fn is_revoked(&self, token_jti: &Uuid) -> QueryResult<bool> {
+        use schema::revoked_tokens::dsl::*;
+
+        diesel::select(exists(revoked_tokens.filter(jti.eq(token_jti)))).get_result(self.conn)
+    }
+}
@@ -1,5 +1,6 @@
 //! Diesel model for the User table
 use diesel::prelude::*;
+use models::outbox::Outgoing;
 use schema::users;
 use uuid::Uuid;
 
@@ -36,12 +37,23 @@ pub trait IOModel {
     /// Find a confirmed user
     fn find(&self, user_id: &Uuid) -> QueryResult<Option<User>>;
 
-    /// Confirm a user
-    fn confirm(&self, user_id: &Uuid) -> QueryResult<usize>;
+    /// Find a confirmed user by their email address
+    fn find_by_email(&self, email: &str) -> QueryResult<Option<User>>;
+
+    /// Confirm a user, `None` when there is no unconfirmed user with the id
+    ///
+    /// The messages that `publish` gives for the user are put in the outbox when it is confirmed
+    fn confirm<F>(&self, user_id: &Uuid, publish: F) -> QueryResult<Option<User>>
+    where
+        F: FnOnce(&User) -> Vec<Outgoing>;
 
     /// Verify a login
     fn verify_login(&self, username: &str, pass: &str) -> QueryResult<Option<User>>;
 
     /// Create a new unconfirmed user
-    fn create(&self, new_user: &NewUser) -> QueryResult<Option<User>>;
+    ///
+    /// The messages that `publish` gives for the user are put in the outbox when it is created
+    fn create<F>(&self, new_user: &NewUser, publish: F) -> QueryResult<Option<User>>
+    where
+        F: FnOnce(&User) -> Vec<Outgoing>;
 }
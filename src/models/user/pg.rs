@@ -3,6 +3,8 @@ use super::{IOModel, NewUser, User};
 use diesel;
 use diesel::prelude::*;
 use libpasta::{hash_password, verify_password};
+use models::outbox::Outgoing;
+use models::outbox::pg::enqueue;
 use uuid::Uuid;
 
 pub struct PgModel<'a> {
@@ -25,12 +27,34 @@ impl<'a> IOModel for PgModel<'a> {
             .optional()
     }
 
-    fn confirm(&self, user_id: &Uuid) -> QueryResult<usize> {
+    fn find_by_email(&self, an_email: &str) -> QueryResult<Option<User>> {
         use schema::users::dsl::*;
-        diesel::update(users)
-            .filter(id.eq(user_id))
-            .set(confirmed.eq(true))
-            .execute(self.conn)
+
+        users
+            .filter(email.eq(an_email))
+            .filter(confirmed.eq(true))
+            .first(self.conn)
+            .optional()
+    }
+
+    fn confirm<F>(&self, user_id: &Uuid, publish: F) -> QueryResult<Option<User>>
+    where
+        F: FnOnce(&User) -> Vec<Outgoing>,
+    {
+        use schema::users::dsl::*;
+
+        self.conn.transaction(|| {
+            let user = diesel::update(users)
+                .filter(id.eq(user_id))
+                .filter(confirmed.eq(false))
+                .set(confirmed.eq(true))
+                .get_result::<User>(self.conn)
+                .optional()?;
+            if let Some(ref user) = user {
+                enqueue(self.conn, &publish(user))?;
+            }
+            Ok(user)
+        })
     }
 
     fn verify_login(&self, username: &str, pass: &str) -> QueryResult<Option<User>> {
@@ -52,7 +76,10 @@ impl<'a> IOModel for PgModel<'a> {
         }))
     }
 
-    fn create(&self, new_user: &NewUser) -> QueryResult<Option<User>> {
+    fn create<F>(&self, new_user: &NewUser, publish: F) -> QueryResult<Option<User>>
+    where
+        F: FnOnce(&User) -> Vec<Outgoing>,
+    {
         use schema::users::dsl::*;
 
         // TODO: move this to the trait
@@ -70,10 +97,13 @@ impl<'a> IOModel for PgModel<'a> {
 
             match user {
                 Some(_) => Ok(None),
-                None => diesel::insert_into(users)
-                    .values(new_user)
-                    .get_result::<User>(self.conn)
-                    .optional(),
+                None => {
+                    let user = diesel::insert_into(users)
+                        .values(new_user)
+                        .get_result::<User>(self.conn)?;
+                    enqueue(self.conn, &publish(&user))?;
+                    Ok(Some(user))
+                }
             }
         })
     }
@@ -0,0 +1,85 @@
+//! Diesel model for the Venue table
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use models::page::{DateIdKey, Page, Paged};
+use schema::venues;
+use uuid::Uuid;
+
+//# Modules
+
+pub mod pg;
+
+//# Structs
+
+/// `NewVenue` is the struct that is used for storing a new venue
+#[derive(Insertable)]
+#[table_name = "venues"]
+pub struct NewVenue<'a> {
+    pub id: &'a Uuid,
+    pub owner_id: &'a Uuid,
+    pub name: &'a str,
+    pub address: &'a str,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub capacity: Option<i32>,
+    pub accessibility_notes: &'a str,
+}
+
+/// `VenueChanges` holds the fields of a venue that can be updated, `None` fields are left alone
+#[derive(AsChangeset, Default)]
+#[table_name = "venues"]
+pub struct VenueChanges<'a> {
+    pub name: Option<&'a str>,
+    pub address: Option<&'a str>,
+    /// `Some(None)` removes the location
+    pub latitude: Option<Option<f64>>,
+    pub longitude: Option<Option<f64>>,
+    /// `Some(None)` removes the capacity
+    pub capacity: Option<Option<i32>>,
+    pub accessibility_notes: Option<&'a str>,
+}
+
+/// Venue is the struct that represents a Venue record
+#[derive(Queryable)]
+pub struct Venue {
+    pub id: Uuid,
+    /// The user that created the venue, only they can change it
+    pub owner_id: Uuid,
+    pub name: String,
+    pub address: String,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    /// The most people the venue holds
+    pub capacity: Option<i32>,
+    pub accessibility_notes: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl<'a> VenueChanges<'a> {
+    /// true when there is nothing to change
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none() && self.address.is_none() && self.latitude.is_none()
+            && self.longitude.is_none() && self.capacity.is_none()
+            && self.accessibility_notes.is_none()
+    }
+}
+
+//# Traits
+
+/// This trait is the IO interface
+pub trait IOModel {
+    /// Find a venue
+    fn find(&self, venue_id: &Uuid) -> QueryResult<Option<Venue>>;
+
+    /// List the venues, the oldest first
+    fn list(&self, page: &Page<DateIdKey>) -> QueryResult<Paged<Venue, DateIdKey>>;
+
+    /// Create a new venue
+    fn create(&self, new_venue: &NewVenue) -> QueryResult<Venue>;
+
+    /// Update a venue
+    fn update(&self, venue_id: &Uuid, changes: &VenueChanges) -> QueryResult<Option<Venue>>;
+
+    /// Delete a venue, its events are left without one
+    fn delete(&self, venue_id: &Uuid) -> QueryResult<usize>;
+}
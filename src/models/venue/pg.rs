@@ -0,0 +1,63 @@
+//! implements an `IOModel` for Postgres
+use super::{IOModel, NewVenue, Venue, VenueChanges};
+use diesel;
+use diesel::prelude::*;
+use models::page::{after_date_id, DateIdKey, Page, Paged};
+use uuid::Uuid;
+
+pub struct PgModel<'a> {
+    conn: &'a PgConnection,
+}
+impl<'a> PgModel<'a> {
+    pub fn new(conn: &'a PgConnection) -> Self {
+        PgModel { conn }
+    }
+}
+impl<'a> IOModel for PgModel<'a> {
+    fn find(&self, venue_id: &Uuid) -> QueryResult<Option<Venue>> {
+        use schema::venues::dsl::*;
+
+        venues
+            .filter(id.eq(venue_id))
+            .get_result(self.conn)
+            .optional()
+    }
+
+    fn list(&self, page: &Page<DateIdKey>) -> QueryResult<Paged<Venue, DateIdKey>> {
+        use schema::venues::dsl::*;
+
+        let mut query = venues
+            .order((created_at.asc(), id.asc()))
+            .limit(page.load_limit())
+            .into_boxed();
+        if let Some(ref key) = page.after {
+            query = query.filter(after_date_id(created_at, id, key));
+        }
+
+        let rows = query.load(self.conn)?;
+        Ok(page.paged(rows, |x: &Venue| (x.created_at, x.id)))
+    }
+
+    fn create(&self, new_venue: &NewVenue) -> QueryResult<Venue> {
+        use schema::venues::dsl::*;
+
+        diesel::insert_into(venues)
+            .values(new_venue)
+            .get_result(self.conn)
+    }
+
+    fn update(&self, venue_id: &Uuid, changes: &VenueChanges) -> QueryResult<Option<Venue>> {
+        use schema::venues::dsl::*;
+
+        diesel::update(venues.filter(id.eq(venue_id)))
+            .set(changes)
+            .get_result(self.conn)
+            .optional()
+    }
+
+    fn delete(&self, venue_id: &Uuid) -> QueryResult<usize> {
+        use schema::venues::dsl::*;
+
+        diesel::delete(venues.filter(id.eq(venue_id))).execute(self.conn)
+    }
+}
@@ -0,0 +1,243 @@
+//! Diesel model for the Webhook tables
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use models::page::{DateIdKey, Page, Paged};
+use schema::{webhook_deliveries, webhooks};
+use std::fmt;
+use std::str::FromStr;
+use uuid::Uuid;
+
+//# Modules
+
+pub mod pg;
+
+//# Enums
+
+/// The domain events that webhooks can subscribe to
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum WebhookTopic {
+    #[serde(rename = "user.registered")]
+    UserRegistered,
+    #[serde(rename = "user.confirmed")]
+    UserConfirmed,
+    #[serde(rename = "event.created")]
+    EventCreated,
+    #[serde(rename = "event.updated")]
+    EventUpdated,
+    /// Someone RSVP'd to an event, changed their answer or took it back
+    #[serde(rename = "rsvp.changed")]
+    RsvpChanged,
+}
+
+impl WebhookTopic {
+    /// every topic, in the order they are documented
+    pub const ALL: [WebhookTopic; 5] = [
+        WebhookTopic::UserRegistered,
+        WebhookTopic::UserConfirmed,
+        WebhookTopic::EventCreated,
+        WebhookTopic::EventUpdated,
+        WebhookTopic::RsvpChanged,
+    ];
+
+    /// the value that is stored in the `topic` and `topics` columns
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            WebhookTopic::UserRegistered => "user.registered",
+            WebhookTopic::UserConfirmed => "user.confirmed",
+            WebhookTopic::EventCreated => "event.created",
+            WebhookTopic::EventUpdated => "event.updated",
+            WebhookTopic::RsvpChanged => "rsvp.changed",
+        }
+    }
+}
+
+impl FromStr for WebhookTopic {
+    type Err = InvalidWebhookTopic;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        WebhookTopic::ALL
+            .iter()
+            .find(|x| x.as_str() == s)
+            .cloned()
+            .ok_or(InvalidWebhookTopic)
+    }
+}
+#[test]
+fn test_webhook_topic_from_str() {
+    for topic in &WebhookTopic::ALL {
+        assert_eq!(WebhookTopic::from_str(topic.as_str()).unwrap(), *topic);
+    }
+    assert_eq!(WebhookTopic::from_str("user.deleted").unwrap_err(), InvalidWebhookTopic);
+}
+
+/// returned when a string is not one of the `WebhookTopic` values
+#[derive(Debug, PartialEq)]
+pub struct InvalidWebhookTopic;
+
+impl fmt::Display for InvalidWebhookTopic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid webhook topic")
+    }
+}
+
+/// How the last attempt at a delivery went
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    /// It has not been attempted yet
+    Pending,
+    Delivered,
+    /// The last attempt failed, it is retried until its outbox message is dead
+    Failed,
+}
+
+impl DeliveryStatus {
+    /// the value that is stored in the `status` column
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            DeliveryStatus::Pending => "pending",
+            DeliveryStatus::Delivered => "delivered",
+            DeliveryStatus::Failed => "failed",
+        }
+    }
+}
+
+impl FromStr for DeliveryStatus {
+    type Err = InvalidDeliveryStatus;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(DeliveryStatus::Pending),
+            "delivered" => Ok(DeliveryStatus::Delivered),
+            "failed" => Ok(DeliveryStatus::Failed),
+            _ => Err(InvalidDeliveryStatus),
+        }
+    }
+}
+#[test]
+fn test_delivery_status_from_str() {
+    for status in &[
+        DeliveryStatus::Pending,
+        DeliveryStatus::Delivered,
+        DeliveryStatus::Failed,
+    ] {
+        assert_eq!(DeliveryStatus::from_str(status.as_str()).unwrap(), *status);
+    }
+    assert_eq!(DeliveryStatus::from_str("sent").unwrap_err(), InvalidDeliveryStatus);
+}
+
+/// returned when a string is not one of the `DeliveryStatus` values
+#[derive(Debug, PartialEq)]
+pub struct InvalidDeliveryStatus;
+
+impl fmt::Display for InvalidDeliveryStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid delivery status")
+    }
+}
+
+//# Structs
+
+/// `NewWebhook` is the struct that is used for storing a new subscription
+#[derive(Insertable)]
+#[table_name = "webhooks"]
+pub struct NewWebhook<'a> {
+    pub id: &'a Uuid,
+    pub owner_id: &'a Uuid,
+    pub url: &'a str,
+    /// The key the payloads are signed with
+    pub secret: &'a str,
+    /// The topics to deliver, all of them when it is empty
+    pub topics: &'a [String],
+}
+
+/// Webhook is the struct that represents a Webhook record
+#[derive(Queryable)]
+pub struct Webhook {
+    pub id: Uuid,
+    pub owner_id: Uuid,
+    pub url: String,
+    pub secret: String,
+    pub topics: Vec<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// `NewDelivery` is the struct that is used for logging a delivery
+#[derive(Insertable)]
+#[table_name = "webhook_deliveries"]
+pub struct NewDelivery<'a> {
+    pub id: &'a Uuid,
+    pub webhook_id: &'a Uuid,
+    pub topic: &'a str,
+    pub payload: &'a str,
+}
+
+/// Delivery is the struct that represents a WebhookDelivery record
+#[derive(Queryable)]
+pub struct Delivery {
+    pub id: Uuid,
+    pub webhook_id: Uuid,
+    pub topic: String,
+    /// The JSON body that is POSTed
+    pub payload: String,
+    pub status: String,
+    /// The number of times it was POSTed
+    pub attempts: i32,
+    /// The HTTP status of the last attempt, `None` when the webhook could not be reached
+    pub response_status: Option<i32>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+impl Delivery {
+    pub fn delivery_status(&self) -> DeliveryStatus {
+        // The status_valid constraint keeps anything else out of the table
+        DeliveryStatus::from_str(&self.status).unwrap_or(DeliveryStatus::Pending)
+    }
+}
+
+//# Traits
+
+/// This trait is the IO interface
+pub trait IOModel {
+    /// Find a subscription
+    fn find(&self, webhook_id: &Uuid) -> QueryResult<Option<Webhook>>;
+
+    /// List a user's subscriptions, the oldest first
+    fn list(
+        &self,
+        owner_id: &Uuid,
+        page: &Page<DateIdKey>,
+    ) -> QueryResult<Paged<Webhook, DateIdKey>>;
+
+    /// Create a new subscription
+    fn create(&self, new_webhook: &NewWebhook) -> QueryResult<Webhook>;
+
+    /// Delete a subscription along with its delivery log
+    fn delete(&self, webhook_id: &Uuid) -> QueryResult<usize>;
+
+    /// Log a delivery of a payload to every subscription to its topic and put them in the outbox
+    fn fan_out(&self, topic: &str, payload: &str) -> QueryResult<usize>;
+
+    /// Find a delivery along with its subscription
+    fn delivery(&self, delivery_id: &Uuid) -> QueryResult<Option<(Delivery, Webhook)>>;
+
+    /// List the delivery log of a subscription, the oldest first
+    fn deliveries(
+        &self,
+        webhook_id: &Uuid,
+        page: &Page<DateIdKey>,
+    ) -> QueryResult<Paged<Delivery, DateIdKey>>;
+
+    /// Record an attempt at a delivery, it is delivered when there is no `error`
+    fn record_attempt(
+        &self,
+        delivery_id: &Uuid,
+        response_status: Option<i32>,
+        error: Option<&str>,
+    ) -> QueryResult<usize>;
+
+    /// Put a delivery of a subscription back in the outbox, `None` when there is no such delivery
+    fn redeliver(&self, webhook_id: &Uuid, delivery_id: &Uuid) -> QueryResult<Option<Delivery>>;
+}
@@ -0,0 +1,185 @@
+//! implements an `IOModel` for Postgres
+use super::{Delivery, DeliveryStatus, IOModel, NewDelivery, NewWebhook, Webhook};
+use chrono::Utc;
+use diesel;
+use diesel::prelude::*;
+use models::outbox::{MessageKind, Outgoing};
+use models::outbox::pg::enqueue;
+use models::page::{after_date_id, DateIdKey, Page, Paged};
+use schema::webhooks;
+use serde_json;
+use uuid::Uuid;
+
+pub struct PgModel<'a> {
+    conn: &'a PgConnection,
+}
+impl<'a> PgModel<'a> {
+    pub fn new(conn: &'a PgConnection) -> Self {
+        PgModel { conn }
+    }
+}
+impl<'a> IOModel for PgModel<'a> {
+    fn find(&self, webhook_id: &Uuid) -> QueryResult<Option<Webhook>> {
+        use schema::webhooks::dsl::*;
+
+        webhooks
+            .filter(id.eq(webhook_id))
+            .get_result(self.conn)
+            .optional()
+    }
+
+    fn list(
+        &self,
+        an_owner_id: &Uuid,
+        page: &Page<DateIdKey>,
+    ) -> QueryResult<Paged<Webhook, DateIdKey>> {
+        use schema::webhooks::dsl::*;
+
+        let mut query = webhooks
+            .filter(owner_id.eq(an_owner_id))
+            .order((created_at.asc(), id.asc()))
+            .limit(page.load_limit())
+            .into_boxed();
+        if let Some(ref key) = page.after {
+            query = query.filter(after_date_id(created_at, id, key));
+        }
+
+        let rows = query.load(self.conn)?;
+        Ok(page.paged(rows, |x: &Webhook| (x.created_at, x.id)))
+    }
+
+    fn create(&self, new_webhook: &NewWebhook) -> QueryResult<Webhook> {
+        use schema::webhooks::dsl::*;
+
+        diesel::insert_into(webhooks)
+            .values(new_webhook)
+            .get_result(self.conn)
+    }
+
+    fn delete(&self, webhook_id: &Uuid) -> QueryResult<usize> {
+        use schema::webhooks::dsl::*;
+
+        diesel::delete(webhooks.filter(id.eq(webhook_id))).execute(self.conn)
+    }
+
+    fn fan_out(&self, a_topic: &str, a_payload: &str) -> QueryResult<usize> {
+        use schema::webhook_deliveries::dsl::*;
+
+        self.conn.transaction(|| {
+            let subscriptions: Vec<Uuid> = webhooks::table
+                .select(webhooks::id)
+                .filter(
+                    webhooks::topics
+                        .eq(Vec::<String>::new())
+                        .or(webhooks::topics.contains(vec![a_topic])),
+                )
+                .load(self.conn)?;
+            let ids = subscriptions
+                .iter()
+                .map(|_| Uuid::new_v4())
+                .collect::<Vec<_>>();
+
+            let new_deliveries = subscriptions
+                .iter()
+                .zip(&ids)
+                .map(|(a_webhook_id, an_id)| NewDelivery {
+                    id: an_id,
+                    webhook_id: a_webhook_id,
+                    topic: a_topic,
+                    payload: a_payload,
+                })
+                .collect::<Vec<_>>();
+            if !new_deliveries.is_empty() {
+                diesel::insert_into(webhook_deliveries)
+                    .values(&new_deliveries)
+                    .execute(self.conn)?;
+            }
+            enqueue(self.conn, &ids.iter().map(delivery_message).collect::<Vec<_>>())
+        })
+    }
+
+    fn delivery(&self, delivery_id: &Uuid) -> QueryResult<Option<(Delivery, Webhook)>> {
+        use schema::webhook_deliveries::dsl::*;
+
+        webhook_deliveries
+            .inner_join(webhooks::table)
+            .filter(id.eq(delivery_id))
+            .get_result(self.conn)
+            .optional()
+    }
+
+    fn deliveries(
+        &self,
+        a_webhook_id: &Uuid,
+        page: &Page<DateIdKey>,
+    ) -> QueryResult<Paged<Delivery, DateIdKey>> {
+        use schema::webhook_deliveries::dsl::*;
+
+        let mut query = webhook_deliveries
+            .filter(webhook_id.eq(a_webhook_id))
+            .order((created_at.asc(), id.asc()))
+            .limit(page.load_limit())
+            .into_boxed();
+        if let Some(ref key) = page.after {
+            query = query.filter(after_date_id(created_at, id, key));
+        }
+
+        let rows = query.load(self.conn)?;
+        Ok(page.paged(rows, |x: &Delivery| (x.created_at, x.id)))
+    }
+
+    fn record_attempt(
+        &self,
+        delivery_id: &Uuid,
+        a_response_status: Option<i32>,
+        error: Option<&str>,
+    ) -> QueryResult<usize> {
+        use schema::webhook_deliveries::dsl::*;
+
+        let (a_status, a_delivered_at) = match error {
+            Some(_) => (DeliveryStatus::Failed, None),
+            None => (DeliveryStatus::Delivered, Some(Utc::now())),
+        };
+        diesel::update(webhook_deliveries.filter(id.eq(delivery_id)))
+            .set((
+                status.eq(a_status.as_str()),
+                attempts.eq(attempts + 1),
+                response_status.eq(a_response_status),
+                last_error.eq(error),
+                delivered_at.eq(a_delivered_at),
+            ))
+            .execute(self.conn)
+    }
+
+    fn redeliver(
+        &self,
+        a_webhook_id: &Uuid,
+        delivery_id: &Uuid,
+    ) -> QueryResult<Option<Delivery>> {
+        use schema::webhook_deliveries::dsl::*;
+
+        self.conn.transaction(|| {
+            let delivery = diesel::update(
+                webhook_deliveries
+                    .filter(id.eq(delivery_id))
+                    .filter(webhook_id.eq(a_webhook_id)),
+            ).set(status.eq(DeliveryStatus::Pending.as_str()))
+                .get_result::<Delivery>(self.conn)
+                .optional()?;
+            if let Some(ref delivery) = delivery {
+                enqueue(self.conn, &[delivery_message(&delivery.id)])?;
+            }
+            Ok(delivery)
+        })
+    }
+}
+
+// Internal
+
+/// the outbox message that POSTs a delivery
+fn delivery_message(delivery_id: &Uuid) -> Outgoing {
+    Outgoing {
+        kind: MessageKind::WebhookDelivery,
+        payload: serde_json::to_string(delivery_id).expect("an id is always valid JSON"),
+    }
+}
@@ -0,0 +1,90 @@
+//! Delivery of the messages that are sent to users outside of the API
+use config::NotifierConfig;
+use serde_json;
+use std::fmt;
+use std::io::{self, Write};
+use uuid::Uuid;
+
+//# Modules
+
+pub mod smtp;
+pub mod webhook;
+
+/// a message for one user
+///
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Notification {
+    pub user_id: Uuid,
+    pub email: String,
+    pub name: String,
+    /// What the notification is about, e.g. `reminder`, so receivers can tell them apart
+    pub kind: String,
+    pub subject: String,
+    pub body: String,
+}
+
+impl Notification {
+    /// the JSON that is stored in the outbox for the notification
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("a notification is always valid JSON")
+    }
+}
+
+/// returned when a notification could not be delivered
+#[derive(Debug, Fail)]
+pub struct NotifyError(pub String);
+
+impl fmt::Display for NotifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "could not deliver the notification: {}", self.0)
+    }
+}
+
+impl From<io::Error> for NotifyError {
+    fn from(it: io::Error) -> Self {
+        NotifyError(it.to_string())
+    }
+}
+
+/// This trait is the interface for delivering notifications
+pub trait Notifier: Send + Sync {
+    /// Deliver a notification, an error means it may be tried again later
+    fn notify(&self, notification: &Notification) -> Result<(), NotifyError>;
+}
+
+/// writes the notifications to stderr, it is meant for development
+pub struct LogNotifier;
+
+impl Notifier for LogNotifier {
+    fn notify(&self, notification: &Notification) -> Result<(), NotifyError> {
+        writeln!(
+            io::stderr(),
+            "Notify {} <{}> [{}]: {}\n{}",
+            notification.name,
+            notification.email,
+            notification.kind,
+            notification.subject,
+            notification.body
+        ).map_err(|x| NotifyError(x.to_string()))
+    }
+}
+
+/// picks the notifier by its `kind`, which is one of `log`, `smtp` or `webhook`
+///
+/// The settings were checked when the config was loaded, the log notifier is the default
+pub fn from_config(config: &NotifierConfig) -> Box<dyn Notifier> {
+    match config.kind.as_str() {
+        "smtp" => {
+            let from = config.smtp_from.as_ref().map_or("", String::as_str);
+            let credentials = config.smtp_username.clone().map(|x| {
+                (x, config.smtp_password.clone().unwrap_or_default())
+            });
+            Box::new(smtp::SmtpNotifier::new(&config.smtp_address, from, credentials))
+        }
+        "webhook" => {
+            let url = config.webhook_url.as_ref().map_or("", String::as_str);
+            Box::new(webhook::WebhookNotifier::new(url))
+        }
+        _ => Box::new(LogNotifier),
+    }
+}
@@ -0,0 +1,161 @@
+//! delivers notifications as plain text email over SMTP
+//!
+//! This speaks just enough SMTP to hand messages to a relay or a local sink like MailHog, it does
+//! not do STARTTLS so the relay should be on the same host or a private network
+use super::{Notification, NotifyError, Notifier};
+use base64;
+use chrono::Utc;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+/// how long to wait on the SMTP server before giving up
+const TIMEOUT_SECS: u64 = 30;
+
+/// sends the notifications to an SMTP server
+pub struct SmtpNotifier {
+    /// The `host:port` of the SMTP server
+    address: String,
+    /// The address the email is sent from
+    from: String,
+    /// The username and password for `AUTH PLAIN`, if the server needs them
+    credentials: Option<(String, String)>,
+}
+
+impl SmtpNotifier {
+    pub fn new(address: &str, from: &str, credentials: Option<(String, String)>) -> Self {
+        SmtpNotifier {
+            address: address.to_string(),
+            from: from.to_string(),
+            credentials,
+        }
+    }
+
+    // Internal
+
+    fn send(&self, notification: &Notification) -> Result<(), NotifyError> {
+        if !is_address(&self.from) || !is_address(&notification.email) {
+            return Err(NotifyError("invalid email address".to_string()));
+        }
+
+        let timeout = Some(Duration::from_secs(TIMEOUT_SECS));
+        let mut writer = TcpStream::connect(&self.address)?;
+        writer.set_read_timeout(timeout)?;
+        writer.set_write_timeout(timeout)?;
+        let reader = &mut BufReader::new(writer.try_clone()?);
+
+        expect(reader, '2')?;
+        command(&mut writer, reader, "EHLO localhost", '2')?;
+        if let Some((ref username, ref password)) = self.credentials {
+            let plain = base64::encode(&format!("\0{}\0{}", username, password));
+            command(&mut writer, reader, &format!("AUTH PLAIN {}", plain), '2')?;
+        }
+        command(&mut writer, reader, &format!("MAIL FROM:<{}>", self.from), '2')?;
+        command(&mut writer, reader, &format!("RCPT TO:<{}>", notification.email), '2')?;
+        command(&mut writer, reader, "DATA", '3')?;
+        writer.write_all(message(&self.from, notification).as_bytes())?;
+        command(&mut writer, reader, ".", '2')?;
+        command(&mut writer, reader, "QUIT", '2')
+    }
+}
+
+impl Notifier for SmtpNotifier {
+    fn notify(&self, notification: &Notification) -> Result<(), NotifyError> {
+        self.send(notification)
+            .map_err(|x| NotifyError(format!("SMTP {}: {}", self.address, x.0)))
+    }
+}
+
+/// writes one SMTP command and checks the class of the reply, e.g. '2' for a 250
+fn command<W: Write, R: BufRead>(
+    writer: &mut W,
+    reader: &mut R,
+    line: &str,
+    class: char,
+) -> Result<(), NotifyError> {
+    writer.write_all(format!("{}\r\n", line).as_bytes())?;
+    writer.flush()?;
+    expect(reader, class)
+}
+
+/// reads a reply, which may span several lines, and checks its class
+fn expect<R: BufRead>(reader: &mut R, class: char) -> Result<(), NotifyError> {
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(NotifyError("connection closed".to_string()));
+        }
+        // The last line of a reply has a space after the code instead of a dash
+        if line.len() < 4 || line.as_bytes()[3] != b'-' {
+            return if line.starts_with(class) {
+                Ok(())
+            } else {
+                Err(NotifyError(line.trim_end().to_string()))
+            };
+        }
+    }
+}
+
+/// true when an address can be put between the angle brackets of an SMTP command
+fn is_address(address: &str) -> bool {
+    address.contains('@')
+        && !address
+            .chars()
+            .any(|x| x.is_control() || x.is_whitespace() || x == '<' || x == '>')
+}
+
+/// the headers and body of the email, ready to be sent after `DATA`
+///
+/// The lines end in CRLF and the ones that start with a dot are doubled so they can't end the
+/// message early
+fn message(from: &str, notification: &Notification) -> String {
+    let headers = format!(
+        "From: {}\r\nTo: {} <{}>\r\nSubject: {}\r\nDate: {}\r\nMIME-Version: 1.0\r\n\
+         Content-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: 8bit\r\n\r\n",
+        from,
+        header_value(&notification.name),
+        notification.email,
+        header_value(&notification.subject),
+        Utc::now().to_rfc2822()
+    );
+    let body = notification
+        .body
+        .lines()
+        .map(|x| if x.starts_with('.') { format!(".{}\r\n", x) } else { format!("{}\r\n", x) })
+        .collect::<String>();
+
+    headers + &body
+}
+#[test]
+fn test_message() {
+    use uuid::Uuid;
+
+    let notification = Notification {
+        user_id: Uuid::new_v4(),
+        email: "jo@example.com".to_string(),
+        name: "Jo\r\nBcc: all@example.com".to_string(),
+        kind: "test".to_string(),
+        subject: "Café".to_string(),
+        body: "Hi\n.\n..hidden".to_string(),
+    };
+    let message = message("events@example.com", &notification);
+
+    assert!(message.contains("\r\nTo: =?utf-8?B?Sm8gIEJjYzogYWxsQGV4YW1wbGUuY29t?= <jo@"));
+    assert!(message.contains("\r\nSubject: =?utf-8?B?Q2Fmw6k=?=\r\n"));
+    assert!(message.ends_with("\r\n\r\nHi\r\n..\r\n...hidden\r\n"));
+    assert!(is_address("jo@example.com"));
+    assert!(!is_address("jo@example.com>\r\nDATA"));
+}
+
+/// a header value that can't start a new header, it is encoded when it isn't plain ASCII
+fn header_value(value: &str) -> String {
+    if value.chars().all(|x| x.is_ascii() && !x.is_control()) {
+        value.to_string()
+    } else {
+        let value = value
+            .chars()
+            .map(|x| if x.is_control() { ' ' } else { x })
+            .collect::<String>();
+        format!("=?utf-8?B?{}?=", base64::encode(&value))
+    }
+}
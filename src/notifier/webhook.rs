@@ -0,0 +1,140 @@
+//! delivers notifications by POSTing them as JSON to a URL
+use super::{Notification, NotifyError, Notifier};
+use serde_json;
+use std::io;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::time::Duration;
+use ureq;
+
+/// how long to wait on the webhook before giving up
+const TIMEOUT_MILLIS: u64 = 30_000;
+
+/// sends each notification to a webhook, any 2xx response means it was delivered
+pub struct WebhookNotifier {
+    url: String,
+}
+
+impl WebhookNotifier {
+    pub fn new(url: &str) -> Self {
+        WebhookNotifier {
+            url: url.to_string(),
+        }
+    }
+}
+
+impl Notifier for WebhookNotifier {
+    fn notify(&self, notification: &Notification) -> Result<(), NotifyError> {
+        let body = serde_json::to_string(notification).map_err(|x| NotifyError(x.to_string()))?;
+        match post_json(&self.url, &body, &[])? {
+            200..=299 => Ok(()),
+            status => Err(NotifyError(format!("webhook {}: HTTP {}", self.url, status))),
+        }
+    }
+}
+
+/// POSTs a JSON body with some extra headers and returns the HTTP status of the response
+///
+/// An error means that the URL could not be reached
+pub fn post_json(url: &str, body: &str, headers: &[(&str, &str)]) -> Result<u16, NotifyError> {
+    post(agent().build(), url, body, headers)
+}
+
+/// like `post_json`, but the URL may only resolve to public addresses
+///
+/// The addresses are checked as the connection is made, so a host that resolved to a public
+/// address when it was subscribed can't be pointed at the private network later.
+pub fn post_json_public(
+    url: &str,
+    body: &str,
+    headers: &[(&str, &str)],
+) -> Result<u16, NotifyError> {
+    post(agent().resolver(resolve_public).build(), url, body, headers)
+}
+
+/// the addresses of a `host:port`, an error when any of them isn't public
+pub fn resolve_public(address: &str) -> io::Result<Vec<SocketAddr>> {
+    let addresses = address.to_socket_addrs()?.collect::<Vec<_>>();
+    if addresses.iter().any(|x| !is_public(x.ip())) {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} is not a public address", address),
+        ));
+    }
+
+    Ok(addresses)
+}
+
+// Internal
+
+fn agent() -> ureq::AgentBuilder {
+    let timeout = Duration::from_millis(TIMEOUT_MILLIS);
+    ureq::AgentBuilder::new()
+        .timeout_connect(timeout)
+        .timeout_read(timeout)
+        .timeout_write(timeout)
+}
+
+fn post(
+    agent: ureq::Agent,
+    url: &str,
+    body: &str,
+    headers: &[(&str, &str)],
+) -> Result<u16, NotifyError> {
+    let mut request = agent.post(url).set("Content-Type", "application/json");
+    for &(name, value) in headers {
+        request = request.set(name, value);
+    }
+
+    match request.send_string(body) {
+        Ok(response) => Ok(response.status()),
+        Err(ureq::Error::Status(status, _)) => Ok(status),
+        Err(e) => Err(NotifyError(format!("webhook {}: {}", url, e))),
+    }
+}
+
+/// false for loopback, private, link-local and other addresses that aren't on the internet
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(x) => {
+            let [a, b, ..] = x.octets();
+            !(x.is_unspecified()
+                || x.is_loopback()
+                || x.is_private()
+                || x.is_link_local()
+                || x.is_broadcast()
+                || x.is_multicast()
+                || x.is_documentation()
+                // 100.64.0.0/10 is shared by carrier-grade NATs
+                || (a == 100 && (b & 0xc0) == 64))
+        }
+        IpAddr::V6(x) => match x.to_ipv4_mapped() {
+            Some(x) => is_public(IpAddr::V4(x)),
+            None => {
+                let first = x.segments()[0];
+                !(x.is_unspecified()
+                    || x.is_loopback()
+                    || x.is_multicast()
+                    // fc00::/7 is unique local and fe80::/10 is link-local
+                    || (first & 0xfe00) == 0xfc00
+                    || (first & 0xffc0) == 0xfe80)
+            }
+        },
+    }
+}
+#[test]
+fn test_is_public() {
+    let public = |x: &str| is_public(x.parse().unwrap());
+    assert!(public("93.184.216.34"));
+    assert!(public("2606:2800:220:1:248:1893:25c8:1946"));
+    assert!(!public("127.0.0.1"));
+    assert!(!public("10.1.2.3"));
+    assert!(!public("172.16.0.1"));
+    assert!(!public("192.168.1.1"));
+    assert!(!public("169.254.169.254"));
+    assert!(!public("100.64.0.1"));
+    assert!(!public("0.0.0.0"));
+    assert!(!public("::1"));
+    assert!(!public("fd00::1"));
+    assert!(!public("fe80::1"));
+    assert!(!public("::ffff:127.0.0.1"));
+}
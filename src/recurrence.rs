@@ -0,0 +1,600 @@
+//! RFC 5545 recurrence rules
+//!
+//! This supports the subset of [RRULE](https://tools.ietf.org/html/rfc5545#section-3.3.10)
+//! that we need for repeating events: `FREQ`, `INTERVAL`, `COUNT`, `UNTIL`, `BYDAY` and
+//! `BYMONTHDAY`.  Occurrences are expanded on the wall clock of the first occurrence so that an
+//! event at 9am stays at 9am.
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday};
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+/// The upper limit on how many periods we walk through while expanding a rule
+///
+/// This keeps a rule like `FREQ=YEARLY;BYMONTHDAY=31;BYDAY=MO` from looping forever
+const MAX_PERIODS: u32 = 10_000;
+
+/// The largest `INTERVAL` we accept, anything bigger is a mistake rather than a schedule
+const MAX_INTERVAL: u32 = 1_000;
+
+/// errors that can happen when parsing a rule
+#[derive(Debug, PartialEq)]
+pub enum RecurrenceError {
+    MissingFrequency,
+    InvalidPart(String),
+}
+
+impl fmt::Display for RecurrenceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// The `FREQ` of a rule
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// A `BYDAY` entry, like `MO` or `-1FR` for the last Friday of the month
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WeekdayNum {
+    pub ordinal: Option<i32>,
+    pub weekday: Weekday,
+}
+
+/// A parsed `RRULE`
+#[derive(Debug, Clone, PartialEq)]
+pub struct RRule {
+    pub frequency: Frequency,
+    pub interval: u32,
+    pub count: Option<u32>,
+    /// Date-only and floating `UNTIL` values are treated as UTC
+    pub until: Option<DateTime<Utc>>,
+    pub by_day: Vec<WeekdayNum>,
+    pub by_month_day: Vec<i32>,
+}
+
+impl FromStr for RRule {
+    type Err = RecurrenceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let s = match s.get(..6) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("RRULE:") => &s[6..],
+            _ => s,
+        };
+
+        let mut frequency = None;
+        let mut rule = RRule {
+            frequency: Frequency::Daily,
+            interval: 1,
+            count: None,
+            until: None,
+            by_day: vec![],
+            by_month_day: vec![],
+        };
+
+        for part in s.split(';').filter(|x| !x.is_empty()) {
+            let invalid = || RecurrenceError::InvalidPart(part.into());
+            let mut kv = part.splitn(2, '=');
+            let key = kv.next().unwrap_or("").to_uppercase();
+            let value = kv.next().ok_or_else(invalid)?.to_uppercase();
+
+            match key.as_str() {
+                "FREQ" => frequency = Some(parse_frequency(&value).ok_or_else(invalid)?),
+                "INTERVAL" => {
+                    rule.interval = value.parse().map_err(|_| invalid())?;
+                    if rule.interval == 0 || rule.interval > MAX_INTERVAL {
+                        return Err(invalid());
+                    }
+                }
+                "COUNT" => rule.count = Some(value.parse().map_err(|_| invalid())?),
+                "UNTIL" => rule.until = Some(parse_until(&value).ok_or_else(invalid)?),
+                "BYDAY" => {
+                    rule.by_day = value
+                        .split(',')
+                        .map(parse_weekday_num)
+                        .collect::<Option<_>>()
+                        .ok_or_else(invalid)?
+                }
+                "BYMONTHDAY" => {
+                    rule.by_month_day = value
+                        .split(',')
+                        .map(|x| x.parse().ok().filter(|x: &i32| *x != 0 && x.abs() <= 31))
+                        .collect::<Option<_>>()
+                        .ok_or_else(invalid)?
+                }
+                "WKST" if value == "MO" => (),
+                _ => return Err(invalid()),
+            }
+        }
+
+        rule.frequency = frequency.ok_or(RecurrenceError::MissingFrequency)?;
+
+        // Ordinal weekdays only make sense for monthly and yearly rules
+        let has_ordinal = rule.by_day.iter().any(|x| x.ordinal.is_some());
+        if has_ordinal && (rule.frequency == Frequency::Daily || rule.frequency == Frequency::Weekly)
+        {
+            return Err(RecurrenceError::InvalidPart("BYDAY".into()));
+        }
+        if rule.count.is_some() && rule.until.is_some() {
+            return Err(RecurrenceError::InvalidPart("UNTIL".into()));
+        }
+
+        Ok(rule)
+    }
+}
+#[test]
+fn test_rrule_from_str() {
+    assert_eq!(
+        RRule::from_str("RRULE:FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,WE;COUNT=4").unwrap(),
+        RRule {
+            frequency: Frequency::Weekly,
+            interval: 2,
+            count: Some(4),
+            until: None,
+            by_day: vec![
+                WeekdayNum {
+                    ordinal: None,
+                    weekday: Weekday::Mon,
+                },
+                WeekdayNum {
+                    ordinal: None,
+                    weekday: Weekday::Wed,
+                },
+            ],
+            by_month_day: vec![],
+        }
+    );
+
+    assert_eq!(
+        RRule::from_str("FREQ=MONTHLY;BYDAY=-1FR;UNTIL=20181231")
+            .unwrap()
+            .until,
+        Some(Utc.ymd(2018, 12, 31).and_hms(0, 0, 0))
+    );
+
+    assert_eq!(
+        RRule::from_str("INTERVAL=2").unwrap_err(),
+        RecurrenceError::MissingFrequency
+    );
+    assert_eq!(
+        RRule::from_str("FREQ=WEEKLY;BYDAY=1MO").unwrap_err(),
+        RecurrenceError::InvalidPart("BYDAY".into())
+    );
+    assert_eq!(
+        RRule::from_str("FREQ=HOURLY").unwrap_err(),
+        RecurrenceError::InvalidPart("FREQ=HOURLY".into())
+    );
+    assert_eq!(
+        RRule::from_str("FREQ=DAILY;BYMONTHDAY=32").unwrap_err(),
+        RecurrenceError::InvalidPart("BYMONTHDAY=32".into())
+    );
+    assert_eq!(
+        RRule::from_str("FREQ=WEEKLY;BYDAY=éA").unwrap_err(),
+        RecurrenceError::InvalidPart("BYDAY=éA".into())
+    );
+    assert_eq!(
+        RRule::from_str("FREQ=DAILY;INTERVAL=100000000").unwrap_err(),
+        RecurrenceError::InvalidPart("INTERVAL=100000000".into())
+    );
+}
+
+impl fmt::Display for RRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let frequency = match self.frequency {
+            Frequency::Daily => "DAILY",
+            Frequency::Weekly => "WEEKLY",
+            Frequency::Monthly => "MONTHLY",
+            Frequency::Yearly => "YEARLY",
+        };
+        write!(f, "FREQ={}", frequency)?;
+        if self.interval != 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        if let Some(count) = self.count {
+            write!(f, ";COUNT={}", count)?;
+        }
+        if let Some(until) = self.until {
+            write!(f, ";UNTIL={}", until.format("%Y%m%dT%H%M%SZ"))?;
+        }
+        if !self.by_day.is_empty() {
+            let days: Vec<String> = self.by_day
+                .iter()
+                .map(|x| match x.ordinal {
+                    Some(n) => format!("{}{}", n, weekday_code(x.weekday)),
+                    None => weekday_code(x.weekday).into(),
+                })
+                .collect();
+            write!(f, ";BYDAY={}", days.join(","))?;
+        }
+        if !self.by_month_day.is_empty() {
+            let days: Vec<String> = self.by_month_day.iter().map(i32::to_string).collect();
+            write!(f, ";BYMONTHDAY={}", days.join(","))?;
+        }
+        Ok(())
+    }
+}
+#[test]
+fn test_rrule_display() {
+    let rule = "FREQ=MONTHLY;INTERVAL=3;UNTIL=20181231T235959Z;BYDAY=-1FR,MO;BYMONTHDAY=1,-1";
+    assert_eq!(RRule::from_str(rule).unwrap().to_string(), rule);
+}
+
+impl RRule {
+    /// the start times of the occurrences that begin at or after `from` and before `to`
+    ///
+    /// `dtstart` is the start of the first occurrence, like RFC 5545 says it is always part of the
+    /// series and counts toward `COUNT`, even when it doesn't match the rule.  Any occurrence that
+    /// starts at one of the `exdates` is left out.
+    pub fn between<Tz: TimeZone>(
+        &self,
+        dtstart: &DateTime<Tz>,
+        from: &DateTime<Tz>,
+        to: &DateTime<Tz>,
+        exdates: &[DateTime<Utc>],
+    ) -> Vec<DateTime<Tz>> {
+        self.starts_from(dtstart, from, exdates)
+            .take_while(|x| x < to)
+            .collect()
+    }
+
+    /// the start times of the occurrences that begin at or after `from`, in order
+    ///
+    /// Like `between`, `dtstart` is always part of the series and the `exdates` are left out.  It
+    /// ends with the series, or after the last period we walk through when the series doesn't.
+    pub fn starts_from<'a, Tz: TimeZone + 'a>(
+        &'a self,
+        dtstart: &DateTime<Tz>,
+        from: &DateTime<Tz>,
+        exdates: &'a [DateTime<Utc>],
+    ) -> impl Iterator<Item = DateTime<Tz>> + 'a {
+        let tz = dtstart.timezone();
+        let from = from.clone();
+
+        self.iter(dtstart.naive_local())
+            // Skip wall clock times that don't exist, like 2:30am on the day DST starts
+            .filter_map(move |local| tz.from_local_datetime(&local).earliest())
+            .take_while(move |start| {
+                self.until.is_none_or(|until| start.with_timezone(&Utc) <= until)
+            })
+            .filter(move |start| *start >= from && !exdates.contains(&start.with_timezone(&Utc)))
+    }
+
+    /// true when `start` is one of the occurrences of the series, ignoring any exdates
+    pub fn includes<Tz: TimeZone>(&self, dtstart: &DateTime<Tz>, start: &DateTime<Tz>) -> bool {
+        let to = start.clone() + Duration::seconds(1);
+        self.between(dtstart, start, &to, &[])
+            .iter()
+            .any(|x| x == start)
+    }
+
+    /// walks through every occurrence on the wall clock, honoring `COUNT` but not `UNTIL`
+    fn iter<'a>(&'a self, dtstart: NaiveDateTime) -> Occurrences<'a> {
+        Occurrences {
+            rule: self,
+            dtstart,
+            period: 0,
+            emitted: 0,
+            pending: vec![dtstart],
+        }
+    }
+
+    /// the candidate dates for a single period, in order, `None` once the period is past the
+    /// dates we can represent
+    fn period_dates(&self, dtstart: NaiveDate, period: u32) -> Option<Vec<NaiveDate>> {
+        let step = i64::from(period) * i64::from(self.interval);
+        let mut dates = match self.frequency {
+            Frequency::Daily => vec![dtstart.checked_add_signed(Duration::days(step))?],
+            Frequency::Weekly => {
+                let weekday = Duration::days(i64::from(dtstart.weekday().num_days_from_monday()));
+                let week_start = dtstart
+                    .checked_sub_signed(weekday)?
+                    .checked_add_signed(Duration::weeks(step))?;
+                if self.by_day.is_empty() {
+                    vec![week_start.checked_add_signed(weekday)?]
+                } else {
+                    (0..7)
+                        .filter_map(|x| week_start.checked_add_signed(Duration::days(x)))
+                        .collect()
+                }
+            }
+            Frequency::Monthly => {
+                let months = i64::from(dtstart.year()) * 12 + i64::from(dtstart.month0()) + step;
+                let (year, month) = (representable_year(months / 12)?, (months % 12) as u32 + 1);
+                if self.by_day.is_empty() && self.by_month_day.is_empty() {
+                    NaiveDate::from_ymd_opt(year, month, dtstart.day())
+                        .into_iter()
+                        .collect()
+                } else {
+                    days_of_month(year, month)
+                }
+            }
+            Frequency::Yearly => {
+                let year = representable_year(i64::from(dtstart.year()) + step)?;
+                if self.by_day.is_empty() && self.by_month_day.is_empty() {
+                    NaiveDate::from_ymd_opt(year, dtstart.month(), dtstart.day())
+                        .into_iter()
+                        .collect()
+                } else {
+                    (1..13).flat_map(|month| days_of_month(year, month)).collect()
+                }
+            }
+        };
+
+        dates.retain(|date| self.matches_by_day(date) && self.matches_by_month_day(date));
+        Some(dates)
+    }
+
+    fn matches_by_day(&self, date: &NaiveDate) -> bool {
+        if self.by_day.is_empty() {
+            return true;
+        }
+        self.by_day.iter().any(|x| {
+            x.weekday == date.weekday() && match x.ordinal {
+                None => true,
+                Some(n) if self.frequency == Frequency::Yearly => {
+                    weekday_ordinals(days_in_year(date.year()), date.ordinal()).contains(&n)
+                }
+                Some(n) => {
+                    let days = days_of_month(date.year(), date.month()).len() as u32;
+                    weekday_ordinals(days, date.day()).contains(&n)
+                }
+            }
+        })
+    }
+
+    fn matches_by_month_day(&self, date: &NaiveDate) -> bool {
+        if self.by_month_day.is_empty() {
+            return true;
+        }
+        let days = days_of_month(date.year(), date.month()).len() as i32;
+        let day = date.day() as i32;
+        self.by_month_day
+            .iter()
+            .any(|x| *x == day || *x == day - days - 1)
+    }
+}
+#[test]
+fn test_rrule_between() {
+    let dtstart = Utc.ymd(2018, 3, 5).and_hms(18, 0, 0); // a Monday
+    let from = Utc.ymd(2018, 1, 1).and_hms(0, 0, 0);
+    let to = Utc.ymd(2019, 1, 1).and_hms(0, 0, 0);
+    let days = |rule: &str, exdates: &[DateTime<Utc>]| -> Vec<(u32, u32)> {
+        RRule::from_str(rule)
+            .unwrap()
+            .between(&dtstart, &from, &to, exdates)
+            .iter()
+            .map(|x| (x.month(), x.day()))
+            .collect()
+    };
+
+    assert_eq!(
+        days("FREQ=WEEKLY;COUNT=3", &[]),
+        vec![(3, 5), (3, 12), (3, 19)]
+    );
+    assert_eq!(
+        days("FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,WE;COUNT=4", &[]),
+        vec![(3, 5), (3, 7), (3, 19), (3, 21)]
+    );
+    assert_eq!(
+        days("FREQ=DAILY;UNTIL=20180308T180000Z", &[]),
+        vec![(3, 5), (3, 6), (3, 7), (3, 8)]
+    );
+    assert_eq!(
+        days(
+            "FREQ=DAILY;COUNT=3",
+            &[Utc.ymd(2018, 3, 6).and_hms(18, 0, 0)]
+        ),
+        vec![(3, 5), (3, 7)]
+    );
+    // The first occurrence is always the start, even when it doesn't match the rule
+    assert_eq!(
+        days("FREQ=MONTHLY;BYDAY=-1FR;COUNT=3", &[]),
+        vec![(3, 5), (3, 30), (4, 27)]
+    );
+    assert_eq!(
+        days("FREQ=MONTHLY;BYMONTHDAY=1,-1;COUNT=3", &[]),
+        vec![(3, 5), (3, 31), (4, 1)]
+    );
+    assert_eq!(
+        days("FREQ=WEEKLY;BYDAY=TU,TH;COUNT=3", &[]),
+        vec![(3, 5), (3, 6), (3, 8)]
+    );
+    assert_eq!(
+        days("FREQ=MONTHLY;BYDAY=1MO;COUNT=3", &[]),
+        vec![(3, 5), (4, 2), (5, 7)]
+    );
+    assert_eq!(days("FREQ=YEARLY;COUNT=2", &[]), vec![(3, 5)]);
+
+    // Only the occurrences inside of the window are returned
+    let from = Utc.ymd(2018, 3, 10).and_hms(0, 0, 0);
+    let to = Utc.ymd(2018, 3, 27).and_hms(0, 0, 0);
+    let starts = RRule::from_str("FREQ=WEEKLY")
+        .unwrap()
+        .between(&dtstart, &from, &to, &[]);
+    assert_eq!(
+        starts,
+        vec![
+            Utc.ymd(2018, 3, 12).and_hms(18, 0, 0),
+            Utc.ymd(2018, 3, 19).and_hms(18, 0, 0),
+            Utc.ymd(2018, 3, 26).and_hms(18, 0, 0),
+        ]
+    );
+
+    // A stored rule with a huge interval ends instead of overflowing the dates
+    let from = Utc.ymd(2018, 1, 1).and_hms(0, 0, 0);
+    let to = Utc.ymd(2019, 1, 1).and_hms(0, 0, 0);
+    for frequency in &["DAILY", "WEEKLY", "MONTHLY", "YEARLY"] {
+        let rule = RRule {
+            interval: 100_000_000,
+            ..RRule::from_str(&format!("FREQ={}", frequency)).unwrap()
+        };
+        assert_eq!(rule.between(&dtstart, &from, &to, &[]), vec![dtstart]);
+    }
+}
+#[test]
+fn test_rrule_includes() {
+    let dtstart = Utc.ymd(2018, 3, 5).and_hms(18, 0, 0);
+    let rule = RRule::from_str("FREQ=WEEKLY;COUNT=3").unwrap();
+
+    assert!(rule.includes(&dtstart, &Utc.ymd(2018, 3, 19).and_hms(18, 0, 0)));
+    assert!(!rule.includes(&dtstart, &Utc.ymd(2018, 3, 26).and_hms(18, 0, 0)));
+    assert!(!rule.includes(&dtstart, &Utc.ymd(2018, 3, 12).and_hms(17, 0, 0)));
+}
+#[test]
+fn test_rrule_starts_from() {
+    let dtstart = Utc.ymd(2018, 3, 5).and_hms(18, 0, 0);
+    let from = Utc.ymd(2018, 3, 10).and_hms(0, 0, 0);
+    let exdates = [Utc.ymd(2018, 3, 12).and_hms(18, 0, 0)];
+    let starts = |rule: &str| -> Vec<DateTime<Utc>> {
+        RRule::from_str(rule)
+            .unwrap()
+            .starts_from(&dtstart, &from, &exdates)
+            .take(2)
+            .collect()
+    };
+
+    assert_eq!(
+        starts("FREQ=WEEKLY"),
+        vec![
+            Utc.ymd(2018, 3, 19).and_hms(18, 0, 0),
+            Utc.ymd(2018, 3, 26).and_hms(18, 0, 0),
+        ]
+    );
+    assert_eq!(
+        starts("FREQ=WEEKLY;COUNT=3"),
+        vec![Utc.ymd(2018, 3, 19).and_hms(18, 0, 0)]
+    );
+    assert!(starts("FREQ=WEEKLY;COUNT=2").is_empty());
+    assert!(starts("FREQ=DAILY;UNTIL=20180309T180000Z").is_empty());
+}
+
+/// The iterator behind `RRule::iter`
+struct Occurrences<'a> {
+    rule: &'a RRule,
+    dtstart: NaiveDateTime,
+    period: u32,
+    emitted: u32,
+    pending: Vec<NaiveDateTime>,
+}
+
+impl<'a> Iterator for Occurrences<'a> {
+    type Item = NaiveDateTime;
+
+    fn next(&mut self) -> Option<NaiveDateTime> {
+        if self.rule.count.is_some_and(|count| self.emitted >= count) {
+            return None;
+        }
+        while self.pending.is_empty() {
+            if self.period >= MAX_PERIODS {
+                return None;
+            }
+            let time = self.dtstart.time();
+            let dtstart = self.dtstart;
+            self.pending = self.rule
+                .period_dates(dtstart.date(), self.period)?
+                .into_iter()
+                .map(|date| date.and_time(time))
+                .filter(|x| *x > dtstart)
+                .rev()
+                .collect();
+            self.period += 1;
+        }
+        self.emitted += 1;
+        self.pending.pop()
+    }
+}
+
+// Internal
+
+fn parse_frequency(s: &str) -> Option<Frequency> {
+    match s {
+        "DAILY" => Some(Frequency::Daily),
+        "WEEKLY" => Some(Frequency::Weekly),
+        "MONTHLY" => Some(Frequency::Monthly),
+        "YEARLY" => Some(Frequency::Yearly),
+        _ => None,
+    }
+}
+
+fn parse_until(s: &str) -> Option<DateTime<Utc>> {
+    let s = s.trim_end_matches('Z');
+    NaiveDateTime::parse_from_str(s, "%Y%m%dT%H%M%S")
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(s, "%Y%m%d")
+                .ok()
+                .map(|x| x.and_hms(0, 0, 0))
+        })
+        .map(|x| DateTime::from_utc(x, Utc))
+}
+
+fn parse_weekday_num(s: &str) -> Option<WeekdayNum> {
+    if s.len() < 2 || !s.is_char_boundary(s.len() - 2) {
+        return None;
+    }
+    let (ordinal, code) = s.split_at(s.len() - 2);
+    let weekday = match code {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return None,
+    };
+    let ordinal = match ordinal {
+        "" => None,
+        n => Some(n.parse().ok().filter(|x: &i32| *x != 0 && x.abs() <= 53)?),
+    };
+    Some(WeekdayNum { ordinal, weekday })
+}
+
+fn weekday_code(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+/// a year that `NaiveDate` can represent, `None` when it is out of range
+fn representable_year(year: i64) -> Option<i32> {
+    i32::try_from(year)
+        .ok()
+        .filter(|x| NaiveDate::from_ymd_opt(*x, 1, 1).is_some())
+}
+
+fn days_of_month(year: i32, month: u32) -> Vec<NaiveDate> {
+    (1..32)
+        .filter_map(|day| NaiveDate::from_ymd_opt(year, month, day))
+        .collect()
+}
+
+fn days_in_year(year: i32) -> u32 {
+    if NaiveDate::from_ymd_opt(year, 2, 29).is_some() {
+        366
+    } else {
+        365
+    }
+}
+
+/// the positive and negative ordinals of a weekday inside of its month or year
+///
+/// The 30th of a 31 day month is both the 5th and the -1st of its weekday
+fn weekday_ordinals(days_in_period: u32, day_of_period: u32) -> [i32; 2] {
+    [
+        ((day_of_period - 1) / 7 + 1) as i32,
+        -(((days_in_period - day_of_period) / 7 + 1) as i32),
+    ]
+}
@@ -0,0 +1,85 @@
+//! The background jobs that run next to the web server
+use chrono::Utc;
+use db;
+use diesel::pg::PgConnection;
+use models::attendance::pg::PgModel as AttendanceModel;
+use models::event::pg::PgModel as EventModel;
+use models::outbox::pg::PgModel as OutboxModel;
+use models::reminder::pg::PgModel as ReminderModel;
+use models::webhook::pg::PgModel as WebhookModel;
+use notifier::Notifier;
+use services::ServiceError;
+use services::outbox::Service as OutboxService;
+use services::reminder::Service as ReminderService;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// how often the due reminders are queued
+const REMINDER_INTERVAL_SECS: u64 = 60;
+
+/// how often the outbox is drained, this is how long a new message may wait
+const OUTBOX_INTERVAL_SECS: u64 = 2;
+
+/// the most messages that are delivered before the outbox worker sleeps again
+const OUTBOX_BATCH: usize = 100;
+
+/// starts the threads that queue the reminders and deliver the outbox until the process exits
+pub fn start(database_url: &str, notifier: Arc<dyn Notifier>) {
+    every(database_url, REMINDER_INTERVAL_SECS, queue_reminders);
+    every(database_url, OUTBOX_INTERVAL_SECS, move |conn| {
+        deliver_outbox(conn, &*notifier)
+    });
+}
+
+// Internal
+
+/// runs a job on its own thread, the errors are logged so the next run can try again
+///
+/// The thread keeps its DB connection between runs, it connects again after a failed run
+fn every<F>(database_url: &str, interval_secs: u64, job: F)
+where
+    F: Fn(&PgConnection) -> Result<(), ServiceError> + Send + 'static,
+{
+    let database_url = database_url.to_string();
+    thread::spawn(move || {
+        let mut conn = None;
+        loop {
+            if conn.is_none() {
+                conn = db::try_connection(&database_url)
+                    .map_err(|e| eprintln!("Scheduler could not connect: {}", e))
+                    .ok();
+            }
+            let failed = match conn {
+                Some(ref conn) => job(conn).map_err(|e| eprintln!("Job failed: {}", e)).is_err(),
+                None => false,
+            };
+            if failed {
+                conn = None;
+            }
+            thread::sleep(Duration::from_secs(interval_secs));
+        }
+    });
+}
+
+fn queue_reminders(conn: &PgConnection) -> Result<(), ServiceError> {
+    let reminder_model = &ReminderModel::new(conn);
+    let event_model = &EventModel::new(conn);
+    let attendance_model = &AttendanceModel::new(conn);
+    let reminder_service = ReminderService::new(reminder_model, event_model, attendance_model);
+
+    let queued = reminder_service.queue_due(&Utc::now())?;
+    if queued > 0 {
+        eprintln!("Queued {} reminders", queued);
+    }
+    Ok(())
+}
+
+fn deliver_outbox(conn: &PgConnection, notifier: &dyn Notifier) -> Result<(), ServiceError> {
+    let outbox_model = &OutboxModel::new(conn);
+    let webhook_model = &WebhookModel::new(conn);
+    let outbox_service = OutboxService::new(outbox_model, webhook_model, notifier);
+
+    outbox_service.deliver_due(&Utc::now(), OUTBOX_BATCH)?;
+    Ok(())
+}
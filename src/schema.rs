@@ -9,3 +9,260 @@ table! {
         confirmed -> Bool,
     }
 }
+
+table! {
+    /// The calendar subscription feeds, one row per feed token
+    calendar_feeds (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    /// The events table
+    events (id) {
+        id -> Uuid,
+        owner_id -> Uuid,
+        name -> Varchar,
+        description -> Text,
+        start_date -> Timestamptz,
+        end_date -> Timestamptz,
+        max_attendees -> Nullable<Int4>,
+        rrule -> Nullable<Varchar>,
+        exdates -> Array<Timestamptz>,
+        uid -> Nullable<Varchar>,
+        time_zone -> Varchar,
+        local_start_date -> Timestamp,
+        local_end_date -> Timestamp,
+        latitude -> Nullable<Float8>,
+        longitude -> Nullable<Float8>,
+        tags -> Array<Varchar>,
+        venue_id -> Nullable<Uuid>,
+        visibility -> Varchar,
+        group_id -> Nullable<Uuid>,
+    }
+}
+
+table! {
+    /// The people with a role in an event, one row per user
+    event_members (event_id, user_id) {
+        event_id -> Uuid,
+        user_id -> Uuid,
+        role -> Varchar,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    /// The overridden or cancelled occurrences of recurring events
+    event_occurrences (event_id, original_start_date) {
+        event_id -> Uuid,
+        original_start_date -> Timestamptz,
+        cancelled -> Bool,
+        name -> Nullable<Varchar>,
+        description -> Nullable<Text>,
+        start_date -> Nullable<Timestamptz>,
+        end_date -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    /// The attendances table, one row per user that RSVP'd to an event
+    attendances (user_id, event_id) {
+        user_id -> Uuid,
+        event_id -> Uuid,
+        status -> Varchar,
+        updated_at -> Timestamptz,
+        waitlisted_at -> Nullable<Timestamptz>,
+        checked_in_at -> Nullable<Timestamptz>,
+        checked_in_by -> Nullable<Uuid>,
+        ticket_id -> Uuid,
+    }
+}
+
+table! {
+    /// The comments on events, replies have a `parent_id`
+    comments (id) {
+        id -> Uuid,
+        event_id -> Uuid,
+        parent_id -> Nullable<Uuid>,
+        author_id -> Uuid,
+        body -> Text,
+        created_at -> Timestamptz,
+        edited_at -> Nullable<Timestamptz>,
+        deleted_at -> Nullable<Timestamptz>,
+        deleted_by -> Nullable<Uuid>,
+    }
+}
+
+table! {
+    /// The groups that run series of events
+    groups (id) {
+        id -> Uuid,
+        name -> Varchar,
+        description -> Text,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    /// The members and admins of groups, one row per user
+    group_members (group_id, user_id) {
+        group_id -> Uuid,
+        user_id -> Uuid,
+        role -> Varchar,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    /// The users that follow groups, one row per follower
+    group_followers (group_id, user_id) {
+        group_id -> Uuid,
+        user_id -> Uuid,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    /// The invitations to events, one row per invited user
+    invitations (event_id, user_id) {
+        event_id -> Uuid,
+        user_id -> Uuid,
+        invited_by -> Uuid,
+        status -> Varchar,
+        created_at -> Timestamptz,
+        responded_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    /// The messages that are waiting to be delivered, along with the ones that were
+    outbox (id) {
+        id -> Uuid,
+        kind -> Varchar,
+        payload -> Text,
+        status -> Varchar,
+        attempts -> Int4,
+        next_attempt_at -> Timestamptz,
+        last_error -> Nullable<Text>,
+        created_at -> Timestamptz,
+        sent_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    /// The refresh tokens that were handed out, a family is the chain of tokens from one login
+    refresh_tokens (jti) {
+        jti -> Uuid,
+        family_id -> Uuid,
+        user_id -> Uuid,
+        created_at -> Timestamptz,
+        expires_at -> Timestamptz,
+        used_at -> Nullable<Timestamptz>,
+        revoked_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    /// The reminders that went out, one row per attendee, occurrence and kind of reminder
+    reminders_sent (event_id, user_id, occurrence_start, kind) {
+        event_id -> Uuid,
+        user_id -> Uuid,
+        occurrence_start -> Timestamptz,
+        kind -> Varchar,
+        sent_at -> Timestamptz,
+    }
+}
+
+table! {
+    /// The denylist of access tokens that were revoked before they expired
+    revoked_tokens (jti) {
+        jti -> Uuid,
+        expires_at -> Timestamptz,
+        revoked_at -> Timestamptz,
+    }
+}
+
+table! {
+    /// The places that events happen at
+    venues (id) {
+        id -> Uuid,
+        owner_id -> Uuid,
+        name -> Varchar,
+        address -> Text,
+        latitude -> Nullable<Float8>,
+        longitude -> Nullable<Float8>,
+        capacity -> Nullable<Int4>,
+        accessibility_notes -> Text,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    /// The webhook subscriptions
+    webhooks (id) {
+        id -> Uuid,
+        owner_id -> Uuid,
+        url -> Varchar,
+        secret -> Varchar,
+        topics -> Array<Varchar>,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    /// The webhook delivery log, one row per payload and subscription
+    webhook_deliveries (id) {
+        id -> Uuid,
+        webhook_id -> Uuid,
+        topic -> Varchar,
+        payload -> Text,
+        status -> Varchar,
+        attempts -> Int4,
+        response_status -> Nullable<Int4>,
+        last_error -> Nullable<Text>,
+        created_at -> Timestamptz,
+        delivered_at -> Nullable<Timestamptz>,
+    }
+}
+
+joinable!(attendances -> events (event_id));
+joinable!(attendances -> users (user_id));
+joinable!(calendar_feeds -> users (user_id));
+joinable!(comments -> events (event_id));
+joinable!(event_members -> events (event_id));
+joinable!(event_occurrences -> events (event_id));
+joinable!(events -> groups (group_id));
+joinable!(events -> users (owner_id));
+joinable!(group_followers -> groups (group_id));
+joinable!(group_members -> groups (group_id));
+joinable!(events -> venues (venue_id));
+joinable!(invitations -> events (event_id));
+joinable!(refresh_tokens -> users (user_id));
+joinable!(reminders_sent -> events (event_id));
+joinable!(venues -> users (owner_id));
+joinable!(webhook_deliveries -> webhooks (webhook_id));
+joinable!(webhooks -> users (owner_id));
+
+allow_tables_to_appear_in_same_query!(
+    attendances,
+    calendar_feeds,
+    comments,
+    event_members,
+    event_occurrences,
+    events,
+    group_followers,
+    group_members,
+    groups,
+    invitations,
+    outbox,
+    refresh_tokens,
+    reminders_sent,
+    revoked_tokens,
+    users,
+    venues,
+    webhook_deliveries,
+    webhooks,
+);
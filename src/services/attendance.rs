@@ -0,0 +1,389 @@
+//! This is the public API for RSVPs to events
+use chrono::{DateTime, Utc};
+use models::attendance::{Attendance, AttendeeKey, NewAttendance, RsvpStatus};
+use models::attendance::IOModel;
+use models::attendance::pg::PgModel;
+use models::event::IOModel as EventIOModel;
+use models::event::pg::PgModel as EventModel;
+use models::event_member::IOModel as MemberIOModel;
+use models::event_member::Role;
+use models::event_member::pg::PgModel as MemberModel;
+use models::outbox::Outgoing;
+use models::page::Page;
+use models::user::User;
+use models::webhook::WebhookTopic;
+use services::ServiceError;
+use services::member::member_event;
+use services::user::Service as UserService;
+use services::webhook::publish;
+use token::{TokenKind, Tokens};
+use uuid::Uuid;
+
+/// used to say whether the current user is going to an event
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RsvpRequest<'a> {
+    /// This is the OAuth 2.0 access token of the user that is responding
+    pub access_token: &'a str,
+    pub event_id: Uuid,
+    pub status: RsvpStatus,
+}
+
+/// the RSVP that was stored for the current user
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RsvpResponse {
+    pub event: Uuid,
+    pub attendee: Uuid,
+    pub status: RsvpStatus,
+    /// true when the event is full and the user is waiting for a spot to open up
+    pub waitlisted: bool,
+    pub updated_at: DateTime<Utc>,
+    /// The signed ticket to show at the door, it is set when the user has a spot
+    pub ticket: Option<String>,
+}
+
+/// the webhook payload for an `rsvp.changed`
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RsvpChange {
+    pub event: Uuid,
+    pub attendee: Uuid,
+    /// The new answer, `None` when the RSVP was taken back
+    pub status: Option<RsvpStatus>,
+    pub waitlisted: bool,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// used to take back the current user's RSVP
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CancelRsvpRequest<'a> {
+    /// This is the OAuth 2.0 access token of the user that is responding
+    pub access_token: &'a str,
+    pub event_id: Uuid,
+}
+
+/// the response from a cancel RSVP request
+///
+/// This is currently an empty object but may be filled in later
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CancelRsvpResponse;
+
+/// used to get the current user's ticket to an event
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TicketRequest<'a> {
+    /// This is the OAuth 2.0 access token of the attendee
+    pub access_token: &'a str,
+    pub event_id: Uuid,
+}
+
+/// a ticket to an event
+///
+/// The ticket is a compact signed token, it is meant to be shown as a QR code
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TicketResponse {
+    pub event: Uuid,
+    pub attendee: Uuid,
+    pub ticket: String,
+}
+
+/// used to check someone in to an event by scanning their ticket
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CheckinRequest<'a> {
+    /// This is the OAuth 2.0 access token of an organizer or a check-in staff member
+    pub access_token: &'a str,
+    pub event_id: Uuid,
+    /// The scanned ticket
+    pub ticket: &'a str,
+}
+
+/// the check-in that was recorded
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CheckinResponse {
+    pub event: Uuid,
+    pub attendee: Uuid,
+    pub checked_in_at: DateTime<Utc>,
+}
+
+/// used to list the people that RSVP'd to an event
+///
+/// When `status` is set, only the attendees with that answer are listed
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AttendeesRequest<'a> {
+    /// This is the OAuth 2.0 access token of the user that is looking, if there is one
+    pub access_token: Option<&'a str>,
+    pub event_id: Uuid,
+    pub status: Option<RsvpStatus>,
+    pub page: Page<AttendeeKey>,
+}
+
+/// a person that RSVP'd to an event
+///
+/// It is formatted as a [schema:Person](https://schema.org/Person), just like
+/// `CurrentUserResponse`, with the RSVP status added
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AttendeeResponse {
+    // https://schema.org/Thing
+    pub identifier: Uuid,
+    pub name: String,
+
+    // https://schema.org/Person
+    /// Only the organizers of the event see it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+
+    pub rsvp_status: RsvpStatus,
+    /// true when the person is waiting for a spot to open up
+    pub waitlisted: bool,
+    pub checked_in_at: Option<DateTime<Utc>>,
+}
+
+/// the people that RSVP'd to an event
+///
+/// The people with a spot are listed first, followed by the waitlist in order
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AttendeesResponse {
+    pub attendees: Vec<AttendeeResponse>,
+    /// The key to ask for the next page with, `None` on the last page
+    #[serde(skip)]
+    pub next: Option<AttendeeKey>,
+}
+
+/// represents the data inside of the JWT for a ticket
+///
+#[derive(Debug, Serialize, Deserialize)]
+struct TicketClaim {
+    /// The standard JWT subject field, this is the attendee
+    sub: String,
+    /// The event the ticket is for
+    evt: String,
+    /// The standard JWT id field, this is the `ticket_id` of the attendance
+    jti: String,
+    /// The flag that makes the claim data a ticket. See the explanation in `AccessTokenClaim`
+    ticket: bool,
+}
+
+/// The API for the attendance service
+pub struct Service<'a> {
+    model: &'a PgModel<'a>,
+    events: &'a EventModel<'a>,
+    members: &'a MemberModel<'a>,
+    users: &'a UserService<'a>,
+    tokens: &'a Tokens,
+}
+
+impl<'a> Service<'a> {
+    /// create a new Service instance
+    pub fn new(
+        model: &'a PgModel<'a>,
+        events: &'a EventModel<'a>,
+        members: &'a MemberModel<'a>,
+        users: &'a UserService<'a>,
+        tokens: &'a Tokens,
+    ) -> Service<'a> {
+        Service {
+            model,
+            events,
+            members,
+            users,
+            tokens,
+        }
+    }
+
+    /// set the current user's RSVP for an event
+    pub fn rsvp(&self, request: &RsvpRequest) -> Result<RsvpResponse, ServiceError> {
+        let user_id = &self.users.current_user_id(request.access_token)?;
+        self.events
+            .find_visible(&request.event_id, Some(user_id))?
+            .ok_or(ServiceError::NotFound)?;
+
+        let attendance = self.model.rsvp(&NewAttendance {
+            user_id,
+            event_id: &request.event_id,
+            status: request.status.as_str(),
+        }, |x| vec![rsvp_webhook(x, Some(x.rsvp_status()))])?;
+
+        Ok(self.rsvp_response(attendance))
+    }
+
+    /// clear the current user's RSVP for an event
+    pub fn cancel_rsvp(
+        &self,
+        request: &CancelRsvpRequest,
+    ) -> Result<CancelRsvpResponse, ServiceError> {
+        let user_id = &self.users.current_user_id(request.access_token)?;
+        self.model
+            .cancel(user_id, &request.event_id, |x| vec![rsvp_webhook(x, None)])?;
+
+        Ok(CancelRsvpResponse)
+    }
+
+    /// list the people that RSVP'd to an event that the current user may see
+    pub fn attendees(&self, request: &AttendeesRequest) -> Result<AttendeesResponse, ServiceError> {
+        let viewer = self.users.viewer_id(request.access_token)?;
+        let event = self.events
+            .find_visible(&request.event_id, viewer.as_ref())?
+            .ok_or(ServiceError::NotFound)?;
+        let role = match viewer {
+            Some(ref x) => self.members.role(&event.id, x)?,
+            None => None,
+        };
+        let with_email = role.is_some_and(|x| x.can_see_emails());
+
+        let paged = self.model
+            .attendees(&request.event_id, request.status, &request.page)?
+            .map(AttendeeResponse::from)
+            .map(|x| AttendeeResponse {
+                email: x.email.filter(|_| with_email),
+                ..x
+            });
+
+        Ok(AttendeesResponse {
+            attendees: paged.rows,
+            next: paged.next,
+        })
+    }
+
+    /// get the current user's ticket to an event they have a spot at
+    pub fn ticket(&self, request: &TicketRequest) -> Result<TicketResponse, ServiceError> {
+        let user_id = &self.users.current_user_id(request.access_token)?;
+        let attendance = self.model
+            .find(user_id, &request.event_id)?
+            .filter(Attendance::has_spot)
+            .ok_or(ServiceError::NotFound)?;
+
+        Ok(TicketResponse {
+            event: attendance.event_id,
+            attendee: attendance.user_id,
+            ticket: self.encode_ticket(&attendance),
+        })
+    }
+
+    /// check an attendee in with their ticket, each ticket can only be used once
+    pub fn check_in(&self, request: &CheckinRequest) -> Result<CheckinResponse, ServiceError> {
+        let staff_id = &self.users.current_user_id(request.access_token)?;
+        member_event(
+            self.events,
+            self.members,
+            staff_id,
+            &request.event_id,
+            Role::can_check_in,
+        )?;
+        let (user_id, event_id, ticket_id) = validate_ticket(self.tokens, request.ticket)
+            .filter(|x| x.1 == request.event_id)
+            .ok_or(ServiceError::InvalidTicket)?;
+
+        // A ticket stops working when its RSVP gives up the spot, and taking the spot again
+        // issues a new ticket.  The ticket is matched by the update that checks the user in, so
+        // one that is replaced in the meantime can't be used.
+        let attendance = match self.model
+            .check_in(&user_id, &event_id, &ticket_id, staff_id)?
+        {
+            Some(attendance) => attendance,
+            None => {
+                let attendance = self.model
+                    .find(&user_id, &event_id)?
+                    .filter(|x| x.has_spot() && x.ticket_id == ticket_id)
+                    .ok_or(ServiceError::InvalidTicket)?;
+                return Err(match attendance.checked_in_at {
+                    Some(_) => ServiceError::AlreadyCheckedIn,
+                    None => ServiceError::InvalidTicket,
+                });
+            }
+        };
+
+        Ok(CheckinResponse {
+            event: attendance.event_id,
+            attendee: attendance.user_id,
+            checked_in_at: attendance.checked_in_at.unwrap_or_else(Utc::now),
+        })
+    }
+
+    fn rsvp_response(&self, attendance: Attendance) -> RsvpResponse {
+        RsvpResponse {
+            event: attendance.event_id,
+            attendee: attendance.user_id,
+            status: attendance.rsvp_status(),
+            waitlisted: attendance.waitlisted_at.is_some(),
+            updated_at: attendance.updated_at,
+            ticket: Some(&attendance)
+                .filter(|x| x.has_spot())
+                .map(|x| self.encode_ticket(x)),
+        }
+    }
+
+    fn encode_ticket(&self, attendance: &Attendance) -> String {
+        self.tokens.encode(
+            TokenKind::Ticket,
+            TicketClaim {
+                sub: attendance.user_id.simple().to_string(),
+                evt: attendance.event_id.simple().to_string(),
+                jti: attendance.ticket_id.simple().to_string(),
+                ticket: true,
+            },
+        )
+    }
+}
+
+// Internal
+
+/// returns the user id, event id and ticket id inside of a ticket
+fn validate_ticket(tokens: &Tokens, token: &str) -> Option<(Uuid, Uuid, Uuid)> {
+    let claims = tokens.decode::<TicketClaim>(TokenKind::Ticket, token).filter(|x| x.ticket)?;
+    let user_id = Uuid::parse_str(&claims.sub).ok()?;
+    let event_id = Uuid::parse_str(&claims.evt).ok()?;
+    let ticket_id = Uuid::parse_str(&claims.jti).ok()?;
+    Some((user_id, event_id, ticket_id))
+}
+#[test]
+fn test_validate_ticket() {
+    use token::Lifetimes;
+
+    let key = &Tokens::new(b"key", "http://api", "http://api", Lifetimes::default());
+    let other = &Tokens::new(b"other", "http://api", "http://api", Lifetimes::default());
+    let (user_id, event_id, ticket_id) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+    let claim = |ticket| TicketClaim {
+        sub: user_id.simple().to_string(),
+        evt: event_id.simple().to_string(),
+        jti: ticket_id.simple().to_string(),
+        ticket,
+    };
+
+    assert_eq!(
+        validate_ticket(key, &key.encode(TokenKind::Ticket, claim(true))),
+        Some((user_id, event_id, ticket_id))
+    );
+    assert_eq!(validate_ticket(key, &key.encode(TokenKind::Ticket, claim(false))), None);
+    assert_eq!(validate_ticket(other, &key.encode(TokenKind::Ticket, claim(true))), None);
+}
+
+impl From<(Attendance, User)> for AttendeeResponse {
+    fn from((attendance, user): (Attendance, User)) -> Self {
+        AttendeeResponse {
+            identifier: user.id,
+            name: user.name,
+            email: Some(user.email),
+            rsvp_status: attendance.rsvp_status(),
+            waitlisted: attendance.waitlisted_at.is_some(),
+            checked_in_at: attendance.checked_in_at,
+        }
+    }
+}
+
+/// the webhook payload for a change to an RSVP, `status` is `None` when it was taken back
+fn rsvp_webhook(attendance: &Attendance, status: Option<RsvpStatus>) -> Outgoing {
+    publish(
+        WebhookTopic::RsvpChanged,
+        &RsvpChange {
+            event: attendance.event_id,
+            attendee: attendance.user_id,
+            status,
+            waitlisted: status.is_some() && attendance.waitlisted_at.is_some(),
+            updated_at: if status.is_some() { attendance.updated_at } else { Utc::now() },
+        },
+    )
+}
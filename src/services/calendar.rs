@@ -0,0 +1,364 @@
+//! This is the public API for iCalendar exports and subscription feeds
+use chrono::{DateTime, Utc};
+use ical::{Calendar, VEvent};
+use models::attendance::IOModel as AttendanceIOModel;
+use models::attendance::pg::PgModel as AttendanceModel;
+use models::calendar_feed::{CalendarFeed, NewCalendarFeed};
+use models::calendar_feed::IOModel;
+use models::calendar_feed::pg::PgModel;
+use models::event::{Event, OccurrenceOverride, Visibility};
+use models::event::IOModel as EventIOModel;
+use models::event::pg::PgModel as EventModel;
+use models::page::{DateIdKey, Page};
+use services::ServiceError;
+use services::user::Service as UserService;
+use token::{TokenKind, Tokens};
+use uuid::Uuid;
+
+/// used to export a single event
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EventCalendarRequest<'a> {
+    /// This is the OAuth 2.0 access token of the user that is looking, if there is one
+    pub access_token: Option<&'a str>,
+    pub event_id: Uuid,
+}
+
+/// used to get the calendar of everything a user RSVP'd to
+///
+/// Calendar clients can't send an `Authorization` header so the feed token is the credential
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FeedCalendarRequest<'a> {
+    pub feed_token: &'a str,
+}
+
+/// an iCalendar document
+///
+#[derive(Debug)]
+pub struct CalendarResponse {
+    pub calendar: Calendar,
+}
+
+/// used to create a new feed token for the current user
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CreateFeedRequest<'a> {
+    /// This is the OAuth 2.0 access token of the user that will own the feed
+    pub access_token: &'a str,
+}
+
+/// used to list the current user's feeds
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ListFeedsRequest<'a> {
+    /// This is the OAuth 2.0 access token of the user that owns the feeds
+    pub access_token: &'a str,
+    pub page: Page<DateIdKey>,
+}
+
+/// used to revoke one of the current user's feeds
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RevokeFeedRequest<'a> {
+    /// This is the OAuth 2.0 access token of the user that owns the feed
+    pub access_token: &'a str,
+    pub feed_id: Uuid,
+}
+
+/// a calendar subscription feed
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FeedResponse {
+    pub identifier: Uuid,
+    /// This is used in the feed URL, `/calendar/{feed_token}.ics`
+    pub feed_token: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// the current user's feeds
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FeedListResponse {
+    pub feeds: Vec<FeedResponse>,
+    /// The key to ask for the next page with, `None` on the last page
+    #[serde(skip)]
+    pub next: Option<DateIdKey>,
+}
+
+/// the response from a revoke feed request
+///
+/// This is currently an empty object but may be filled in later
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RevokeFeedResponse;
+
+/// represents the data inside of the JWT for the feed token
+///
+#[derive(Debug, Serialize, Deserialize)]
+struct FeedTokenClaim {
+    /// The standard JWT subject field
+    sub: String,
+    /// The standard JWT ID field, this is the id of the `calendar_feeds` row that keeps the token alive
+    jti: String,
+    /// The flag that makes the claim data a feed token. See the explanation in `AccessTokenClaim`
+    feed_token: bool,
+}
+
+/// The API for the calendar service
+pub struct Service<'a> {
+    model: &'a PgModel<'a>,
+    events: &'a EventModel<'a>,
+    attendances: &'a AttendanceModel<'a>,
+    users: &'a UserService<'a>,
+    tokens: &'a Tokens,
+}
+
+impl<'a> Service<'a> {
+    /// create a new Service instance
+    pub fn new(
+        model: &'a PgModel<'a>,
+        events: &'a EventModel<'a>,
+        attendances: &'a AttendanceModel<'a>,
+        users: &'a UserService<'a>,
+        tokens: &'a Tokens,
+    ) -> Service<'a> {
+        Service {
+            model,
+            events,
+            attendances,
+            users,
+            tokens,
+        }
+    }
+
+    /// export a single event that the current user may see as a calendar
+    pub fn event_calendar(
+        &self,
+        request: &EventCalendarRequest,
+    ) -> Result<CalendarResponse, ServiceError> {
+        let viewer = self.users.viewer_id(request.access_token)?;
+        let event = self.events
+            .find_visible(&request.event_id, viewer.as_ref())?
+            .ok_or(ServiceError::NotFound)?;
+        let overrides = self.events.occurrence_overrides(&event.id)?;
+
+        Ok(CalendarResponse {
+            calendar: Calendar {
+                name: event.name.clone(),
+                events: event_components(&event, &overrides, &Utc::now()),
+            },
+        })
+    }
+
+    /// the calendar of every event the owner of a feed token is going or maybe going to
+    pub fn feed_calendar(
+        &self,
+        request: &FeedCalendarRequest,
+    ) -> Result<CalendarResponse, ServiceError> {
+        let (user_id, feed_id) = validate_feed_token(self.tokens, request.feed_token)
+            .ok_or(ServiceError::PermissionDenied)?;
+        self.model
+            .find(&feed_id)?
+            .filter(|x| x.user_id == user_id)
+            .ok_or(ServiceError::PermissionDenied)?;
+
+        let dtstamp = Utc::now();
+        let mut events = vec![];
+        for (_, event) in self.attendances.events_for_user(&user_id)? {
+            // Taking back an invitation hides the event even from the people that RSVP'd to it
+            if self.events
+                .find_visible(&event.id, Some(&user_id))?
+                .is_none()
+            {
+                continue;
+            }
+            let overrides = self.events.occurrence_overrides(&event.id)?;
+            events.extend(event_components(&event, &overrides, &dtstamp));
+        }
+
+        Ok(CalendarResponse {
+            calendar: Calendar {
+                name: "rs-events".into(),
+                events,
+            },
+        })
+    }
+
+    /// create a new feed token for the current user
+    pub fn create_feed(&self, request: &CreateFeedRequest) -> Result<FeedResponse, ServiceError> {
+        let user_id = &self.users.current_user_id(request.access_token)?;
+        let feed = self.model.create(&NewCalendarFeed {
+            id: &Uuid::new_v4(),
+            user_id,
+        })?;
+
+        Ok(self.feed_response(feed))
+    }
+
+    /// list the current user's feeds
+    pub fn list_feeds(&self, request: &ListFeedsRequest) -> Result<FeedListResponse, ServiceError> {
+        let user_id = &self.users.current_user_id(request.access_token)?;
+        let paged = self.model
+            .list(user_id, &request.page)?
+            .map(|x| self.feed_response(x));
+
+        Ok(FeedListResponse {
+            feeds: paged.rows,
+            next: paged.next,
+        })
+    }
+
+    /// revoke one of the current user's feeds, its feed token stops working
+    pub fn revoke_feed(&self, request: &RevokeFeedRequest) -> Result<RevokeFeedResponse, ServiceError> {
+        let user_id = &self.users.current_user_id(request.access_token)?;
+        match self.model.revoke(user_id, &request.feed_id)? {
+            0 => Err(ServiceError::NotFound),
+            _ => Ok(RevokeFeedResponse),
+        }
+    }
+
+    fn feed_response(&self, feed: CalendarFeed) -> FeedResponse {
+        FeedResponse {
+            identifier: feed.id,
+            feed_token: self.tokens.encode(
+                TokenKind::Feed,
+                FeedTokenClaim {
+                    sub: feed.user_id.simple().to_string(),
+                    jti: feed.id.simple().to_string(),
+                    feed_token: true,
+                },
+            ),
+            created_at: feed.created_at,
+        }
+    }
+}
+
+// Internal
+
+/// returns the user id and feed id inside of a feed token
+fn validate_feed_token(tokens: &Tokens, token: &str) -> Option<(Uuid, Uuid)> {
+    let claims = tokens.decode::<FeedTokenClaim>(TokenKind::Feed, token).filter(|x| x.feed_token)?;
+    let user_id = Uuid::parse_str(&claims.sub).ok()?;
+    let feed_id = Uuid::parse_str(&claims.jti).ok()?;
+    Some((user_id, feed_id))
+}
+#[test]
+fn test_validate_feed_token() {
+    use token::Lifetimes;
+
+    let key = &Tokens::new(b"key", "http://api", "http://api", Lifetimes::default());
+    let other = &Tokens::new(b"other", "http://api", "http://api", Lifetimes::default());
+    let (user_id, feed_id) = (Uuid::new_v4(), Uuid::new_v4());
+    let claim = |feed_token| FeedTokenClaim {
+        sub: user_id.simple().to_string(),
+        jti: feed_id.simple().to_string(),
+        feed_token,
+    };
+
+    assert_eq!(
+        validate_feed_token(key, &key.encode(TokenKind::Feed, claim(true))),
+        Some((user_id, feed_id))
+    );
+    assert_eq!(
+        validate_feed_token(key, &key.encode(TokenKind::Feed, claim(false))),
+        None
+    );
+    assert_eq!(
+        validate_feed_token(other, &key.encode(TokenKind::Feed, claim(true))),
+        None
+    );
+}
+
+/// the `VEVENT`s for an event, one for the series and one for each overridden occurrence
+fn event_components(
+    event: &Event,
+    overrides: &[OccurrenceOverride],
+    dtstamp: &DateTime<Utc>,
+) -> Vec<VEvent> {
+    let uid = event
+        .uid
+        .clone()
+        .unwrap_or_else(|| format!("{}@rs-events", event.id.simple()));
+    let duration = event.end_date.signed_duration_since(event.start_date);
+    let is_recurring = event.rrule.is_some();
+
+    let mut exdates = event.exdates.clone();
+    if is_recurring {
+        exdates.extend(
+            overrides
+                .iter()
+                .filter(|x| x.cancelled)
+                .map(|x| x.original_start_date),
+        );
+    }
+
+    let mut components = vec![VEvent {
+        uid: uid.clone(),
+        dtstamp: *dtstamp,
+        start: event.start_date,
+        end: event.end_date,
+        summary: event.name.clone(),
+        description: event.description.clone(),
+        rrule: event.rrule.clone(),
+        exdates,
+        recurrence_id: None,
+        cancelled: false,
+        private: event.visibility() == Visibility::InviteOnly,
+        time_zone: Some(event.time_zone.clone()),
+    }];
+
+    if is_recurring {
+        components.extend(overrides.iter().filter(|x| !x.cancelled).map(|x| {
+            let start = x.start_date.unwrap_or(x.original_start_date);
+            VEvent {
+                uid: uid.clone(),
+                dtstamp: *dtstamp,
+                start,
+                end: x.end_date.unwrap_or(start + duration),
+                summary: x.name.clone().unwrap_or_else(|| event.name.clone()),
+                description: x.description
+                    .clone()
+                    .unwrap_or_else(|| event.description.clone()),
+                rrule: None,
+                exdates: vec![],
+                recurrence_id: Some(x.original_start_date),
+                cancelled: false,
+                private: event.visibility() == Visibility::InviteOnly,
+                time_zone: Some(event.time_zone.clone()),
+            }
+        }));
+    }
+    components
+}
+#[test]
+fn test_event_components() {
+    use chrono::TimeZone;
+    use models::event::test_event;
+
+    let event = test_event("FREQ=WEEKLY");
+    let override_at = |day, cancelled| OccurrenceOverride {
+        event_id: event.id,
+        original_start_date: Utc.ymd(2018, 3, day).and_hms(18, 0, 0),
+        cancelled,
+        name: None,
+        description: None,
+        start_date: None,
+        end_date: Some(Utc.ymd(2018, 3, day).and_hms(21, 0, 0)),
+    };
+    let components = event_components(
+        &event,
+        &[override_at(12, true), override_at(19, false)],
+        &Utc::now(),
+    );
+
+    assert_eq!(components.len(), 2);
+    assert_eq!(
+        components[0].exdates,
+        vec![Utc.ymd(2018, 3, 12).and_hms(18, 0, 0)]
+    );
+    assert_eq!(components[1].uid, components[0].uid);
+    assert_eq!(
+        components[1].recurrence_id,
+        Some(Utc.ymd(2018, 3, 19).and_hms(18, 0, 0))
+    );
+    assert_eq!(components[1].end, Utc.ymd(2018, 3, 19).and_hms(21, 0, 0));
+}
@@ -0,0 +1,249 @@
+//! This is the public API for the discussion threads on events
+use chrono::{DateTime, Utc};
+use models::comment::{Comment, NewComment};
+use models::comment::IOModel;
+use models::comment::pg::PgModel;
+use models::event::Event;
+use models::event::IOModel as EventIOModel;
+use models::event::pg::PgModel as EventModel;
+use models::event_member::IOModel as MemberIOModel;
+use models::event_member::pg::PgModel as MemberModel;
+use models::page::{DateIdKey, Page};
+use services::ServiceError;
+use services::user::Service as UserService;
+use uuid::Uuid;
+
+/// The longest comment body, in characters
+pub const MAX_COMMENT_LENGTH: usize = 5000;
+
+/// used to comment on an event, or to reply to a comment when `parent_id` is set
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CreateCommentRequest<'a> {
+    /// This is the OAuth 2.0 access token of the author
+    pub access_token: &'a str,
+    pub event_id: Uuid,
+    pub parent_id: Option<Uuid>,
+    pub body: &'a str,
+}
+
+/// used to list the comments on an event
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ListCommentsRequest<'a> {
+    /// This is the OAuth 2.0 access token of the user that is looking, if there is one
+    pub access_token: Option<&'a str>,
+    pub event_id: Uuid,
+    pub page: Page<DateIdKey>,
+}
+
+/// used to change the body of a comment
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EditCommentRequest<'a> {
+    /// This is the OAuth 2.0 access token of the author
+    pub access_token: &'a str,
+    pub event_id: Uuid,
+    pub comment_id: Uuid,
+    pub body: &'a str,
+}
+
+/// used to delete a comment
+///
+/// The replies to a deleted comment stay where they are
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DeleteCommentRequest<'a> {
+    /// This is the OAuth 2.0 access token of the author, the owner or a co-organizer of the event
+    pub access_token: &'a str,
+    pub event_id: Uuid,
+    pub comment_id: Uuid,
+}
+
+/// a comment on an event
+///
+/// The `body` and `author` of a deleted comment are left out
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CommentResponse {
+    pub id: Uuid,
+    pub event: Uuid,
+    /// The comment this is a reply to
+    pub parent: Option<Uuid>,
+    pub author: Option<Uuid>,
+    pub body: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub edited_at: Option<DateTime<Utc>>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+/// a list of comments, the oldest first
+///
+/// Replies always come after the comment they answer, so a client can build the threads as it
+/// goes through the pages
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CommentListResponse {
+    pub comments: Vec<CommentResponse>,
+    /// The key to ask for the next page with, `None` on the last page
+    #[serde(skip)]
+    pub next: Option<DateIdKey>,
+}
+
+/// the response from a delete comment request
+///
+/// This is currently an empty object but may be filled in later
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DeleteCommentResponse;
+
+/// The API for the comment service
+pub struct Service<'a> {
+    model: &'a PgModel<'a>,
+    events: &'a EventModel<'a>,
+    members: &'a MemberModel<'a>,
+    users: &'a UserService<'a>,
+}
+
+impl<'a> Service<'a> {
+    /// create a new Service instance
+    pub fn new(
+        model: &'a PgModel<'a>,
+        events: &'a EventModel<'a>,
+        members: &'a MemberModel<'a>,
+        users: &'a UserService<'a>,
+    ) -> Service<'a> {
+        Service {
+            model,
+            events,
+            members,
+            users,
+        }
+    }
+
+    /// comment on an event the current user may see
+    pub fn create(&self, request: &CreateCommentRequest) -> Result<CommentResponse, ServiceError> {
+        let user_id = &self.users.current_user_id(request.access_token)?;
+        let event = self.visible_event(Some(user_id), &request.event_id)?;
+        let body = validate_comment(request.body)?;
+        if let Some(ref parent_id) = request.parent_id {
+            // Replies stay in the thread of their own event, and deleted comments are closed
+            self.model
+                .find(parent_id)?
+                .filter(|x| x.event_id == event.id && !x.is_deleted())
+                .ok_or(ServiceError::InvalidComment)?;
+        }
+
+        let comment = self.model.create(&NewComment {
+            id: &Uuid::new_v4(),
+            event_id: &event.id,
+            parent_id: request.parent_id.as_ref(),
+            author_id: user_id,
+            body,
+        })?;
+
+        Ok(CommentResponse::from(comment))
+    }
+
+    /// list the comments on an event, the deleted ones are kept to hold the threads together
+    pub fn list(&self, request: &ListCommentsRequest) -> Result<CommentListResponse, ServiceError> {
+        let viewer = self.users.viewer_id(request.access_token)?;
+        let event = self.visible_event(viewer.as_ref(), &request.event_id)?;
+        let paged = self.model
+            .list(&event.id, &request.page)?
+            .map(CommentResponse::from);
+
+        Ok(CommentListResponse {
+            comments: paged.rows,
+            next: paged.next,
+        })
+    }
+
+    /// change the body of one of the current user's comments
+    pub fn edit(&self, request: &EditCommentRequest) -> Result<CommentResponse, ServiceError> {
+        let user_id = &self.users.current_user_id(request.access_token)?;
+        let comment = self.event_comment(user_id, &request.event_id, &request.comment_id)?;
+        if comment.author_id != *user_id {
+            return Err(ServiceError::PermissionDenied);
+        }
+        let body = validate_comment(request.body)?;
+
+        let comment = self.model
+            .edit(&comment.id, body)?
+            .ok_or(ServiceError::NotFound)?;
+
+        Ok(CommentResponse::from(comment))
+    }
+
+    /// delete a comment, the author and the organizers of the event may delete it
+    pub fn delete(
+        &self,
+        request: &DeleteCommentRequest,
+    ) -> Result<DeleteCommentResponse, ServiceError> {
+        let user_id = &self.users.current_user_id(request.access_token)?;
+        let comment = self.event_comment(user_id, &request.event_id, &request.comment_id)?;
+        if comment.author_id != *user_id {
+            let role = self.members.role(&comment.event_id, user_id)?;
+            if !role.is_some_and(|x| x.can_edit_event()) {
+                return Err(ServiceError::PermissionDenied);
+            }
+        }
+
+        match self.model.delete(&comment.id, user_id)? {
+            Some(_) => Ok(DeleteCommentResponse),
+            None => Err(ServiceError::NotFound),
+        }
+    }
+
+    /// find an event that the user may see
+    fn visible_event(&self, viewer: Option<&Uuid>, event_id: &Uuid) -> Result<Event, ServiceError> {
+        self.events
+            .find_visible(event_id, viewer)?
+            .ok_or(ServiceError::NotFound)
+    }
+
+    /// find a comment that was not deleted on an event the user may see
+    fn event_comment(
+        &self,
+        user_id: &Uuid,
+        event_id: &Uuid,
+        comment_id: &Uuid,
+    ) -> Result<Comment, ServiceError> {
+        let event = self.visible_event(Some(user_id), event_id)?;
+        self.model
+            .find(comment_id)?
+            .filter(|x| x.event_id == event.id && !x.is_deleted())
+            .ok_or(ServiceError::NotFound)
+    }
+}
+
+// Internal
+
+impl From<Comment> for CommentResponse {
+    fn from(comment: Comment) -> Self {
+        let deleted = comment.is_deleted();
+        CommentResponse {
+            id: comment.id,
+            event: comment.event_id,
+            parent: comment.parent_id,
+            author: Some(comment.author_id).filter(|_| !deleted),
+            body: Some(comment.body).filter(|_| !deleted),
+            created_at: comment.created_at,
+            edited_at: comment.edited_at,
+            deleted_at: comment.deleted_at,
+        }
+    }
+}
+
+/// returns the trimmed body of a comment
+fn validate_comment(body: &str) -> Result<&str, ServiceError> {
+    let body = body.trim();
+    if !body.is_empty() && body.chars().count() <= MAX_COMMENT_LENGTH {
+        Ok(body)
+    } else {
+        Err(ServiceError::InvalidComment)
+    }
+}
+#[test]
+fn test_validate_comment() {
+    assert_eq!(validate_comment(" When do doors open? ").unwrap(), "When do doors open?");
+    assert!(validate_comment("  \n").is_err());
+    assert!(validate_comment(&"a".repeat(MAX_COMMENT_LENGTH)).is_ok());
+    assert!(validate_comment(&"a".repeat(MAX_COMMENT_LENGTH + 1)).is_err());
+}
@@ -0,0 +1,1500 @@
+//! This is the public API for managing events
+use chrono::{DateTime, Duration, FixedOffset, NaiveDateTime, Offset, Utc};
+use chrono_tz::Tz;
+use ical::{parse_events, VEvent};
+use models::event::{Event, EventChanges, EventQuery, NewEvent, OccurrenceOverride, SearchKey,
+                    TagCount, TagMatch, Visibility};
+use models::event::IOModel;
+use models::event::pg::PgModel;
+use models::event_member::Role;
+use models::event_member::pg::PgModel as MemberModel;
+use models::group::GroupRole;
+use models::group::IOModel as GroupIOModel;
+use models::group::pg::PgModel as GroupModel;
+use models::outbox::Outgoing;
+use models::page::{DateIdKey, Page, Paged};
+use models::venue::IOModel as VenueIOModel;
+use models::venue::pg::PgModel as VenueModel;
+use models::webhook::WebhookTopic;
+use recurrence::RRule;
+use services::ServiceError;
+use services::member::member_event;
+use services::webhook::publish;
+use std::collections::HashMap;
+use std::str::FromStr;
+use services::user::Service as UserService;
+use uuid::Uuid;
+
+/// represents the form that is needed to create a new event
+///
+/// It is formatted as a [schema:Event](https://schema.org/Event)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CreateEventRequest<'a> {
+    /// This is the OAuth 2.0 access token of the user that will own the event
+    pub access_token: &'a str,
+    pub name: &'a str,
+    pub description: &'a str,
+    pub start_date: DateTime<Utc>,
+    pub end_date: DateTime<Utc>,
+    /// RSVPs past this many people are put on a waitlist
+    pub max_attendees: Option<i32>,
+    /// An RFC 5545 RRULE that repeats the event, the dates are the first occurrence
+    pub rrule: Option<&'a str>,
+    /// The starts of the occurrences that the RRULE should skip
+    pub exdates: Vec<DateTime<Utc>>,
+    /// The IANA time zone of the event, like `America/New_York`, it defaults to `UTC`
+    ///
+    /// A recurring event repeats on the wall clock of its time zone
+    pub time_zone: Option<&'a str>,
+    /// The location of the event, set both or neither
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    /// Free form labels to find the event by
+    pub tags: Vec<String>,
+    /// The venue the event is held at
+    pub venue_id: Option<Uuid>,
+    /// Who can see the event
+    pub visibility: Visibility,
+    /// The group that runs the event, only its admins can add events to it
+    pub group_id: Option<Uuid>,
+}
+
+/// used to look up a single event
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GetEventRequest<'a> {
+    /// This is the OAuth 2.0 access token of the user that is looking, if there is one
+    pub access_token: Option<&'a str>,
+    pub event_id: Uuid,
+    /// The IANA time zone to render the dates in, they are in the event's time zone by default
+    pub tz: Option<&'a str>,
+}
+
+/// used to list the upcoming events
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ListEventsRequest<'a> {
+    /// This is the OAuth 2.0 access token of the user that is looking, if there is one
+    pub access_token: Option<&'a str>,
+    /// Only lists the events with these tags, they are combined by `tag_match`
+    pub tags: Vec<String>,
+    pub tag_match: TagMatch,
+    /// The IANA time zone to render the dates in, they are in each event's time zone by default
+    pub tz: Option<&'a str>,
+    pub page: Page<DateIdKey>,
+}
+
+/// used to change an existing event
+///
+/// Only the fields that are `Some` are changed
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UpdateEventRequest<'a> {
+    /// This is the OAuth 2.0 access token of the owner or a co-organizer of the event
+    pub access_token: &'a str,
+    pub event_id: Uuid,
+    pub name: Option<&'a str>,
+    pub description: Option<&'a str>,
+    pub start_date: Option<DateTime<Utc>>,
+    pub end_date: Option<DateTime<Utc>>,
+    /// `Some(None)` removes the attendee limit
+    pub max_attendees: Option<Option<i32>>,
+    /// `Some(None)` stops the event from repeating
+    pub rrule: Option<Option<&'a str>>,
+    pub exdates: Option<Vec<DateTime<Utc>>>,
+    /// Changing the time zone keeps the start and end instants and moves the wall clock times
+    pub time_zone: Option<&'a str>,
+    /// `Some(None)` removes the location
+    pub latitude: Option<Option<f64>>,
+    pub longitude: Option<Option<f64>>,
+    pub tags: Option<Vec<String>>,
+    /// `Some(None)` removes the venue
+    pub venue_id: Option<Option<Uuid>>,
+    pub visibility: Option<Visibility>,
+    /// `Some(None)` takes the event out of its group, only the group's admins can add it to one
+    pub group_id: Option<Option<Uuid>>,
+}
+
+/// used to list the upcoming events of a group
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GroupEventsRequest<'a> {
+    /// This is the OAuth 2.0 access token of the user that is looking, if there is one
+    pub access_token: Option<&'a str>,
+    pub group_id: Uuid,
+    /// The IANA time zone to render the dates in, they are in each event's time zone by default
+    pub tz: Option<&'a str>,
+    pub page: Page<DateIdKey>,
+}
+
+/// used to list the upcoming events of the groups the current user follows
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FollowingEventsRequest<'a> {
+    /// This is the OAuth 2.0 access token of the follower
+    pub access_token: &'a str,
+    /// The IANA time zone to render the dates in, they are in each event's time zone by default
+    pub tz: Option<&'a str>,
+    pub page: Page<DateIdKey>,
+}
+
+/// used to search the events
+///
+/// Every filter is optional, only the events that match all of them are returned
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SearchEventsRequest<'a> {
+    /// This is the OAuth 2.0 access token of the user that is looking, if there is one
+    pub access_token: Option<&'a str>,
+    /// Full text search over the name and description
+    pub q: Option<&'a str>,
+    /// The start of the date range, it defaults to now
+    pub from: Option<DateTime<Utc>>,
+    /// The end of the date range, it defaults to a year after `from`
+    pub to: Option<DateTime<Utc>>,
+    /// The latitude and longitude to search around
+    pub near: Option<(f64, f64)>,
+    /// How far from `near` to search, it defaults to 25km
+    pub radius_km: Option<f64>,
+    /// Only matches the events with these tags, they are combined by `tag_match`
+    pub tags: Vec<String>,
+    pub tag_match: TagMatch,
+    /// The IANA time zone to render the dates in, they are in each event's time zone by default
+    pub tz: Option<&'a str>,
+    pub page: Page<SearchKey>,
+}
+
+/// used to list the tags of the events the current user may see listed
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TagsRequest<'a> {
+    /// This is the OAuth 2.0 access token of the user that is looking, if there is one
+    pub access_token: Option<&'a str>,
+}
+
+/// used to delete an existing event
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DeleteEventRequest<'a> {
+    /// This is the OAuth 2.0 access token of the owner of the event or an admin of its group
+    pub access_token: &'a str,
+    pub event_id: Uuid,
+}
+
+/// the data about an event
+///
+/// It is formatted as a [schema:Event](https://schema.org/Event)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EventResponse {
+    // https://schema.org/Thing
+    pub identifier: Uuid,
+    pub name: String,
+    pub description: String,
+
+    // https://schema.org/Event
+    pub start_date: DateTime<FixedOffset>,
+    pub end_date: DateTime<FixedOffset>,
+    /// The IANA time zone of the event
+    pub time_zone: String,
+    // https://schema.org/maximumAttendeeCapacity
+    pub max_attendees: Option<i32>,
+    /// The identifier of the user that owns the event
+    pub organizer: Uuid,
+    /// The RFC 5545 RRULE that repeats the event
+    pub rrule: Option<String>,
+    pub exdates: Vec<DateTime<Utc>>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    // https://schema.org/keywords
+    pub tags: Vec<String>,
+    // https://schema.org/location
+    /// The identifier of the venue the event is held at
+    pub venue: Option<Uuid>,
+    pub visibility: Visibility,
+    /// The identifier of the group that runs the event
+    pub group: Option<Uuid>,
+}
+
+/// a list of events
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EventListResponse<K> {
+    pub events: Vec<EventResponse>,
+    /// The key to ask for the next page with, `None` on the last page
+    #[serde(skip)]
+    pub next: Option<K>,
+}
+
+/// the results of an event search
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SearchEventsResponse {
+    pub events: Vec<EventResponse>,
+    /// The tags of every event that matches the search, not just the ones on this page
+    pub facets: Vec<TagResponse>,
+    /// The key to ask for the next page with, `None` on the last page
+    #[serde(skip)]
+    pub next: Option<SearchKey>,
+}
+
+/// a tag and the number of events that have it
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TagResponse {
+    pub tag: String,
+    pub count: i64,
+}
+
+/// a list of tags, the most used first
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TagsResponse {
+    pub tags: Vec<TagResponse>,
+}
+
+/// used to list the occurrences of an event that overlap a date range
+///
+/// An event that doesn't repeat has a single occurrence
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OccurrencesRequest<'a> {
+    /// This is the OAuth 2.0 access token of the user that is looking, if there is one
+    pub access_token: Option<&'a str>,
+    pub event_id: Uuid,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// The IANA time zone to render the dates in, they are in the event's time zone by default
+    pub tz: Option<&'a str>,
+}
+
+/// used to change a single occurrence of a recurring event
+///
+/// The `None` fields are taken from the event
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OverrideOccurrenceRequest<'a> {
+    /// This is the OAuth 2.0 access token of the owner or a co-organizer of the event
+    pub access_token: &'a str,
+    pub event_id: Uuid,
+    /// The start that the RRULE gives the occurrence
+    pub original_start_date: DateTime<Utc>,
+    pub name: Option<&'a str>,
+    pub description: Option<&'a str>,
+    pub start_date: Option<DateTime<Utc>>,
+    pub end_date: Option<DateTime<Utc>>,
+}
+
+/// used to cancel a single occurrence of a recurring event
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CancelOccurrenceRequest<'a> {
+    /// This is the OAuth 2.0 access token of the owner or a co-organizer of the event
+    pub access_token: &'a str,
+    pub event_id: Uuid,
+    /// The start that the RRULE gives the occurrence
+    pub original_start_date: DateTime<Utc>,
+}
+
+/// a single occurrence of an event
+///
+/// It is formatted as a [schema:Event](https://schema.org/Event)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OccurrenceResponse {
+    /// The identifier of the event this is an occurrence of
+    pub identifier: Uuid,
+    pub name: String,
+    pub description: String,
+    pub start_date: DateTime<FixedOffset>,
+    pub end_date: DateTime<FixedOffset>,
+    /// The start that the RRULE gives the occurrence, this identifies it within the event
+    pub original_start_date: DateTime<Utc>,
+}
+
+/// the occurrences of an event, ordered by their original start dates
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OccurrencesResponse {
+    pub occurrences: Vec<OccurrenceResponse>,
+}
+
+/// the response from a cancel occurrence request
+///
+/// This is currently an empty object but may be filled in later
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CancelOccurrenceResponse;
+
+/// used to import the events of an iCalendar document
+///
+/// Events are matched to the current user's events by their `UID`, so importing the same
+/// document again updates the events instead of duplicating them
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ImportEventsRequest<'a> {
+    /// This is the OAuth 2.0 access token of the user that will own the events
+    pub access_token: &'a str,
+    /// The `text/calendar` document
+    pub calendar: &'a str,
+    /// The visibility of every imported event, when it is `None` the `CLASS` of each event picks
+    /// public or invite only
+    pub visibility: Option<Visibility>,
+}
+
+/// what happened to a `VEVENT` during an import
+///
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportStatus {
+    Created,
+    Updated,
+    Skipped,
+}
+
+/// the result of importing a single `VEVENT`
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ImportResult {
+    pub uid: Option<String>,
+    /// Set when this is an override of a single occurrence, the start the RRULE gives it
+    pub recurrence_id: Option<DateTime<Utc>>,
+    pub status: ImportStatus,
+    /// The identifier of the event that was created or updated
+    pub identifier: Option<Uuid>,
+    /// Why the `VEVENT` was skipped
+    pub reason: Option<String>,
+}
+
+/// the results of an import, in the order of the `VEVENT`s in the document
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ImportEventsResponse {
+    pub events: Vec<ImportResult>,
+}
+
+/// the response from a delete event request
+///
+/// This is currently an empty object but may be filled in later
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DeleteEventResponse;
+
+/// The API for the event service
+pub struct Service<'a> {
+    model: &'a PgModel<'a>,
+    venues: &'a VenueModel<'a>,
+    members: &'a MemberModel<'a>,
+    groups: &'a GroupModel<'a>,
+    users: &'a UserService<'a>,
+}
+
+impl<'a> Service<'a> {
+    /// create a new Service instance
+    pub fn new(
+        model: &'a PgModel<'a>,
+        venues: &'a VenueModel<'a>,
+        members: &'a MemberModel<'a>,
+        groups: &'a GroupModel<'a>,
+        users: &'a UserService<'a>,
+    ) -> Service<'a> {
+        Service {
+            model,
+            venues,
+            members,
+            groups,
+            users,
+        }
+    }
+
+    /// create a new event owned by the current user
+    pub fn create(&self, request: &CreateEventRequest) -> Result<EventResponse, ServiceError> {
+        let owner_id = &self.users.current_user_id(request.access_token)?;
+        validate_dates(&request.start_date, &request.end_date)?;
+        validate_max_attendees(request.max_attendees)?;
+        let rrule = normalize_rrule(request.rrule)?;
+        let time_zone = parse_time_zone(request.time_zone.unwrap_or("UTC"))?;
+        validate_location(request.latitude, request.longitude)?;
+        let tags = normalize_tags(&request.tags);
+        self.validate_venue(request.venue_id.as_ref())?;
+        self.validate_group(owner_id, request.group_id.as_ref())?;
+
+        let new_event = NewEvent {
+            id: &Uuid::new_v4(),
+            owner_id,
+            name: request.name,
+            description: request.description,
+            start_date: &request.start_date,
+            end_date: &request.end_date,
+            max_attendees: request.max_attendees,
+            rrule: rrule.as_deref(),
+            exdates: &request.exdates,
+            uid: None,
+            time_zone: time_zone.name(),
+            local_start_date: &wall_clock(&request.start_date, time_zone),
+            local_end_date: &wall_clock(&request.end_date, time_zone),
+            latitude: request.latitude,
+            longitude: request.longitude,
+            tags: &tags,
+            venue_id: request.venue_id.as_ref(),
+            visibility: request.visibility.as_str(),
+            group_id: request.group_id.as_ref(),
+        };
+        let event = self.model
+            .create(&new_event, |x| vec![event_webhook(WebhookTopic::EventCreated, x)])?;
+
+        Ok(EventResponse::from(event))
+    }
+
+    /// get a single event that the current user may see
+    pub fn get(&self, request: &GetEventRequest) -> Result<EventResponse, ServiceError> {
+        let tz = request.tz.map(parse_time_zone).transpose()?;
+        let event = self.visible_event(request.access_token, &request.event_id)?;
+
+        Ok(event_response(event, tz))
+    }
+
+    /// list the events that have not ended yet and that the current user may see listed
+    ///
+    /// They are ordered by the start of their next occurrence, the recurring events whose
+    /// occurrences have all ended are left out.
+    pub fn list(
+        &self,
+        request: &ListEventsRequest,
+    ) -> Result<EventListResponse<DateIdKey>, ServiceError> {
+        let viewer = self.users.viewer_id(request.access_token)?;
+        let tz = request.tz.map(parse_time_zone).transpose()?;
+        let tags = normalize_tags(&request.tags);
+        let one_off = self.model
+            .upcoming(viewer.as_ref(), &tags, request.tag_match, &request.page)?;
+        let recurring = self.model
+            .recurring(viewer.as_ref(), None, &tags, request.tag_match)?;
+        let paged = self.upcoming_page(one_off, recurring, &request.page)?
+            .map(|x| event_response(x, tz));
+
+        Ok(EventListResponse {
+            events: paged.rows,
+            next: paged.next,
+        })
+    }
+
+    /// search the events by text, date range, distance and tags
+    ///
+    /// The date range may be `max_range` wide at most.  The recurring events without an occurrence
+    /// in it are left out before the events are paged.  Like `list`, only the events that the
+    /// current user may see listed are searched.  The facets count the tags of the same events.
+    pub fn search(
+        &self,
+        request: &SearchEventsRequest,
+    ) -> Result<SearchEventsResponse, ServiceError> {
+        let viewer = self.users.viewer_id(request.access_token)?;
+        let tz = request.tz.map(parse_time_zone).transpose()?;
+        let from = request.from.unwrap_or_else(Utc::now);
+        let to = request.to.unwrap_or(from + max_range());
+        validate_range(&from, &to)?;
+        let radius_km = match request.near {
+            Some((latitude, longitude)) => {
+                validate_location(Some(latitude), Some(longitude))
+                    .map_err(|_| ServiceError::InvalidLocation)?;
+                Some(request.radius_km.unwrap_or(25.0))
+            }
+            None => None,
+        };
+        if radius_km.is_some_and(|x| x.is_nan() || x <= 0.0) {
+            return Err(ServiceError::InvalidLocation);
+        }
+        let tags = normalize_tags(&request.tags);
+
+        let query = EventQuery {
+            text: request.q.filter(|x| !x.trim().is_empty()),
+            from: Some(&from),
+            to: Some(&to),
+            near: request.near,
+            radius_km,
+            tags: &tags,
+            tag_match: request.tag_match,
+            viewer: viewer.as_ref(),
+            excluded: &[],
+        };
+        // A recurring event only matches when one of its occurrences is in the range
+        let recurring = self.model.search_recurring(&query)?;
+        let overrides = self.overrides_of(&recurring)?;
+        let excluded: Vec<Uuid> = recurring
+            .iter()
+            .filter(|x| {
+                expand_occurrences(x, overrides_for(&overrides, x), &from, &to, Tz::UTC).is_empty()
+            })
+            .map(|x| x.id)
+            .collect();
+        let query = EventQuery {
+            excluded: &excluded,
+            ..query
+        };
+        let paged = self.model.search(&query, &request.page)?;
+        let facets = self.model
+            .tag_counts(&query, MAX_TAGS)?
+            .into_iter()
+            .map(TagResponse::from)
+            .collect();
+        let paged = paged.map(|x| event_response(x, tz));
+
+        Ok(SearchEventsResponse {
+            events: paged.rows,
+            facets,
+            next: paged.next,
+        })
+    }
+
+    /// list the tags of the events that the current user may see listed, with how many events
+    /// have each one
+    pub fn tags(&self, request: &TagsRequest) -> Result<TagsResponse, ServiceError> {
+        let viewer = self.users.viewer_id(request.access_token)?;
+        let query = EventQuery {
+            viewer: viewer.as_ref(),
+            ..EventQuery::default()
+        };
+        let tags = self.model
+            .tag_counts(&query, MAX_TAGS)?
+            .into_iter()
+            .map(TagResponse::from)
+            .collect();
+
+        Ok(TagsResponse { tags })
+    }
+
+    /// list the upcoming events of a group that the current user may see listed
+    pub fn group_events(
+        &self,
+        request: &GroupEventsRequest,
+    ) -> Result<EventListResponse<DateIdKey>, ServiceError> {
+        let viewer = self.users.viewer_id(request.access_token)?;
+        let tz = request.tz.map(parse_time_zone).transpose()?;
+        let group = self.groups
+            .find(&request.group_id)?
+            .ok_or(ServiceError::NotFound)?;
+        let one_off = self.model
+            .group_upcoming(&[group.id], viewer.as_ref(), &request.page)?;
+        let recurring = self.model
+            .recurring(viewer.as_ref(), Some(&[group.id]), &[], TagMatch::All)?;
+        let paged = self.upcoming_page(one_off, recurring, &request.page)?
+            .map(|x| event_response(x, tz));
+
+        Ok(EventListResponse {
+            events: paged.rows,
+            next: paged.next,
+        })
+    }
+
+    /// list the upcoming events of the groups the current user follows
+    pub fn following(
+        &self,
+        request: &FollowingEventsRequest,
+    ) -> Result<EventListResponse<DateIdKey>, ServiceError> {
+        let user_id = &self.users.current_user_id(request.access_token)?;
+        let tz = request.tz.map(parse_time_zone).transpose()?;
+        let group_ids = self.groups.followed(user_id)?;
+        let one_off = self.model
+            .group_upcoming(&group_ids, Some(user_id), &request.page)?;
+        let recurring = self.model
+            .recurring(Some(user_id), Some(&group_ids), &[], TagMatch::All)?;
+        let paged = self.upcoming_page(one_off, recurring, &request.page)?
+            .map(|x| event_response(x, tz));
+
+        Ok(EventListResponse {
+            events: paged.rows,
+            next: paged.next,
+        })
+    }
+
+    /// update an event the current user organizes
+    pub fn update(&self, request: &UpdateEventRequest) -> Result<EventResponse, ServiceError> {
+        let event = self.organized_event(request.access_token, &request.event_id)?;
+        let start_date = request.start_date.unwrap_or(event.start_date);
+        let end_date = request.end_date.unwrap_or(event.end_date);
+        validate_dates(&start_date, &end_date)?;
+        validate_max_attendees(request.max_attendees.unwrap_or(None))?;
+        let rrule = match request.rrule {
+            Some(rrule) => Some(normalize_rrule(rrule)?),
+            None => None,
+        };
+        let time_zone = match request.time_zone {
+            Some(x) => parse_time_zone(x)?,
+            None => event_time_zone(&event),
+        };
+        validate_location(
+            request.latitude.unwrap_or(event.latitude),
+            request.longitude.unwrap_or(event.longitude),
+        )?;
+        let tags = request.tags.as_ref().map(|x| normalize_tags(x));
+        if let Some(venue_id) = request.venue_id {
+            self.validate_venue(venue_id.as_ref())?;
+        }
+        if let Some(group_id) = request.group_id.filter(|x| *x != event.group_id) {
+            let user_id = self.users.current_user_id(request.access_token)?;
+            self.validate_group(&user_id, group_id.as_ref())?;
+        }
+        // The wall clock times only move when the dates or the time zone do
+        let moved = request.start_date.is_some() || request.end_date.is_some()
+            || request.time_zone.is_some();
+        let local_start_date = wall_clock(&start_date, time_zone);
+        let local_end_date = wall_clock(&end_date, time_zone);
+
+        let changes = EventChanges {
+            name: request.name,
+            description: request.description,
+            start_date: request.start_date.as_ref(),
+            end_date: request.end_date.as_ref(),
+            max_attendees: request.max_attendees,
+            rrule: rrule.as_ref().map(|x| x.as_deref()),
+            exdates: request.exdates.as_deref(),
+            time_zone: request.time_zone.map(|_| time_zone.name()),
+            local_start_date: Some(&local_start_date).filter(|_| moved),
+            local_end_date: Some(&local_end_date).filter(|_| moved),
+            latitude: request.latitude,
+            longitude: request.longitude,
+            tags: tags.as_deref(),
+            venue_id: request.venue_id.as_ref().map(Option::as_ref),
+            visibility: request.visibility.map(|x| x.as_str()),
+            group_id: request.group_id.as_ref().map(Option::as_ref),
+        };
+        if changes.is_empty() {
+            return Ok(EventResponse::from(event));
+        }
+
+        let event = self.model
+            .update(&request.event_id, &changes, |x| {
+                vec![event_webhook(WebhookTopic::EventUpdated, x)]
+            })?
+            .ok_or(ServiceError::NotFound)?;
+
+        Ok(EventResponse::from(event))
+    }
+
+    /// delete an event owned by the current user or in a group they are an admin of, the
+    /// co-organizers can't delete it
+    pub fn delete(&self, request: &DeleteEventRequest) -> Result<DeleteEventResponse, ServiceError> {
+        let user_id = &self.users.current_user_id(request.access_token)?;
+        member_event(
+            self.model,
+            self.members,
+            user_id,
+            &request.event_id,
+            Role::can_delete_event,
+        )?;
+        self.model.delete(&request.event_id)?;
+
+        Ok(DeleteEventResponse)
+    }
+
+    /// list the occurrences of an event that overlap a date range
+    pub fn occurrences(
+        &self,
+        request: &OccurrencesRequest,
+    ) -> Result<OccurrencesResponse, ServiceError> {
+        validate_range(&request.from, &request.to)?;
+        let tz = request.tz.map(parse_time_zone).transpose()?;
+        let event = self.visible_event(request.access_token, &request.event_id)?;
+        let overrides = self.model.occurrence_overrides(&event.id)?;
+        let tz = tz.unwrap_or_else(|| event_time_zone(&event));
+
+        Ok(OccurrencesResponse {
+            occurrences: expand_occurrences(&event, &overrides, &request.from, &request.to, tz),
+        })
+    }
+
+    /// change a single occurrence of a recurring event the current user organizes
+    pub fn override_occurrence(
+        &self,
+        request: &OverrideOccurrenceRequest,
+    ) -> Result<OccurrenceResponse, ServiceError> {
+        let event = self.organized_event(request.access_token, &request.event_id)?;
+        if !is_occurrence(&event, &request.original_start_date) {
+            return Err(ServiceError::NotFound);
+        }
+
+        let occurrence = OccurrenceOverride {
+            event_id: event.id,
+            original_start_date: request.original_start_date,
+            cancelled: false,
+            name: request.name.map(String::from),
+            description: request.description.map(String::from),
+            start_date: request.start_date,
+            end_date: request.end_date,
+        };
+        let response = apply_override(&event, &occurrence, event_time_zone(&event));
+        validate_dates(
+            &response.start_date.with_timezone(&Utc),
+            &response.end_date.with_timezone(&Utc),
+        )?;
+        self.model.save_occurrence_override(&occurrence)?;
+
+        Ok(response)
+    }
+
+    /// cancel a single occurrence of a recurring event the current user organizes
+    pub fn cancel_occurrence(
+        &self,
+        request: &CancelOccurrenceRequest,
+    ) -> Result<CancelOccurrenceResponse, ServiceError> {
+        let event = self.organized_event(request.access_token, &request.event_id)?;
+        if !is_occurrence(&event, &request.original_start_date) {
+            return Err(ServiceError::NotFound);
+        }
+
+        self.model.save_occurrence_override(&OccurrenceOverride {
+            event_id: event.id,
+            original_start_date: request.original_start_date,
+            cancelled: true,
+            name: None,
+            description: None,
+            start_date: None,
+            end_date: None,
+        })?;
+
+        Ok(CancelOccurrenceResponse)
+    }
+
+    /// create or update the current user's events from an iCalendar document
+    ///
+    /// Overrides of single occurrences are applied after every recurring event has been imported.
+    /// The import runs in one transaction, so an error leaves every event as it was.
+    pub fn import(&self, request: &ImportEventsRequest) -> Result<ImportEventsResponse, ServiceError> {
+        let owner_id = &self.users.current_user_id(request.access_token)?;
+        let components = parse_events(request.calendar).map_err(|_| ServiceError::InvalidCalendar)?;
+
+        self.model.transaction(|| {
+            let mut results = Vec::with_capacity(components.len());
+            let mut occurrences = vec![];
+            for component in components {
+                match component {
+                    Ok(x) => if x.recurrence_id.is_some() {
+                        occurrences.push((results.len(), x));
+                        results.push(None);
+                    } else {
+                        results.push(Some(self.import_event(owner_id, &x, request.visibility)?));
+                    },
+                    Err(x) => results.push(Some(ImportResult {
+                        uid: x.uid,
+                        recurrence_id: None,
+                        status: ImportStatus::Skipped,
+                        identifier: None,
+                        reason: Some(x.reason),
+                    })),
+                }
+            }
+            for (i, x) in occurrences {
+                results[i] = Some(self.import_occurrence(owner_id, &x)?);
+            }
+
+            Ok(ImportEventsResponse {
+                events: results.into_iter().flatten().collect(),
+            })
+        })
+    }
+
+    /// create or update a single imported event, `visibility` overrides its `CLASS`
+    fn import_event(
+        &self,
+        owner_id: &Uuid,
+        component: &VEvent,
+        visibility: Option<Visibility>,
+    ) -> Result<ImportResult, ServiceError> {
+        let result = |status, identifier, reason: Option<&str>| ImportResult {
+            uid: Some(component.uid.clone()),
+            recurrence_id: None,
+            status,
+            identifier,
+            reason: reason.map(String::from),
+        };
+        if validate_dates(&component.start, &component.end).is_err() {
+            return Ok(result(ImportStatus::Skipped, None, Some("DTEND is before DTSTART")));
+        }
+        let rrule = match normalize_rrule(component.rrule.as_deref()) {
+            Ok(x) => x,
+            Err(_) => return Ok(result(ImportStatus::Skipped, None, Some("invalid RRULE"))),
+        };
+
+        let time_zone = component
+            .time_zone
+            .as_ref()
+            .and_then(|x| parse_time_zone(x).ok())
+            .unwrap_or(Tz::UTC);
+        let local_start_date = wall_clock(&component.start, time_zone);
+        let local_end_date = wall_clock(&component.end, time_zone);
+        let visibility = visibility.unwrap_or(if component.private {
+            Visibility::InviteOnly
+        } else {
+            Visibility::Public
+        });
+
+        let event = match self.model.find_by_uid(owner_id, &component.uid)? {
+            Some(x) => x,
+            None => {
+                let new_event = NewEvent {
+                    id: &Uuid::new_v4(),
+                    owner_id,
+                    name: &component.summary,
+                    description: &component.description,
+                    start_date: &component.start,
+                    end_date: &component.end,
+                    max_attendees: None,
+                    rrule: rrule.as_deref(),
+                    exdates: &component.exdates,
+                    uid: Some(&component.uid),
+                    time_zone: time_zone.name(),
+                    local_start_date: &local_start_date,
+                    local_end_date: &local_end_date,
+                    latitude: None,
+                    longitude: None,
+                    tags: &[],
+                    venue_id: None,
+                    visibility: visibility.as_str(),
+                    group_id: None,
+                };
+                let event = self.model
+                    .create(&new_event, |x| vec![event_webhook(WebhookTopic::EventCreated, x)])?;
+                return Ok(result(ImportStatus::Created, Some(event.id), None));
+            }
+        };
+
+        let changes = EventChanges {
+            name: Some(&component.summary).filter(|x| **x != event.name).map(String::as_str),
+            description: Some(&component.description)
+                .filter(|x| **x != event.description)
+                .map(String::as_str),
+            start_date: Some(&component.start).filter(|x| **x != event.start_date),
+            end_date: Some(&component.end).filter(|x| **x != event.end_date),
+            max_attendees: None,
+            rrule: Some(rrule.as_deref()).filter(|x| *x != event.rrule.as_deref()),
+            exdates: Some(&component.exdates[..]).filter(|x| *x != &event.exdates[..]),
+            time_zone: Some(time_zone.name()).filter(|x| *x != event.time_zone),
+            local_start_date: Some(&local_start_date).filter(|x| **x != event.local_start_date),
+            local_end_date: Some(&local_end_date).filter(|x| **x != event.local_end_date),
+            visibility: Some(visibility.as_str()).filter(|x| *x != event.visibility),
+            ..Default::default()
+        };
+        if changes.is_empty() {
+            return Ok(result(ImportStatus::Skipped, Some(event.id), Some("unchanged")));
+        }
+
+        self.model.update(&event.id, &changes, |x| {
+            vec![event_webhook(WebhookTopic::EventUpdated, x)]
+        })?;
+        Ok(result(ImportStatus::Updated, Some(event.id), None))
+    }
+
+    /// create or update the override of a single occurrence of an imported recurring event
+    fn import_occurrence(
+        &self,
+        owner_id: &Uuid,
+        component: &VEvent,
+    ) -> Result<ImportResult, ServiceError> {
+        let original_start_date = component.recurrence_id.unwrap_or(component.start);
+        let result = |status, identifier, reason: Option<&str>| ImportResult {
+            uid: Some(component.uid.clone()),
+            recurrence_id: Some(original_start_date),
+            status,
+            identifier,
+            reason: reason.map(String::from),
+        };
+
+        let event = match self.model.find_by_uid(owner_id, &component.uid)? {
+            Some(x) => x,
+            None => {
+                return Ok(result(ImportStatus::Skipped, None, Some("no recurring event with this UID")))
+            }
+        };
+        if !is_occurrence(&event, &original_start_date) {
+            return Ok(result(
+                ImportStatus::Skipped,
+                Some(event.id),
+                Some("RECURRENCE-ID is not an occurrence of the event"),
+            ));
+        }
+        if validate_dates(&component.start, &component.end).is_err() {
+            return Ok(result(ImportStatus::Skipped, Some(event.id), Some("DTEND is before DTSTART")));
+        }
+
+        let occurrence = OccurrenceOverride {
+            event_id: event.id,
+            original_start_date,
+            cancelled: component.cancelled,
+            name: Some(component.summary.clone()),
+            description: Some(component.description.clone()),
+            start_date: Some(component.start),
+            end_date: Some(component.end),
+        };
+        let status = match self.model
+            .occurrence_overrides(&event.id)?
+            .into_iter()
+            .find(|x| x.original_start_date == original_start_date)
+        {
+            Some(ref x) if *x == occurrence => {
+                return Ok(result(ImportStatus::Skipped, Some(event.id), Some("unchanged")))
+            }
+            Some(_) => ImportStatus::Updated,
+            None => ImportStatus::Created,
+        };
+        self.model.save_occurrence_override(&occurrence)?;
+
+        Ok(result(status, Some(event.id), None))
+    }
+
+    /// a page of upcoming events, the `one_off` events are merged with the `recurring` events
+    /// that have an occurrence which hasn't ended yet, by the start of their next occurrence
+    fn upcoming_page(
+        &self,
+        one_off: Paged<Event, DateIdKey>,
+        recurring: Vec<Event>,
+        page: &Page<DateIdKey>,
+    ) -> Result<Paged<Event, DateIdKey>, ServiceError> {
+        let now = Utc::now();
+        let overrides = self.overrides_of(&recurring)?;
+        let extra = recurring
+            .into_iter()
+            .filter_map(|event| {
+                let start = next_occurrence(&event, overrides_for(&overrides, &event), &now);
+                start.map(|x| ((x, event.id), event))
+            })
+            .collect();
+
+        Ok(page.merged(one_off, extra, |x| (x.start_date, x.id)))
+    }
+
+    /// the overrides of every one of the `events`, loaded with one query
+    fn overrides_of(
+        &self,
+        events: &[Event],
+    ) -> Result<HashMap<Uuid, Vec<OccurrenceOverride>>, ServiceError> {
+        let event_ids: Vec<Uuid> = events.iter().map(|x| x.id).collect();
+        let mut overrides: HashMap<Uuid, Vec<OccurrenceOverride>> = HashMap::new();
+        for occurrence in self.model.occurrence_overrides_of(&event_ids)? {
+            overrides.entry(occurrence.event_id).or_default().push(occurrence);
+        }
+        Ok(overrides)
+    }
+
+    /// find an event that the current user may see, the ones they may not see are not found
+    fn visible_event(
+        &self,
+        access_token: Option<&str>,
+        event_id: &Uuid,
+    ) -> Result<Event, ServiceError> {
+        let viewer = self.users.viewer_id(access_token)?;
+        self.model
+            .find_visible(event_id, viewer.as_ref())?
+            .ok_or(ServiceError::NotFound)
+    }
+
+    /// find an event that the current user owns or co-organizes
+    fn organized_event(&self, access_token: &str, event_id: &Uuid) -> Result<Event, ServiceError> {
+        let user_id = &self.users.current_user_id(access_token)?;
+        let (event, _) = member_event(
+            self.model,
+            self.members,
+            user_id,
+            event_id,
+            Role::can_edit_event,
+        )?;
+        Ok(event)
+    }
+
+    /// make sure that the group an event is being added to exists and that the user is one of its
+    /// admins
+    fn validate_group(&self, user_id: &Uuid, group_id: Option<&Uuid>) -> Result<(), ServiceError> {
+        match group_id {
+            Some(group_id) => {
+                self.groups
+                    .find(group_id)?
+                    .ok_or(ServiceError::InvalidGroup)?;
+                match self.groups.role(group_id, user_id)? {
+                    Some(GroupRole::Admin) => Ok(()),
+                    _ => Err(ServiceError::PermissionDenied),
+                }
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// make sure that the venue an event is being moved to exists
+    fn validate_venue(&self, venue_id: Option<&Uuid>) -> Result<(), ServiceError> {
+        match venue_id {
+            Some(venue_id) => self.venues
+                .find(venue_id)?
+                .map(|_| ())
+                .ok_or(ServiceError::InvalidVenue),
+            None => Ok(()),
+        }
+    }
+}
+
+// Internal
+
+/// the webhook payload for a change to an event, it is shaped like `EventResponse`
+fn event_webhook(topic: WebhookTopic, event: &Event) -> Outgoing {
+    publish(topic, &EventResponse::from(event.clone()))
+}
+
+impl From<Event> for EventResponse {
+    fn from(event: Event) -> Self {
+        event_response(event, None)
+    }
+}
+
+/// the data about an event with its dates rendered in `tz`, or in its own time zone
+fn event_response(event: Event, tz: Option<Tz>) -> EventResponse {
+    let tz = tz.unwrap_or_else(|| event_time_zone(&event));
+    let visibility = event.visibility();
+    EventResponse {
+        identifier: event.id,
+        name: event.name,
+        description: event.description,
+        start_date: render_date(&event.start_date, tz),
+        end_date: render_date(&event.end_date, tz),
+        time_zone: event.time_zone,
+        max_attendees: event.max_attendees,
+        organizer: event.owner_id,
+        rrule: event.rrule,
+        exdates: event.exdates,
+        latitude: event.latitude,
+        longitude: event.longitude,
+        tags: event.tags,
+        venue: event.venue_id,
+        visibility,
+        group: event.group_id,
+    }
+}
+
+/// parses an IANA time zone name, like `America/New_York`
+fn parse_time_zone(name: &str) -> Result<Tz, ServiceError> {
+    Tz::from_str(name).map_err(|_| ServiceError::InvalidTimeZone)
+}
+
+/// the time zone of an event, the name is checked before it is stored
+pub(crate) fn event_time_zone(event: &Event) -> Tz {
+    Tz::from_str(&event.time_zone).unwrap_or(Tz::UTC)
+}
+
+/// the wall clock time of an instant in `tz`
+fn wall_clock(date: &DateTime<Utc>, tz: Tz) -> NaiveDateTime {
+    date.with_timezone(&tz).naive_local()
+}
+
+/// an instant on the wall clock of `tz`, with the UTC offset it has there
+fn render_date(date: &DateTime<Utc>, tz: Tz) -> DateTime<FixedOffset> {
+    let local = date.with_timezone(&tz);
+    local.with_timezone(&local.offset().fix())
+}
+#[test]
+fn test_render_date() {
+    use chrono::TimeZone;
+
+    let tz = Tz::America__New_York;
+    // Daylight saving time started on March 11th
+    assert_eq!(
+        render_date(&Utc.ymd(2018, 3, 5).and_hms(14, 0, 0), tz).to_rfc3339(),
+        "2018-03-05T09:00:00-05:00"
+    );
+    assert_eq!(
+        render_date(&Utc.ymd(2018, 3, 12).and_hms(13, 0, 0), tz).to_rfc3339(),
+        "2018-03-12T09:00:00-04:00"
+    );
+}
+
+/// The most tags that are counted, the least used ones are left out
+const MAX_TAGS: i64 = 100;
+
+impl From<TagCount> for TagResponse {
+    fn from(tag_count: TagCount) -> Self {
+        TagResponse {
+            tag: tag_count.tag,
+            count: tag_count.count,
+        }
+    }
+}
+
+/// The widest date range that occurrences can be listed for
+fn max_range() -> Duration {
+    Duration::days(366)
+}
+
+/// parses an RRULE and writes it back out in the canonical form we store
+fn normalize_rrule(rrule: Option<&str>) -> Result<Option<String>, ServiceError> {
+    match rrule {
+        Some(rrule) => RRule::from_str(rrule)
+            .map(|x| Some(x.to_string()))
+            .map_err(|_| ServiceError::InvalidRecurrence),
+        None => Ok(None),
+    }
+}
+#[test]
+fn test_normalize_rrule() {
+    assert_eq!(
+        normalize_rrule(Some("RRULE:freq=weekly;count=2")).unwrap(),
+        Some("FREQ=WEEKLY;COUNT=2".to_string())
+    );
+    assert_eq!(normalize_rrule(None).unwrap(), None);
+    // The import skips a component with a rule like these instead of storing it
+    assert!(normalize_rrule(Some("FREQ=HOURLY")).is_err());
+    assert!(normalize_rrule(Some("FREQ=WEEKLY;BYDAY=éA")).is_err());
+}
+
+/// the parsed RRULE of an event, `None` when it doesn't repeat
+fn event_rrule(event: &Event) -> Option<RRule> {
+    event
+        .rrule
+        .as_ref()
+        .and_then(|x| RRule::from_str(x).ok())
+}
+
+/// true when the RRULE of an event puts an occurrence at `start`
+fn is_occurrence(event: &Event, start: &DateTime<Utc>) -> bool {
+    match event_rrule(event) {
+        Some(rule) => {
+            let tz = event_time_zone(event);
+            rule.includes(&event.start_date.with_timezone(&tz), &start.with_timezone(&tz))
+                && !event.exdates.contains(start)
+        }
+        None => false,
+    }
+}
+
+/// the occurrences of an event that overlap `from` and `to`, with the overrides applied
+///
+/// The occurrences repeat on the wall clock of the event's time zone and are rendered in `tz`
+pub(crate) fn expand_occurrences(
+    event: &Event,
+    overrides: &[OccurrenceOverride],
+    from: &DateTime<Utc>,
+    to: &DateTime<Utc>,
+    tz: Tz,
+) -> Vec<OccurrenceResponse> {
+    let duration = event.end_date.signed_duration_since(event.start_date);
+    let starts = match event_rrule(event) {
+        Some(rule) => {
+            let event_tz = event_time_zone(event);
+            // Start looking early enough to catch the occurrences that are already underway
+            rule.between(
+                &event.start_date.with_timezone(&event_tz),
+                &(*from - duration).with_timezone(&event_tz),
+                &to.with_timezone(&event_tz),
+                &event.exdates,
+            ).into_iter()
+                .map(|x| x.with_timezone(&Utc))
+                .collect()
+        }
+        None => vec![event.start_date],
+    };
+
+    starts
+        .into_iter()
+        .filter_map(|start| match overrides.iter().find(|x| x.original_start_date == start) {
+            Some(x) if x.cancelled => None,
+            Some(x) => Some(apply_override(event, x, tz)),
+            None => Some(OccurrenceResponse {
+                identifier: event.id,
+                name: event.name.clone(),
+                description: event.description.clone(),
+                start_date: render_date(&start, tz),
+                end_date: render_date(&(start + duration), tz),
+                original_start_date: start,
+            }),
+        })
+        .filter(|x| x.end_date > *from && x.start_date < *to)
+        .collect()
+}
+
+/// the overrides of an event out of the ones loaded by `Service::overrides_of`
+fn overrides_for<'a>(
+    overrides: &'a HashMap<Uuid, Vec<OccurrenceOverride>>,
+    event: &Event,
+) -> &'a [OccurrenceOverride] {
+    overrides.get(&event.id).map_or(&[], |x| x)
+}
+
+/// the start of the first occurrence of an event that hasn't ended by `now`, with the overrides
+/// applied, `None` when they have all ended
+///
+/// Occurrences are only looked for up to `max_range` past `now`, a series whose next one is
+/// further away than that has none.
+pub(crate) fn next_occurrence(
+    event: &Event,
+    overrides: &[OccurrenceOverride],
+    now: &DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    let rule = match event_rrule(event) {
+        Some(x) => x,
+        None => return Some(event.start_date).filter(|_| event.end_date > *now),
+    };
+    let duration = event.end_date.signed_duration_since(event.start_date);
+    let event_tz = event_time_zone(event);
+
+    // Start looking early enough to catch an occurrence that is already underway
+    let next = rule.starts_from(
+        &event.start_date.with_timezone(&event_tz),
+        &(*now - duration).with_timezone(&event_tz),
+        &event.exdates,
+    ).map(|x| x.with_timezone(&Utc))
+        .take_while(|start| *start < *now + max_range())
+        .filter_map(|start| match overrides.iter().find(|x| x.original_start_date == start) {
+            Some(x) if x.cancelled => None,
+            Some(x) => {
+                let start_date = x.start_date.unwrap_or(start);
+                Some((start_date, x.end_date.unwrap_or(start_date + duration)))
+            }
+            None => Some((start, start + duration)),
+        })
+        .find(|&(_, end_date)| end_date > *now);
+    next.map(|(start_date, _)| start_date)
+}
+#[test]
+fn test_next_occurrence() {
+    use chrono::TimeZone;
+    use models::event::test_event;
+
+    let event = Event {
+        exdates: vec![Utc.ymd(2018, 3, 26).and_hms(18, 0, 0)],
+        ..test_event("FREQ=WEEKLY;COUNT=4")
+    };
+    let overrides = vec![
+        OccurrenceOverride {
+            event_id: event.id,
+            original_start_date: Utc.ymd(2018, 3, 12).and_hms(18, 0, 0),
+            cancelled: false,
+            name: None,
+            description: None,
+            start_date: Some(Utc.ymd(2018, 3, 12).and_hms(19, 0, 0)),
+            end_date: None,
+        },
+        OccurrenceOverride {
+            event_id: event.id,
+            original_start_date: Utc.ymd(2018, 3, 19).and_hms(18, 0, 0),
+            cancelled: true,
+            name: None,
+            description: None,
+            start_date: None,
+            end_date: None,
+        },
+    ];
+    let next = |now: DateTime<Utc>| next_occurrence(&event, &overrides, &now);
+
+    assert_eq!(
+        next(Utc.ymd(2018, 3, 1).and_hms(0, 0, 0)),
+        Some(Utc.ymd(2018, 3, 5).and_hms(18, 0, 0))
+    );
+    // An occurrence that is underway is still the next one
+    assert_eq!(
+        next(Utc.ymd(2018, 3, 5).and_hms(19, 0, 0)),
+        Some(Utc.ymd(2018, 3, 5).and_hms(18, 0, 0))
+    );
+    assert_eq!(
+        next(Utc.ymd(2018, 3, 10).and_hms(0, 0, 0)),
+        Some(Utc.ymd(2018, 3, 12).and_hms(19, 0, 0))
+    );
+    // The rest of the series is cancelled, excluded or past its COUNT
+    assert_eq!(next(Utc.ymd(2018, 3, 13).and_hms(0, 0, 0)), None);
+
+    // The expansion stops a `max_range` ahead
+    let biennial = Event {
+        rrule: Some("FREQ=YEARLY;INTERVAL=2".into()),
+        exdates: vec![],
+        ..event.clone()
+    };
+    let now = Utc.ymd(2018, 3, 6).and_hms(0, 0, 0);
+    assert_eq!(next_occurrence(&biennial, &[], &now), None);
+    let now = Utc.ymd(2019, 6, 1).and_hms(0, 0, 0);
+    assert_eq!(
+        next_occurrence(&biennial, &[], &now),
+        Some(Utc.ymd(2020, 3, 5).and_hms(18, 0, 0))
+    );
+
+    let one_off = Event {
+        rrule: None,
+        exdates: vec![],
+        ..event.clone()
+    };
+    let now = Utc.ymd(2018, 3, 5).and_hms(19, 0, 0);
+    assert_eq!(
+        next_occurrence(&one_off, &[], &now),
+        Some(one_off.start_date)
+    );
+    let now = Utc.ymd(2018, 3, 6).and_hms(0, 0, 0);
+    assert_eq!(next_occurrence(&one_off, &[], &now), None);
+}
+
+fn apply_override(event: &Event, occurrence: &OccurrenceOverride, tz: Tz) -> OccurrenceResponse {
+    let duration = event.end_date.signed_duration_since(event.start_date);
+    let start_date = occurrence
+        .start_date
+        .unwrap_or(occurrence.original_start_date);
+
+    OccurrenceResponse {
+        identifier: event.id,
+        name: occurrence.name.clone().unwrap_or_else(|| event.name.clone()),
+        description: occurrence
+            .description
+            .clone()
+            .unwrap_or_else(|| event.description.clone()),
+        start_date: render_date(&start_date, tz),
+        end_date: render_date(&occurrence.end_date.unwrap_or(start_date + duration), tz),
+        original_start_date: occurrence.original_start_date,
+    }
+}
+
+fn validate_range(from: &DateTime<Utc>, to: &DateTime<Utc>) -> Result<(), ServiceError> {
+    if to > from && to.signed_duration_since(*from) <= max_range() {
+        Ok(())
+    } else {
+        Err(ServiceError::InvalidRange)
+    }
+}
+
+fn validate_dates(start_date: &DateTime<Utc>, end_date: &DateTime<Utc>) -> Result<(), ServiceError> {
+    if end_date >= start_date {
+        Ok(())
+    } else {
+        Err(ServiceError::InvalidEvent)
+    }
+}
+#[test]
+fn test_validate_dates() {
+    use chrono::TimeZone;
+
+    let start = Utc.ymd(2018, 3, 5).and_hms(18, 0, 0);
+    let end = Utc.ymd(2018, 3, 5).and_hms(20, 0, 0);
+
+    assert!(validate_dates(&start, &end).is_ok());
+    assert!(validate_dates(&start, &start).is_ok());
+    match validate_dates(&end, &start) {
+        Err(ServiceError::InvalidEvent) => (),
+        x => panic!("expected InvalidEvent, got {:?}", x),
+    }
+}
+
+/// a location needs both a latitude and a longitude
+fn validate_location(latitude: Option<f64>, longitude: Option<f64>) -> Result<(), ServiceError> {
+    match (latitude, longitude) {
+        (None, None) => Ok(()),
+        (Some(lat), Some(lng)) if (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lng) => {
+            Ok(())
+        }
+        _ => Err(ServiceError::InvalidEvent),
+    }
+}
+#[test]
+fn test_validate_location() {
+    assert!(validate_location(None, None).is_ok());
+    assert!(validate_location(Some(40.7), Some(-74.0)).is_ok());
+    assert!(validate_location(Some(40.7), None).is_err());
+    assert!(validate_location(Some(91.0), Some(0.0)).is_err());
+    assert!(validate_location(Some(0.0), Some(f64::NAN)).is_err());
+}
+
+/// trims and lowercases tags, dropping the empty and repeated ones
+fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = vec![];
+    for tag in tags {
+        let tag = tag.trim().to_lowercase();
+        if !tag.is_empty() && !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    normalized
+}
+#[test]
+fn test_normalize_tags() {
+    let tags = vec![" Rust ".into(), "rust".into(), "".into(), "Meetup".into()];
+    assert_eq!(normalize_tags(&tags), vec!["rust", "meetup"]);
+}
+
+fn validate_max_attendees(max_attendees: Option<i32>) -> Result<(), ServiceError> {
+    match max_attendees {
+        Some(x) if x < 1 => Err(ServiceError::InvalidEvent),
+        _ => Ok(()),
+    }
+}
+#[test]
+fn test_validate_max_attendees() {
+    assert!(validate_max_attendees(None).is_ok());
+    assert!(validate_max_attendees(Some(1)).is_ok());
+    match validate_max_attendees(Some(0)) {
+        Err(ServiceError::InvalidEvent) => (),
+        x => panic!("expected InvalidEvent, got {:?}", x),
+    }
+}
+#[test]
+fn test_expand_occurrences() {
+    use chrono::TimeZone;
+    use models::event::test_event;
+
+    let event = Event {
+        exdates: vec![Utc.ymd(2018, 3, 26).and_hms(18, 0, 0)],
+        ..test_event("FREQ=WEEKLY;COUNT=4")
+    };
+    let overrides = vec![
+        OccurrenceOverride {
+            event_id: event.id,
+            original_start_date: Utc.ymd(2018, 3, 12).and_hms(18, 0, 0),
+            cancelled: false,
+            name: Some("Meetup at the library".into()),
+            description: None,
+            start_date: Some(Utc.ymd(2018, 3, 12).and_hms(19, 0, 0)),
+            end_date: None,
+        },
+        OccurrenceOverride {
+            event_id: event.id,
+            original_start_date: Utc.ymd(2018, 3, 19).and_hms(18, 0, 0),
+            cancelled: true,
+            name: None,
+            description: None,
+            start_date: None,
+            end_date: None,
+        },
+    ];
+
+    // The first occurrence is underway at the start of the range so it is included
+    let from = Utc.ymd(2018, 3, 5).and_hms(19, 0, 0);
+    let to = Utc.ymd(2018, 4, 1).and_hms(0, 0, 0);
+    let occurrences = expand_occurrences(&event, &overrides, &from, &to, Tz::UTC);
+
+    let starts: Vec<_> = occurrences.iter().map(|x| x.start_date).collect();
+    assert_eq!(
+        starts,
+        vec![
+            Utc.ymd(2018, 3, 5).and_hms(18, 0, 0),
+            Utc.ymd(2018, 3, 12).and_hms(19, 0, 0),
+        ]
+    );
+    assert_eq!(occurrences[1].name, "Meetup at the library");
+    assert_eq!(occurrences[1].end_date, Utc.ymd(2018, 3, 12).and_hms(21, 0, 0));
+    assert_eq!(
+        occurrences[1].original_start_date,
+        Utc.ymd(2018, 3, 12).and_hms(18, 0, 0)
+    );
+}
+#[test]
+fn test_expand_occurrences_time_zone() {
+    use chrono::{NaiveDate, TimeZone};
+    use models::event::test_event;
+
+    // 9am in New York every Monday, daylight saving time started on March 11th
+    let event = Event {
+        name: "Stand up".into(),
+        start_date: Utc.ymd(2018, 3, 5).and_hms(14, 0, 0),
+        end_date: Utc.ymd(2018, 3, 5).and_hms(15, 0, 0),
+        time_zone: "America/New_York".into(),
+        local_start_date: NaiveDate::from_ymd(2018, 3, 5).and_hms(9, 0, 0),
+        local_end_date: NaiveDate::from_ymd(2018, 3, 5).and_hms(10, 0, 0),
+        ..test_event("FREQ=WEEKLY;COUNT=2")
+    };
+    let from = Utc.ymd(2018, 3, 1).and_hms(0, 0, 0);
+    let to = Utc.ymd(2018, 4, 1).and_hms(0, 0, 0);
+
+    let occurrences = expand_occurrences(&event, &[], &from, &to, Tz::America__New_York);
+    let starts: Vec<_> = occurrences.iter().map(|x| x.start_date.to_rfc3339()).collect();
+    assert_eq!(
+        starts,
+        vec!["2018-03-05T09:00:00-05:00", "2018-03-12T09:00:00-04:00"]
+    );
+    assert_eq!(
+        occurrences[1].original_start_date,
+        Utc.ymd(2018, 3, 12).and_hms(13, 0, 0)
+    );
+
+    let occurrences = expand_occurrences(&event, &[], &from, &to, Tz::Europe__London);
+    assert_eq!(
+        occurrences[1].start_date.to_rfc3339(),
+        "2018-03-12T13:00:00+00:00"
+    );
+}
@@ -0,0 +1,372 @@
+//! This is the public API for managing groups, their members and their followers
+use chrono::{DateTime, Utc};
+use models::group::{Group, GroupChanges, GroupMember, GroupRole, NewGroup, NewGroupMember};
+use models::group::IOModel;
+use models::group::pg::PgModel;
+use models::page::{DateIdKey, Page};
+use models::user::IOModel as UserIOModel;
+use models::user::pg::PgModel as UserModel;
+use services::ServiceError;
+use services::user::Service as UserService;
+use uuid::Uuid;
+
+/// represents the form that is needed to create a new group
+///
+/// It is formatted as a [schema:Organization](https://schema.org/Organization)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CreateGroupRequest<'a> {
+    /// This is the OAuth 2.0 access token of the user that will be the group's first admin
+    pub access_token: &'a str,
+    pub name: &'a str,
+    pub description: &'a str,
+}
+
+/// used to look up a single group
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GetGroupRequest {
+    pub group_id: Uuid,
+}
+
+/// used to list the groups
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ListGroupsRequest {
+    pub page: Page<DateIdKey>,
+}
+
+/// used to change an existing group
+///
+/// Only the fields that are `Some` are changed
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UpdateGroupRequest<'a> {
+    /// This is the OAuth 2.0 access token of an admin of the group
+    pub access_token: &'a str,
+    pub group_id: Uuid,
+    pub name: Option<&'a str>,
+    pub description: Option<&'a str>,
+}
+
+/// used to delete an existing group, its events are left on their own
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DeleteGroupRequest<'a> {
+    /// This is the OAuth 2.0 access token of an admin of the group
+    pub access_token: &'a str,
+    pub group_id: Uuid,
+}
+
+/// used to list the members of a group
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GroupMembersRequest {
+    pub group_id: Uuid,
+}
+
+/// used to add a user to a group or to change their role in it
+///
+/// A group always keeps at least one admin
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SetGroupMemberRequest<'a> {
+    /// This is the OAuth 2.0 access token of an admin of the group
+    pub access_token: &'a str,
+    pub group_id: Uuid,
+    pub user_id: Uuid,
+    pub role: GroupRole,
+}
+
+/// used to take a user out of a group
+///
+/// Members can leave on their own, but the last admin can't
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RemoveGroupMemberRequest<'a> {
+    /// This is the OAuth 2.0 access token of an admin of the group or of the member
+    pub access_token: &'a str,
+    pub group_id: Uuid,
+    pub user_id: Uuid,
+}
+
+/// used to follow or stop following a group
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FollowGroupRequest<'a> {
+    /// This is the OAuth 2.0 access token of the follower
+    pub access_token: &'a str,
+    pub group_id: Uuid,
+}
+
+/// the data about a group
+///
+/// It is formatted as a [schema:Organization](https://schema.org/Organization)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GroupResponse {
+    // https://schema.org/Thing
+    pub identifier: Uuid,
+    pub name: String,
+    pub description: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// a list of groups
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GroupListResponse {
+    pub groups: Vec<GroupResponse>,
+    /// The key to ask for the next page with, `None` on the last page
+    #[serde(skip)]
+    pub next: Option<DateIdKey>,
+}
+
+/// a user's role in a group
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GroupMemberResponse {
+    pub group: Uuid,
+    /// The identifier of the user
+    pub member: Uuid,
+    pub role: GroupRole,
+    pub created_at: DateTime<Utc>,
+}
+
+/// the members of a group, the admins first
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GroupMembersResponse {
+    pub members: Vec<GroupMemberResponse>,
+}
+
+/// the response from a delete group request
+///
+/// This is currently an empty object but may be filled in later
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DeleteGroupResponse;
+
+/// the response from a remove group member request
+///
+/// This is currently an empty object but may be filled in later
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RemoveGroupMemberResponse;
+
+/// the response from a follow or unfollow request
+///
+/// This is currently an empty object but may be filled in later
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FollowGroupResponse;
+
+/// The API for the group service
+pub struct Service<'a> {
+    model: &'a PgModel<'a>,
+    user_model: &'a UserModel<'a>,
+    users: &'a UserService<'a>,
+}
+
+impl<'a> Service<'a> {
+    /// create a new Service instance
+    pub fn new(
+        model: &'a PgModel<'a>,
+        user_model: &'a UserModel<'a>,
+        users: &'a UserService<'a>,
+    ) -> Service<'a> {
+        Service {
+            model,
+            user_model,
+            users,
+        }
+    }
+
+    /// create a new group with the current user as its admin
+    pub fn create(&self, request: &CreateGroupRequest) -> Result<GroupResponse, ServiceError> {
+        let user_id = &self.users.current_user_id(request.access_token)?;
+        validate_group(request.name)?;
+
+        let group = self.model.create(
+            &NewGroup {
+                id: &Uuid::new_v4(),
+                name: request.name,
+                description: request.description,
+            },
+            user_id,
+        )?;
+
+        Ok(GroupResponse::from(group))
+    }
+
+    /// get a single group
+    pub fn get(&self, request: &GetGroupRequest) -> Result<GroupResponse, ServiceError> {
+        let group = self.model
+            .find(&request.group_id)?
+            .ok_or(ServiceError::NotFound)?;
+
+        Ok(GroupResponse::from(group))
+    }
+
+    /// list the groups
+    pub fn list(&self, request: &ListGroupsRequest) -> Result<GroupListResponse, ServiceError> {
+        let paged = self.model.list(&request.page)?.map(GroupResponse::from);
+
+        Ok(GroupListResponse {
+            groups: paged.rows,
+            next: paged.next,
+        })
+    }
+
+    /// update a group the current user is an admin of
+    pub fn update(&self, request: &UpdateGroupRequest) -> Result<GroupResponse, ServiceError> {
+        let group = self.administered_group(request.access_token, &request.group_id)?;
+        validate_group(request.name.unwrap_or(&group.name))?;
+
+        let changes = GroupChanges {
+            name: request.name,
+            description: request.description,
+        };
+        if changes.is_empty() {
+            return Ok(GroupResponse::from(group));
+        }
+
+        let group = self.model
+            .update(&group.id, &changes)?
+            .ok_or(ServiceError::NotFound)?;
+
+        Ok(GroupResponse::from(group))
+    }
+
+    /// delete a group the current user is an admin of, its events are left on their own
+    pub fn delete(&self, request: &DeleteGroupRequest) -> Result<DeleteGroupResponse, ServiceError> {
+        let group = self.administered_group(request.access_token, &request.group_id)?;
+        self.model.delete(&group.id)?;
+
+        Ok(DeleteGroupResponse)
+    }
+
+    /// list the members of a group
+    pub fn members(
+        &self,
+        request: &GroupMembersRequest,
+    ) -> Result<GroupMembersResponse, ServiceError> {
+        let group = self.model
+            .find(&request.group_id)?
+            .ok_or(ServiceError::NotFound)?;
+        let members = self.model
+            .members(&group.id)?
+            .into_iter()
+            .map(GroupMemberResponse::from)
+            .collect();
+
+        Ok(GroupMembersResponse { members })
+    }
+
+    /// add a user to a group the current user is an admin of, or change their role in it
+    pub fn set_member(
+        &self,
+        request: &SetGroupMemberRequest,
+    ) -> Result<GroupMemberResponse, ServiceError> {
+        let group = self.administered_group(request.access_token, &request.group_id)?;
+        self.user_model
+            .find(&request.user_id)?
+            .ok_or(ServiceError::NotFound)?;
+
+        let member = self.model
+            .save_member(&NewGroupMember {
+                group_id: &group.id,
+                user_id: &request.user_id,
+                role: request.role.as_str(),
+            })?
+            .ok_or(ServiceError::PermissionDenied)?;
+
+        Ok(GroupMemberResponse::from(member))
+    }
+
+    /// take a user out of a group, the admins can take anyone out and the members can leave
+    pub fn remove_member(
+        &self,
+        request: &RemoveGroupMemberRequest,
+    ) -> Result<RemoveGroupMemberResponse, ServiceError> {
+        let user_id = &self.users.current_user_id(request.access_token)?;
+        let group = self.model
+            .find(&request.group_id)?
+            .ok_or(ServiceError::NotFound)?;
+        let leaving = request.user_id == *user_id;
+        if !leaving && self.model.role(&group.id, user_id)? != Some(GroupRole::Admin) {
+            return Err(ServiceError::PermissionDenied);
+        }
+        self.model
+            .role(&group.id, &request.user_id)?
+            .ok_or(ServiceError::NotFound)?;
+
+        match self.model.remove_member(&group.id, &request.user_id)? {
+            0 => Err(ServiceError::PermissionDenied),
+            _ => Ok(RemoveGroupMemberResponse),
+        }
+    }
+
+    /// follow a group to see its upcoming events
+    pub fn follow(&self, request: &FollowGroupRequest) -> Result<FollowGroupResponse, ServiceError> {
+        let user_id = &self.users.current_user_id(request.access_token)?;
+        let group = self.model
+            .find(&request.group_id)?
+            .ok_or(ServiceError::NotFound)?;
+        self.model.follow(&group.id, user_id)?;
+
+        Ok(FollowGroupResponse)
+    }
+
+    /// stop following a group
+    pub fn unfollow(
+        &self,
+        request: &FollowGroupRequest,
+    ) -> Result<FollowGroupResponse, ServiceError> {
+        let user_id = &self.users.current_user_id(request.access_token)?;
+        match self.model.unfollow(&request.group_id, user_id)? {
+            0 => Err(ServiceError::NotFound),
+            _ => Ok(FollowGroupResponse),
+        }
+    }
+
+    /// find a group and make sure that the current user is one of its admins
+    fn administered_group(&self, access_token: &str, group_id: &Uuid) -> Result<Group, ServiceError> {
+        let user_id = self.users.current_user_id(access_token)?;
+        let group = self.model.find(group_id)?.ok_or(ServiceError::NotFound)?;
+
+        match self.model.role(&group.id, &user_id)? {
+            Some(GroupRole::Admin) => Ok(group),
+            _ => Err(ServiceError::PermissionDenied),
+        }
+    }
+}
+
+// Internal
+
+impl From<Group> for GroupResponse {
+    fn from(group: Group) -> Self {
+        GroupResponse {
+            identifier: group.id,
+            name: group.name,
+            description: group.description,
+            created_at: group.created_at,
+        }
+    }
+}
+
+impl From<GroupMember> for GroupMemberResponse {
+    fn from(member: GroupMember) -> Self {
+        GroupMemberResponse {
+            group: member.group_id,
+            member: member.user_id,
+            role: member.member_role(),
+            created_at: member.created_at,
+        }
+    }
+}
+
+fn validate_group(name: &str) -> Result<(), ServiceError> {
+    if name.trim().is_empty() {
+        Err(ServiceError::InvalidGroup)
+    } else {
+        Ok(())
+    }
+}
+#[test]
+fn test_validate_group() {
+    assert!(validate_group("Rust Meetup").is_ok());
+    assert!(validate_group(" ").is_err());
+}
@@ -0,0 +1,233 @@
+//! This is the public API for inviting people to events
+use chrono::{DateTime, Utc};
+use models::event::Event;
+use models::event::pg::PgModel as EventModel;
+use models::event_member::Role;
+use models::event_member::pg::PgModel as MemberModel;
+use models::invitation::{Invitation, InvitationStatus, NewInvitation};
+use models::invitation::IOModel;
+use models::invitation::pg::PgModel;
+use models::user::IOModel as UserIOModel;
+use models::user::pg::PgModel as UserModel;
+use services::ServiceError;
+use services::member::member_event;
+use services::user::Service as UserService;
+use uuid::Uuid;
+
+/// used to invite a user to an event the current user organizes
+///
+/// The invitee is found by `user_id` or by `email`, set one of them
+#[derive(Serialize, Deserialize, Debug)]
+pub struct InviteRequest<'a> {
+    /// This is the OAuth 2.0 access token of the owner or a co-organizer of the event
+    pub access_token: &'a str,
+    pub event_id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub email: Option<&'a str>,
+}
+
+/// used to list the invitations to an event the current user organizes
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EventInvitationsRequest<'a> {
+    /// This is the OAuth 2.0 access token of the owner or a co-organizer of the event
+    pub access_token: &'a str,
+    pub event_id: Uuid,
+}
+
+/// used to take back an invitation to an event the current user organizes
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RevokeInvitationRequest<'a> {
+    /// This is the OAuth 2.0 access token of the owner or a co-organizer of the event
+    pub access_token: &'a str,
+    pub event_id: Uuid,
+    /// The invited user
+    pub user_id: Uuid,
+}
+
+/// used to accept or decline the current user's invitation to an event
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RespondInvitationRequest<'a> {
+    /// This is the OAuth 2.0 access token of the invited user
+    pub access_token: &'a str,
+    pub event_id: Uuid,
+    /// Either `accepted` or `declined`
+    pub status: InvitationStatus,
+}
+
+/// used to list the current user's invitations
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MyInvitationsRequest<'a> {
+    /// This is the OAuth 2.0 access token of the invited user
+    pub access_token: &'a str,
+}
+
+/// an invitation to an event
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct InvitationResponse {
+    pub event: Uuid,
+    /// The identifier of the invited user
+    pub invitee: Uuid,
+    /// The identifier of the user that sent the invitation
+    pub invited_by: Uuid,
+    pub status: InvitationStatus,
+    pub created_at: DateTime<Utc>,
+    pub responded_at: Option<DateTime<Utc>>,
+}
+
+/// a list of invitations
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct InvitationListResponse {
+    pub invitations: Vec<InvitationResponse>,
+}
+
+/// the response from a revoke invitation request
+///
+/// This is currently an empty object but may be filled in later
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RevokeInvitationResponse;
+
+/// The API for the invitation service
+pub struct Service<'a> {
+    model: &'a PgModel<'a>,
+    events: &'a EventModel<'a>,
+    members: &'a MemberModel<'a>,
+    user_model: &'a UserModel<'a>,
+    users: &'a UserService<'a>,
+}
+
+impl<'a> Service<'a> {
+    /// create a new Service instance
+    pub fn new(
+        model: &'a PgModel<'a>,
+        events: &'a EventModel<'a>,
+        members: &'a MemberModel<'a>,
+        user_model: &'a UserModel<'a>,
+        users: &'a UserService<'a>,
+    ) -> Service<'a> {
+        Service {
+            model,
+            events,
+            members,
+            user_model,
+            users,
+        }
+    }
+
+    /// invite a user to an event the current user organizes
+    ///
+    /// Inviting someone again leaves their invitation as it is
+    pub fn invite(&self, request: &InviteRequest) -> Result<InvitationResponse, ServiceError> {
+        let user_id = &self.users.current_user_id(request.access_token)?;
+        let event = self.organized_event(user_id, &request.event_id)?;
+        let invitee = match (request.user_id, request.email) {
+            (Some(ref user_id), None) => self.user_model.find(user_id)?,
+            (None, Some(email)) => self.user_model.find_by_email(email.trim())?,
+            _ => None,
+        }.ok_or(ServiceError::InvalidInvitation)?;
+        if invitee.id == event.owner_id {
+            return Err(ServiceError::InvalidInvitation);
+        }
+
+        let invitation = self.model.invite(&NewInvitation {
+            event_id: &event.id,
+            user_id: &invitee.id,
+            invited_by: user_id,
+        })?;
+
+        Ok(InvitationResponse::from(invitation))
+    }
+
+    /// list the invitations to an event the current user organizes, the oldest first
+    pub fn event_invitations(
+        &self,
+        request: &EventInvitationsRequest,
+    ) -> Result<InvitationListResponse, ServiceError> {
+        let user_id = &self.users.current_user_id(request.access_token)?;
+        let event = self.organized_event(user_id, &request.event_id)?;
+        let invitations = self.model
+            .for_event(&event.id)?
+            .into_iter()
+            .map(InvitationResponse::from)
+            .collect();
+
+        Ok(InvitationListResponse { invitations })
+    }
+
+    /// take back an invitation to an event the current user organizes
+    pub fn revoke(
+        &self,
+        request: &RevokeInvitationRequest,
+    ) -> Result<RevokeInvitationResponse, ServiceError> {
+        let user_id = &self.users.current_user_id(request.access_token)?;
+        let event = self.organized_event(user_id, &request.event_id)?;
+        match self.model.revoke(&event.id, &request.user_id)? {
+            0 => Err(ServiceError::NotFound),
+            _ => Ok(RevokeInvitationResponse),
+        }
+    }
+
+    /// accept or decline the current user's invitation to an event
+    pub fn respond(
+        &self,
+        request: &RespondInvitationRequest,
+    ) -> Result<InvitationResponse, ServiceError> {
+        let user_id = &self.users.current_user_id(request.access_token)?;
+        if request.status == InvitationStatus::Pending {
+            return Err(ServiceError::InvalidInvitation);
+        }
+
+        let invitation = self.model
+            .respond(&request.event_id, user_id, request.status)?
+            .ok_or(ServiceError::NotFound)?;
+
+        Ok(InvitationResponse::from(invitation))
+    }
+
+    /// list the current user's invitations, ordered by the start dates of their events
+    pub fn my_invitations(
+        &self,
+        request: &MyInvitationsRequest,
+    ) -> Result<InvitationListResponse, ServiceError> {
+        let user_id = &self.users.current_user_id(request.access_token)?;
+        let invitations = self.model
+            .for_user(user_id)?
+            .into_iter()
+            .map(InvitationResponse::from)
+            .collect();
+
+        Ok(InvitationListResponse { invitations })
+    }
+
+    /// find an event that a user owns or co-organizes
+    fn organized_event(&self, user_id: &Uuid, event_id: &Uuid) -> Result<Event, ServiceError> {
+        let (event, _) = member_event(
+            self.events,
+            self.members,
+            user_id,
+            event_id,
+            Role::can_edit_event,
+        )?;
+        Ok(event)
+    }
+}
+
+// Internal
+
+impl From<Invitation> for InvitationResponse {
+    fn from(invitation: Invitation) -> Self {
+        InvitationResponse {
+            event: invitation.event_id,
+            invitee: invitation.user_id,
+            invited_by: invitation.invited_by,
+            status: invitation.invitation_status(),
+            created_at: invitation.created_at,
+            responded_at: invitation.responded_at,
+        }
+    }
+}
@@ -0,0 +1,198 @@
+//! This is the public API for managing the roles people have in events
+use chrono::{DateTime, Utc};
+use models::event::Event;
+use models::event::IOModel as EventIOModel;
+use models::event::pg::PgModel as EventModel;
+use models::event_member::{EventMember, NewEventMember, Role};
+use models::event_member::IOModel;
+use models::event_member::pg::PgModel;
+use models::user::IOModel as UserIOModel;
+use models::user::pg::PgModel as UserModel;
+use services::ServiceError;
+use services::user::Service as UserService;
+use uuid::Uuid;
+
+/// used to list the members of an event
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MembersRequest<'a> {
+    /// This is the OAuth 2.0 access token of a member of the event
+    pub access_token: &'a str,
+    pub event_id: Uuid,
+}
+
+/// used to give a user a role in an event
+///
+/// Only the owner can add co-organizers, co-organizers can add check-in staff and attendees
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SetMemberRequest<'a> {
+    /// This is the OAuth 2.0 access token of the owner or a co-organizer of the event
+    pub access_token: &'a str,
+    pub event_id: Uuid,
+    pub user_id: Uuid,
+    pub role: Role,
+}
+
+/// used to take away a user's role in an event
+///
+/// Anyone but the owner can take away their own role
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RemoveMemberRequest<'a> {
+    /// This is the OAuth 2.0 access token of the owner or a co-organizer of the event
+    pub access_token: &'a str,
+    pub event_id: Uuid,
+    pub user_id: Uuid,
+}
+
+/// a user's role in an event
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MemberResponse {
+    pub event: Uuid,
+    /// The identifier of the user
+    pub member: Uuid,
+    pub role: Role,
+    pub created_at: DateTime<Utc>,
+}
+
+/// the members of an event, the owner first
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MembersResponse {
+    pub members: Vec<MemberResponse>,
+}
+
+/// the response from a remove member request
+///
+/// This is currently an empty object but may be filled in later
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RemoveMemberResponse;
+
+/// The API for the member service
+pub struct Service<'a> {
+    model: &'a PgModel<'a>,
+    events: &'a EventModel<'a>,
+    user_model: &'a UserModel<'a>,
+    users: &'a UserService<'a>,
+}
+
+impl<'a> Service<'a> {
+    /// create a new Service instance
+    pub fn new(
+        model: &'a PgModel<'a>,
+        events: &'a EventModel<'a>,
+        user_model: &'a UserModel<'a>,
+        users: &'a UserService<'a>,
+    ) -> Service<'a> {
+        Service {
+            model,
+            events,
+            user_model,
+            users,
+        }
+    }
+
+    /// list the members of an event the current user is a member of
+    pub fn members(&self, request: &MembersRequest) -> Result<MembersResponse, ServiceError> {
+        let user_id = &self.users.current_user_id(request.access_token)?;
+        let (event, _) = member_event(self.events, self.model, user_id, &request.event_id, |_| {
+            true
+        })?;
+        let members = self.model
+            .list(&event.id)?
+            .into_iter()
+            .map(MemberResponse::from)
+            .collect();
+
+        Ok(MembersResponse { members })
+    }
+
+    /// give a user a role in an event, replacing the role they had
+    pub fn set_member(&self, request: &SetMemberRequest) -> Result<MemberResponse, ServiceError> {
+        let user_id = &self.users.current_user_id(request.access_token)?;
+        let (event, role) = member_event(
+            self.events,
+            self.model,
+            user_id,
+            &request.event_id,
+            Role::can_edit_event,
+        )?;
+        let current = self.model.role(&event.id, &request.user_id)?;
+        if !role.can_assign(request.role) || current.is_some_and(|x| !role.can_assign(x)) {
+            return Err(ServiceError::PermissionDenied);
+        }
+        self.user_model
+            .find(&request.user_id)?
+            .ok_or(ServiceError::NotFound)?;
+
+        let member = self.model
+            .save(&NewEventMember {
+                event_id: &event.id,
+                user_id: &request.user_id,
+                role: request.role.as_str(),
+            })?
+            .ok_or(ServiceError::PermissionDenied)?;
+
+        Ok(MemberResponse::from(member))
+    }
+
+    /// take away a user's role in an event
+    pub fn remove_member(
+        &self,
+        request: &RemoveMemberRequest,
+    ) -> Result<RemoveMemberResponse, ServiceError> {
+        let user_id = &self.users.current_user_id(request.access_token)?;
+        let (event, role) = member_event(self.events, self.model, user_id, &request.event_id, |_| {
+            true
+        })?;
+        let removed = self.model
+            .role(&event.id, &request.user_id)?
+            .ok_or(ServiceError::NotFound)?;
+        let leaving = request.user_id == *user_id && removed != Role::Owner;
+        if !leaving && !role.can_assign(removed) {
+            return Err(ServiceError::PermissionDenied);
+        }
+
+        match self.model.remove(&event.id, &request.user_id)? {
+            0 => Err(ServiceError::NotFound),
+            _ => Ok(RemoveMemberResponse),
+        }
+    }
+}
+
+/// find an event that a user may see along with the role they have in it
+///
+/// This is used by the other services to authorize their requests, `allowed` says whether the
+/// role is enough.  The events the user may not see are not found, so their ids don't leak.
+pub fn member_event<F>(
+    events: &EventModel,
+    members: &PgModel,
+    user_id: &Uuid,
+    event_id: &Uuid,
+    allowed: F,
+) -> Result<(Event, Role), ServiceError>
+where
+    F: Fn(&Role) -> bool,
+{
+    let event = events
+        .find_visible(event_id, Some(user_id))?
+        .ok_or(ServiceError::NotFound)?;
+
+    match members.role(&event.id, user_id)? {
+        Some(role) if allowed(&role) => Ok((event, role)),
+        _ => Err(ServiceError::PermissionDenied),
+    }
+}
+
+// Internal
+
+impl From<EventMember> for MemberResponse {
+    fn from(member: EventMember) -> Self {
+        MemberResponse {
+            event: member.event_id,
+            member: member.user_id,
+            role: member.member_role(),
+            created_at: member.created_at,
+        }
+    }
+}
@@ -1,2 +1,52 @@
 //! API for the various services
+use diesel;
+use std::fmt;
+
+pub mod attendance;
+pub mod calendar;
+pub mod comment;
+pub mod event;
+pub mod group;
+pub mod invitation;
+pub mod member;
+pub mod outbox;
+pub mod reminder;
 pub mod user;
+pub mod venue;
+pub mod webhook;
+
+/// errors that can happen with the services
+///
+#[derive(Debug, Fail)]
+pub enum ServiceError {
+    AlreadyCheckedIn,
+    InvalidCalendar,
+    InvalidComment,
+    InvalidConfirmToken,
+    InvalidEvent,
+    InvalidGroup,
+    InvalidInvitation,
+    InvalidLocation,
+    InvalidRange,
+    InvalidRecurrence,
+    InvalidTicket,
+    InvalidTimeZone,
+    InvalidVenue,
+    InvalidWebhook,
+    NotFound,
+    PermissionDenied,
+    UserExists,
+    DBError(diesel::result::Error),
+}
+
+impl From<diesel::result::Error> for ServiceError {
+    fn from(it: diesel::result::Error) -> Self {
+        ServiceError::DBError(it)
+    }
+}
+
+impl fmt::Display for ServiceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
@@ -0,0 +1,86 @@
+//! This delivers the messages that are waiting in the outbox
+use chrono::{DateTime, Utc};
+use models::outbox::{Message, MessageKind, MessageStatus};
+use models::outbox::IOModel;
+use models::outbox::pg::PgModel;
+use models::webhook::IOModel as WebhookIOModel;
+use models::webhook::pg::PgModel as WebhookModel;
+use notifier::{Notification, Notifier};
+use serde_json;
+use services::ServiceError;
+use services::webhook::{self, WebhookPayload};
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// The API for the outbox service
+pub struct Service<'a> {
+    model: &'a PgModel<'a>,
+    webhooks: &'a WebhookModel<'a>,
+    notifier: &'a dyn Notifier,
+}
+
+impl<'a> Service<'a> {
+    /// create a new Service instance
+    pub fn new(
+        model: &'a PgModel<'a>,
+        webhooks: &'a WebhookModel<'a>,
+        notifier: &'a dyn Notifier,
+    ) -> Service<'a> {
+        Service {
+            model,
+            webhooks,
+            notifier,
+        }
+    }
+
+    /// deliver up to `limit` of the messages that are due at `now`, returns how many were tried
+    ///
+    /// The failures are retried by a later call, with a growing wait, until they are dead
+    pub fn deliver_due(&self, now: &DateTime<Utc>, limit: usize) -> Result<usize, ServiceError> {
+        let mut tried = 0;
+        while tried < limit {
+            let message = match self.model.deliver_next(now, |x| self.deliver(x))? {
+                Some(x) => x,
+                None => break,
+            };
+            if message.message_status() == MessageStatus::Dead {
+                eprintln!(
+                    "Message {} is dead after {} attempts: {}",
+                    message.id,
+                    message.attempts,
+                    message.last_error.as_ref().map_or("", String::as_str)
+                );
+            }
+            tried += 1;
+        }
+        Ok(tried)
+    }
+
+    // Internal
+
+    fn deliver(&self, message: &Message) -> Result<(), String> {
+        match MessageKind::from_str(&message.kind).map_err(|x| x.to_string())? {
+            MessageKind::Notification => {
+                let notification: Notification =
+                    serde_json::from_str(&message.payload).map_err(|x| x.to_string())?;
+                self.notifier
+                    .notify(&notification)
+                    .map_err(|x| x.to_string())
+            }
+            // This runs in the message's transaction, so the deliveries are queued exactly once
+            MessageKind::Webhook => {
+                let payload: WebhookPayload =
+                    serde_json::from_str(&message.payload).map_err(|x| x.to_string())?;
+                self.webhooks
+                    .fan_out(payload.topic.as_str(), &message.payload)
+                    .map(|_| ())
+                    .map_err(|x| x.to_string())
+            }
+            MessageKind::WebhookDelivery => {
+                let delivery_id: Uuid =
+                    serde_json::from_str(&message.payload).map_err(|x| x.to_string())?;
+                webhook::deliver(self.webhooks, &delivery_id)
+            }
+        }
+    }
+}
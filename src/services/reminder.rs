@@ -0,0 +1,126 @@
+//! This queues the reminders for the people that are going to an event
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use models::attendance::IOModel as AttendanceIOModel;
+use models::attendance::pg::PgModel as AttendanceModel;
+use models::event::{Event, IOModel as EventIOModel};
+use models::event::pg::PgModel as EventModel;
+use models::outbox::{MessageKind, Outgoing};
+use models::reminder::{IOModel, NewReminder, ReminderKind};
+use models::reminder::pg::PgModel;
+use models::user::User;
+use notifier::Notification;
+use services::ServiceError;
+use services::event::{event_time_zone, expand_occurrences, OccurrenceResponse};
+
+/// The API for the reminder service
+pub struct Service<'a> {
+    model: &'a PgModel<'a>,
+    events: &'a EventModel<'a>,
+    attendances: &'a AttendanceModel<'a>,
+}
+
+impl<'a> Service<'a> {
+    /// create a new Service instance
+    pub fn new(
+        model: &'a PgModel<'a>,
+        events: &'a EventModel<'a>,
+        attendances: &'a AttendanceModel<'a>,
+    ) -> Service<'a> {
+        Service {
+            model,
+            events,
+            attendances,
+        }
+    }
+
+    /// queue the reminders that are due at `now` and return how many were queued
+    ///
+    /// Every reminder is recorded along with its message in the outbox so it never goes out
+    /// twice, not even after a restart or from another server
+    pub fn queue_due(&self, now: &DateTime<Utc>) -> Result<usize, ServiceError> {
+        let to = *now + ReminderKind::DayBefore.lead_time();
+        let mut queued = 0;
+        for event in self.events.starting_between(now, &to)? {
+            let overrides = if event.rrule.is_some() {
+                self.events.occurrence_overrides(&event.id)?
+            } else {
+                vec![]
+            };
+            let tz = event_time_zone(&event);
+            let due = expand_occurrences(&event, &overrides, now, &to, tz)
+                .into_iter()
+                .filter_map(|x| {
+                    ReminderKind::due(now, &x.start_date.with_timezone(&Utc)).map(|kind| (x, kind))
+                })
+                .collect::<Vec<_>>();
+            if due.is_empty() {
+                continue;
+            }
+
+            let attendees = self.attendances.confirmed(&event.id)?;
+            for &(ref occurrence, kind) in &due {
+                for user in &attendees {
+                    if self.queue(&event, occurrence, kind, user, tz)? {
+                        queued += 1;
+                    }
+                }
+            }
+        }
+        Ok(queued)
+    }
+
+    // Internal
+
+    /// queue one reminder unless it was already queued, true when it was put in the outbox
+    fn queue(
+        &self,
+        event: &Event,
+        occurrence: &OccurrenceResponse,
+        kind: ReminderKind,
+        user: &User,
+        tz: Tz,
+    ) -> Result<bool, ServiceError> {
+        let reminder = NewReminder {
+            event_id: &event.id,
+            user_id: &user.id,
+            occurrence_start: &occurrence.original_start_date,
+            kind: kind.as_str(),
+        };
+        let message = Outgoing {
+            kind: MessageKind::Notification,
+            payload: reminder_notification(occurrence, kind, user, tz).to_json(),
+        };
+
+        Ok(self.model.record(&reminder, &message)?)
+    }
+}
+
+/// the message that reminds a user of an occurrence, its time is on the event's wall clock
+fn reminder_notification(
+    occurrence: &OccurrenceResponse,
+    kind: ReminderKind,
+    user: &User,
+    tz: Tz,
+) -> Notification {
+    let when = match kind {
+        ReminderKind::HourBefore => "in an hour",
+        ReminderKind::DayBefore => "tomorrow",
+    };
+    let start = occurrence.start_date.with_timezone(&tz);
+
+    Notification {
+        user_id: user.id,
+        email: user.email.clone(),
+        name: user.name.clone(),
+        kind: "reminder".to_string(),
+        subject: format!("Reminder: {} starts {}", occurrence.name, when),
+        body: format!(
+            "Hi {},\n\n{} starts {}, on {}.\n",
+            user.name,
+            occurrence.name,
+            when,
+            start.format("%A %B %-d at %H:%M %Z")
+        ),
+    }
+}
@@ -1,35 +1,23 @@
 //!  This serves as the public API for the events service
+use chrono::Utc;
 use uuid::Uuid;
-use diesel;
-use std::fmt;
 use models::user::{NewUser, User};
 use models::user::IOModel;
 use models::user::pg::PgModel;
-use jsonwebtoken as jwt;
+use models::refresh_token::{NewRefreshToken, RefreshToken, Rotation};
+use models::refresh_token::IOModel as RefreshTokenIOModel;
+use models::refresh_token::pg::PgModel as RefreshTokenModel;
+use models::revoked_token::NewRevokedToken;
+use models::revoked_token::IOModel as RevokedTokenIOModel;
+use models::revoked_token::pg::PgModel as RevokedTokenModel;
+use models::outbox::{MessageKind, Outgoing};
+use models::webhook::WebhookTopic;
+use notifier::Notification;
+use services::webhook::publish;
 use std::default::Default;
-use serde::ser::Serialize;
+use token::{TokenKind, Tokens};
 
-/// errors that can happen with the service
-///
-#[derive(Debug, Fail)]
-pub enum ServiceError {
-    InvalidConfirmToken,
-    PermissionDenied,
-    UserExists,
-    DBError(diesel::result::Error),
-}
-
-impl From<diesel::result::Error> for ServiceError {
-    fn from(it: diesel::result::Error) -> Self {
-        ServiceError::DBError(it)
-    }
-}
-
-impl fmt::Display for ServiceError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{:?}", self)
-    }
-}
+pub use services::ServiceError;
 
 /// represents an OAuth 2.0 password grant
 ///
@@ -61,6 +49,10 @@ pub struct AccessTokenResponse {
 struct AccessTokenClaim {
     /// The standard JWT subject field
     sub: String,
+    /// The standard JWT ID field, it is the key of the token on the denylist
+    jti: String,
+    /// The session, which is the family of the refresh token from the same login
+    sid: String,
     /// A flag that marks the JSON as an access token so that the access token is shaped differntly that
     /// a refresh or confirm token.  Without this flag, a refresh or confirm token could be used as an access token
     access_token: bool,
@@ -79,14 +71,55 @@ pub struct RefreshGrantRequest<'a> {
 
 /// represents the data inside of the [JWT](https://en.wikipedia.org/wiki/JSON_Web_Token) for the refresh token
 ///
+/// Each refresh token can only be used once, the `jti` is the key of its row in the
+/// `refresh_tokens` table
 #[derive(Debug, Serialize, Deserialize)]
 struct RefreshTokenClaim {
     /// The standard JWT subject field
     sub: String,
+    /// The standard JWT ID field
+    jti: String,
     /// The flag that makes the claim data a refresh token. See the explanation in [`AccessTokenClaim`]
     refresh_token: bool,
 }
 
+/// the checked contents of an access token
+#[derive(Debug, PartialEq)]
+struct AccessToken {
+    user_id: Uuid,
+    jti: Uuid,
+    session_id: Uuid,
+}
+
+/// represents an OAuth 2.0 Token Revocation request
+///
+/// The `token_type_hint` of the spec isn't needed, the claims tell an access token from a
+/// refresh token.
+///
+/// See: [RFC-7009 Section 2.1](https://tools.ietf.org/html/rfc7009#section-2.1)
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct RevokeRequest<'a> {
+    /// An access or a refresh token
+    pub token: &'a str,
+}
+
+/// the response from a revoke request
+///
+/// It is the same whether or not the token was valid
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RevokeResponse;
+
+/// used to log the current user out of every session
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LogOutAllRequest<'a> {
+    /// This is the OAuth 2.0 access token that authorizes the current user
+    pub access_token: &'a str,
+}
+
+/// the response from a log out request
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LogOutAllResponse;
+
 /// represents the form that is needed to register a new user
 ///
 /// It is formatted as a [schema:Person](https://schema.org/Person) with an additional
@@ -99,21 +132,20 @@ pub struct RegisterRequest<'a> {
     pub password: &'a str,
 }
 
-/// contains the confirmation token for confirming the new user
+/// the response from a register request
 ///
+/// The confirmation token is emailed to the new user, this is currently an empty object but may
+/// be filled in later
 #[derive(Serialize, Deserialize, Debug)]
-pub struct RegisterResponse {
-    /// This is used in the ConfirmNewUserRequest in order to activate a new user
-    pub confirm_token: String,
-}
+pub struct RegisterResponse;
 
 /// used to confirm the new user
 ///
-/// The `confirm_token` is the same confirm token that was given out after registering
+/// The `confirm_token` is the same confirm token that was emailed after registering
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ConfirmNewUserRequest<'a> {
-    /// The confirm_token that was given out after regitering
+    /// The confirm_token that was emailed after registering
     pub confirm_token: &'a str,
 }
 
@@ -157,13 +189,29 @@ pub struct CurrentUserResponse {
 pub struct Service<'a> {
     // TODO: make this generic so we can mock it out
     model: &'a PgModel<'a>,
-    secret_key: &'a [u8],
+    refresh_token_model: &'a RefreshTokenModel<'a>,
+    revoked_token_model: &'a RevokedTokenModel<'a>,
+    tokens: &'a Tokens,
+    /// The URL the API is served at, the confirmation link in the email points to it
+    public_url: &'a str,
 }
 
 impl<'a> Service<'a> {
     /// create a new Service instance
-    pub fn new(model: &'a PgModel<'a>, secret_key: &'a [u8]) -> Service<'a> {
-        Service { model, secret_key }
+    pub fn new(
+        model: &'a PgModel<'a>,
+        refresh_token_model: &'a RefreshTokenModel<'a>,
+        revoked_token_model: &'a RevokedTokenModel<'a>,
+        tokens: &'a Tokens,
+        public_url: &'a str,
+    ) -> Service<'a> {
+        Service {
+            model,
+            refresh_token_model,
+            revoked_token_model,
+            tokens,
+            public_url,
+        }
     }
 
     /// call to get an access token using a un/pw
@@ -175,43 +223,116 @@ impl<'a> Service<'a> {
             .verify_login(request.username, request.password)?
             .ok_or(ServiceError::PermissionDenied)?;
 
-        Ok(access_token_response(self.secret_key, &user))
+        // Every login starts a new family of refresh tokens
+        let refresh_token = self.refresh_token_model.create(&NewRefreshToken {
+            jti: &Uuid::new_v4(),
+            family_id: &Uuid::new_v4(),
+            user_id: &user.id,
+            expires_at: &(Utc::now() + self.tokens.lifetimes().refresh),
+        })?;
+
+        Ok(access_token_response(self.tokens, &user, &refresh_token))
     }
 
     /// call to get a new access token using a refresh token
+    ///
+    /// The refresh token is traded for a new one.  Using it a second time means that it leaked,
+    /// so every token from the same login is revoked, see
+    /// [OAuth 2.0 Security BCP section 4.14](https://tools.ietf.org/html/draft-ietf-oauth-security-topics#section-4.14)
     pub fn refresh_token_grant(
         &self,
         request: &RefreshGrantRequest,
     ) -> Result<AccessTokenResponse, ServiceError> {
-        let id = &validate_refresh_token(self.secret_key, request.refresh_token)
+        let (id, jti) = validate_refresh_token(self.tokens, request.refresh_token)
             .ok_or(ServiceError::PermissionDenied)?;
 
-        let user = self.model.find(id)?.ok_or(ServiceError::PermissionDenied)?;
+        let rotation = self.refresh_token_model.rotate(
+            &jti,
+            &Uuid::new_v4(),
+            &(Utc::now() + self.tokens.lifetimes().refresh),
+        )?;
+        let refresh_token = match rotation {
+            Rotation::Rotated(x) => x,
+            // A reused token has revoked its family in the same transaction
+            Rotation::Reused | Rotation::Invalid => return Err(ServiceError::PermissionDenied),
+        };
+        if refresh_token.user_id != id {
+            return Err(ServiceError::PermissionDenied);
+        }
+
+        let user = self.model.find(&id)?.ok_or(ServiceError::PermissionDenied)?;
 
-        Ok(access_token_response(self.secret_key, &user))
+        Ok(access_token_response(self.tokens, &user, &refresh_token))
     }
 
-    /// call to register a new user
+    /// call to revoke an access or a refresh token
+    ///
+    /// An access token goes on the denylist until it expires.  A refresh token revokes its
+    /// family, which also revokes the access tokens from the same login.  A token that is
+    /// invalid is ignored, as the spec asks.
+    ///
+    /// See: [RFC-7009 Section 2.2](https://tools.ietf.org/html/rfc7009#section-2.2)
+    pub fn revoke(&self, request: &RevokeRequest) -> Result<RevokeResponse, ServiceError> {
+        if let Some(token) = decode_access_token(self.tokens, request.token) {
+            self.revoked_token_model.revoke(&NewRevokedToken {
+                jti: &token.jti,
+                // No access token outlives this
+                expires_at: &(Utc::now() + self.tokens.lifetimes().access),
+            })?;
+        } else if let Some((id, jti)) = validate_refresh_token(self.tokens, request.token) {
+            if let Some(token) = self.refresh_token_model.find(&jti)? {
+                if token.user_id == id {
+                    self.refresh_token_model.revoke_family(&token.family_id)?;
+                }
+            }
+        }
+
+        Ok(RevokeResponse)
+    }
+
+    /// call to log the current user out of every session
+    ///
+    /// Every refresh token of the user is revoked, along with the access tokens from the same
+    /// logins, including the one that made this request
+    pub fn log_out_all(
+        &self,
+        request: &LogOutAllRequest,
+    ) -> Result<LogOutAllResponse, ServiceError> {
+        let user = self.authenticate(request.access_token)?;
+        self.refresh_token_model.revoke_user(&user.id)?;
+
+        Ok(LogOutAllResponse)
+    }
+
+    /// call to register a new user, the confirmation token is emailed to them
+    ///
+    /// The email goes through the outbox so it is only sent when the user is stored
     pub fn register(&self, request: &RegisterRequest) -> Result<RegisterResponse, ServiceError> {
+        let id = &Uuid::new_v4();
         let new_user = NewUser {
-            id: &Uuid::new_v4(),
+            id,
             name: request.name,
             password: request.password,
             email: request.email,
         };
-        let user = self.model
-            .create(&new_user)?
+        let confirm_token = self.tokens.encode(
+            TokenKind::Confirm,
+            ConfirmTokenClaim {
+                sub: id.simple().to_string(),
+                confirm_token: true,
+            },
+        );
+        let email = Outgoing {
+            kind: MessageKind::Notification,
+            payload: confirm_notification(&new_user, &self.confirm_url(&confirm_token)).to_json(),
+        };
+        self.model
+            .create(&new_user, |user| {
+                vec![email, user_webhook(WebhookTopic::UserRegistered, user)]
+            })?
             .ok_or(ServiceError::UserExists)?;
 
-        Ok(RegisterResponse {
-            confirm_token: encode_token(
-                self.secret_key,
-                ConfirmTokenClaim {
-                    sub: user.id.simple().to_string(),
-                    confirm_token: true,
-                },
-            ),
-        })
+        Ok(RegisterResponse)
     }
 
     /// confirm a user
@@ -219,10 +340,12 @@ impl<'a> Service<'a> {
         &self,
         request: &ConfirmNewUserRequest,
     ) -> Result<ConfirmNewUserResponse, ServiceError> {
-        let id = &validate_confirm_token(self.secret_key, request.confirm_token)
+        let id = &validate_confirm_token(self.tokens, request.confirm_token)
             .ok_or(ServiceError::InvalidConfirmToken)?;
 
-        self.model.confirm(id)?;
+        // A user that was already confirmed is left alone, so the webhook only fires once
+        self.model
+            .confirm(id, |user| vec![user_webhook(WebhookTopic::UserConfirmed, user)])?;
 
         Ok(ConfirmNewUserResponse)
     }
@@ -232,72 +355,201 @@ impl<'a> Service<'a> {
         &self,
         request: &CurrentUserRequest,
     ) -> Result<CurrentUserResponse, ServiceError> {
-        let id = &validate_access_token(self.secret_key, request.access_token)
+        let user = self.authenticate(request.access_token)?;
+
+        Ok(CurrentUserResponse::from(user))
+    }
+
+    /// get the id of the user for a request token
+    ///
+    /// This is used by the other services to authorize their requests
+    pub fn current_user_id(&self, access_token: &str) -> Result<Uuid, ServiceError> {
+        Ok(self.authenticate(access_token)?.id)
+    }
+
+    /// get the id of the user for a request that may be made anonymously
+    ///
+    /// This is used by the read paths that enforce event visibility, a token that is sent must
+    /// still be valid
+    pub fn viewer_id(&self, access_token: Option<&str>) -> Result<Option<Uuid>, ServiceError> {
+        access_token.map(|x| self.current_user_id(x)).transpose()
+    }
+
+    /// the link that confirms a new user
+    fn confirm_url(&self, confirm_token: &str) -> String {
+        format!(
+            "{}/oauth/register/confirm?confirm_token={}",
+            self.public_url.trim_end_matches('/'),
+            confirm_token
+        )
+    }
+
+    /// find the confirmed user that owns an access token
+    fn authenticate(&self, access_token: &str) -> Result<User, ServiceError> {
+        let id = &self.validate_access_token(access_token)?
             .ok_or(ServiceError::PermissionDenied)?;
-        let user = self.model.find(id)?.ok_or(ServiceError::PermissionDenied)?;
 
-        Ok(CurrentUserResponse {
+        self.model.find(id)?.ok_or(ServiceError::PermissionDenied)
+    }
+
+    /// the user that owns an access token, `None` when it is invalid or it or its session was
+    /// revoked
+    fn validate_access_token(&self, access_token: &str) -> Result<Option<Uuid>, ServiceError> {
+        let token = match decode_access_token(self.tokens, access_token) {
+            Some(x) => x,
+            None => return Ok(None),
+        };
+        if self.revoked_token_model.is_revoked(&token.jti)?
+            || self.refresh_token_model.is_family_revoked(&token.session_id)?
+        {
+            return Ok(None);
+        }
+
+        Ok(Some(token.user_id))
+    }
+}
+
+impl From<User> for CurrentUserResponse {
+    fn from(user: User) -> Self {
+        CurrentUserResponse {
             identifier: user.id,
             name: user.name,
             email: user.email,
-        })
+        }
     }
 }
 
 // Internal
 
-fn validate_confirm_token(key: &[u8], token: &str) -> Option<Uuid> {
-    let token_result = jwt::decode::<ConfirmTokenClaim>(token, key, &jwt::Validation::default());
-
-    token_result.ok().and_then(|token| {
-        if token.claims.confirm_token {
-            Uuid::parse_str(&token.claims.sub).ok()
-        } else {
-            None
-        }
-    })
+/// the webhook payload for a change to a user, it is shaped like `CurrentUserResponse`
+fn user_webhook(topic: WebhookTopic, user: &User) -> Outgoing {
+    publish(
+        topic,
+        &CurrentUserResponse {
+            identifier: user.id,
+            name: user.name.clone(),
+            email: user.email.clone(),
+        },
+    )
 }
 
-fn validate_access_token(key: &[u8], token: &str) -> Option<Uuid> {
-    if let Ok(data) = jwt::decode::<AccessTokenClaim>(token, key, &jwt::Validation::default()) {
-        if data.claims.access_token {
-            return Uuid::parse_str(&data.claims.sub).ok();
-        }
+/// the email that asks a new user to confirm their address
+fn confirm_notification(user: &NewUser, confirm_url: &str) -> Notification {
+    Notification {
+        user_id: *user.id,
+        email: user.email.to_string(),
+        name: user.name.to_string(),
+        kind: "confirm".to_string(),
+        subject: "Confirm your account".to_string(),
+        body: format!(
+            "Hi {},\n\nOpen this link to confirm your account:\n\n{}\n",
+            user.name, confirm_url
+        ),
     }
-    None
 }
 
-fn validate_refresh_token(key: &[u8], token: &str) -> Option<Uuid> {
-    if let Ok(data) = jwt::decode::<RefreshTokenClaim>(token, key, &jwt::Validation::default()) {
-        if data.claims.refresh_token {
-            return Uuid::parse_str(&data.claims.sub).ok();
-        }
-    }
-    None
+fn validate_confirm_token(tokens: &Tokens, token: &str) -> Option<Uuid> {
+    tokens
+        .decode::<ConfirmTokenClaim>(TokenKind::Confirm, token)
+        .filter(|x| x.confirm_token)
+        .and_then(|x| Uuid::parse_str(&x.sub).ok())
 }
 
-fn encode_token<T: Serialize>(key: &[u8], claims: T) -> String {
-    // TODO: handle error correctly
-    jwt::encode(&jwt::Header::default(), &claims, key).unwrap_or_else(|_| "".into())
+/// the contents of an access token with a valid signature and lifetime, the denylist is checked
+/// by `Service::validate_access_token`
+fn decode_access_token(tokens: &Tokens, token: &str) -> Option<AccessToken> {
+    tokens
+        .decode::<AccessTokenClaim>(TokenKind::Access, token)
+        .filter(|x| x.access_token)
+        .and_then(|x| {
+            Some(AccessToken {
+                user_id: Uuid::parse_str(&x.sub).ok()?,
+                jti: Uuid::parse_str(&x.jti).ok()?,
+                session_id: Uuid::parse_str(&x.sid).ok()?,
+            })
+        })
+}
+
+/// the user and the jti of a refresh token
+fn validate_refresh_token(tokens: &Tokens, token: &str) -> Option<(Uuid, Uuid)> {
+    tokens
+        .decode::<RefreshTokenClaim>(TokenKind::Refresh, token)
+        .filter(|x| x.refresh_token)
+        .and_then(|x| Some((Uuid::parse_str(&x.sub).ok()?, Uuid::parse_str(&x.jti).ok()?)))
+}
+#[test]
+fn test_validate_tokens() {
+    use token::Lifetimes;
+
+    let tokens = &Tokens::new(b"key", "http://api", "http://api", Lifetimes::default());
+    let user_id = Uuid::new_v4();
+    let jti = Uuid::new_v4();
+    let session_id = Uuid::new_v4();
+    let sub = user_id.simple().to_string();
+    let access = tokens.encode(
+        TokenKind::Access,
+        AccessTokenClaim {
+            sub: sub.clone(),
+            jti: jti.simple().to_string(),
+            sid: session_id.simple().to_string(),
+            access_token: true,
+        },
+    );
+    let refresh = tokens.encode(
+        TokenKind::Refresh,
+        RefreshTokenClaim {
+            sub: sub.clone(),
+            jti: jti.simple().to_string(),
+            refresh_token: true,
+        },
+    );
+    let confirm = tokens.encode(
+        TokenKind::Confirm,
+        ConfirmTokenClaim {
+            sub,
+            confirm_token: true,
+        },
+    );
+
+    assert_eq!(
+        decode_access_token(tokens, &access),
+        Some(AccessToken {
+            user_id,
+            jti,
+            session_id,
+        })
+    );
+    assert_eq!(validate_refresh_token(tokens, &refresh), Some((user_id, jti)));
+    assert_eq!(validate_confirm_token(tokens, &confirm), Some(user_id));
+    assert_eq!(decode_access_token(tokens, &refresh), None);
+    assert_eq!(decode_access_token(tokens, &confirm), None);
+    assert_eq!(validate_refresh_token(tokens, &access), None);
 }
 
-fn access_token_response(key: &[u8], user: &User) -> AccessTokenResponse {
+fn access_token_response(
+    tokens: &Tokens,
+    user: &User,
+    refresh_token: &RefreshToken,
+) -> AccessTokenResponse {
     AccessTokenResponse {
-        access_token: encode_token(
-            key,
+        access_token: tokens.encode(
+            TokenKind::Access,
             AccessTokenClaim {
                 sub: user.id.simple().to_string(),
+                jti: Uuid::new_v4().simple().to_string(),
+                sid: refresh_token.family_id.simple().to_string(),
                 access_token: true,
             },
         ),
-        refresh_token: encode_token(
-            key,
+        refresh_token: tokens.encode(
+            TokenKind::Refresh,
             RefreshTokenClaim {
                 sub: user.id.simple().to_string(),
+                jti: refresh_token.jti.simple().to_string(),
                 refresh_token: true,
             },
         ),
         token_type: "bearer".into(),
-        expires_in: 3600,
+        expires_in: tokens.lifetimes().access.num_seconds(),
     }
 }
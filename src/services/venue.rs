@@ -0,0 +1,271 @@
+//! This is the public API for managing venues
+use chrono::{DateTime, Utc};
+use models::page::{DateIdKey, Page};
+use models::venue::{NewVenue, Venue, VenueChanges};
+use models::venue::IOModel;
+use models::venue::pg::PgModel;
+use services::ServiceError;
+use services::user::Service as UserService;
+use uuid::Uuid;
+
+/// a latitude and longitude
+///
+/// It is formatted as a [schema:GeoCoordinates](https://schema.org/GeoCoordinates)
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct GeoCoordinates {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+/// represents the form that is needed to create a new venue
+///
+/// It is formatted as a [schema:Place](https://schema.org/Place)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CreateVenueRequest<'a> {
+    /// This is the OAuth 2.0 access token of the user that will own the venue
+    pub access_token: &'a str,
+    pub name: &'a str,
+    pub address: &'a str,
+    pub geo: Option<GeoCoordinates>,
+    pub capacity: Option<i32>,
+    pub accessibility_notes: &'a str,
+}
+
+/// used to look up a single venue
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GetVenueRequest {
+    pub venue_id: Uuid,
+}
+
+/// used to list the venues
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ListVenuesRequest {
+    pub page: Page<DateIdKey>,
+}
+
+/// used to change an existing venue
+///
+/// Only the fields that are `Some` are changed
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UpdateVenueRequest<'a> {
+    /// This is the OAuth 2.0 access token of the user that owns the venue
+    pub access_token: &'a str,
+    pub venue_id: Uuid,
+    pub name: Option<&'a str>,
+    pub address: Option<&'a str>,
+    /// `Some(None)` removes the location
+    pub geo: Option<Option<GeoCoordinates>>,
+    /// `Some(None)` removes the capacity
+    pub capacity: Option<Option<i32>>,
+    pub accessibility_notes: Option<&'a str>,
+}
+
+/// used to delete an existing venue
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DeleteVenueRequest<'a> {
+    /// This is the OAuth 2.0 access token of the user that owns the venue
+    pub access_token: &'a str,
+    pub venue_id: Uuid,
+}
+
+/// the data about a venue
+///
+/// It is formatted as a [schema:Place](https://schema.org/Place)
+#[derive(Serialize, Deserialize, Debug)]
+pub struct VenueResponse {
+    // https://schema.org/Thing
+    pub identifier: Uuid,
+    pub name: String,
+
+    // https://schema.org/Place
+    pub address: String,
+    pub geo: Option<GeoCoordinates>,
+    // https://schema.org/maximumAttendeeCapacity
+    pub capacity: Option<i32>,
+    pub accessibility_notes: String,
+    /// The identifier of the user that owns the venue
+    pub owner: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+/// a list of venues
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct VenueListResponse {
+    pub venues: Vec<VenueResponse>,
+    /// The key to ask for the next page with, `None` on the last page
+    #[serde(skip)]
+    pub next: Option<DateIdKey>,
+}
+
+/// the response from a delete venue request
+///
+/// This is currently an empty object but may be filled in later
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DeleteVenueResponse;
+
+/// The API for the venue service
+pub struct Service<'a> {
+    model: &'a PgModel<'a>,
+    users: &'a UserService<'a>,
+}
+
+impl<'a> Service<'a> {
+    /// create a new Service instance
+    pub fn new(model: &'a PgModel<'a>, users: &'a UserService<'a>) -> Service<'a> {
+        Service { model, users }
+    }
+
+    /// create a new venue owned by the current user
+    pub fn create(&self, request: &CreateVenueRequest) -> Result<VenueResponse, ServiceError> {
+        let owner_id = &self.users.current_user_id(request.access_token)?;
+        validate_venue(request.name, request.geo, request.capacity)?;
+
+        let venue = self.model.create(&NewVenue {
+            id: &Uuid::new_v4(),
+            owner_id,
+            name: request.name,
+            address: request.address,
+            latitude: request.geo.map(|x| x.latitude),
+            longitude: request.geo.map(|x| x.longitude),
+            capacity: request.capacity,
+            accessibility_notes: request.accessibility_notes,
+        })?;
+
+        Ok(VenueResponse::from(venue))
+    }
+
+    /// get a single venue
+    pub fn get(&self, request: &GetVenueRequest) -> Result<VenueResponse, ServiceError> {
+        let venue = self.model
+            .find(&request.venue_id)?
+            .ok_or(ServiceError::NotFound)?;
+
+        Ok(VenueResponse::from(venue))
+    }
+
+    /// list the venues
+    pub fn list(&self, request: &ListVenuesRequest) -> Result<VenueListResponse, ServiceError> {
+        let paged = self.model.list(&request.page)?.map(VenueResponse::from);
+
+        Ok(VenueListResponse {
+            venues: paged.rows,
+            next: paged.next,
+        })
+    }
+
+    /// update a venue owned by the current user
+    pub fn update(&self, request: &UpdateVenueRequest) -> Result<VenueResponse, ServiceError> {
+        let venue = self.owned_venue(request.access_token, &request.venue_id)?;
+        validate_venue(
+            request.name.unwrap_or(&venue.name),
+            request.geo.unwrap_or_else(|| venue_geo(&venue)),
+            request.capacity.unwrap_or(venue.capacity),
+        )?;
+
+        let changes = VenueChanges {
+            name: request.name,
+            address: request.address,
+            latitude: request.geo.map(|x| x.map(|x| x.latitude)),
+            longitude: request.geo.map(|x| x.map(|x| x.longitude)),
+            capacity: request.capacity,
+            accessibility_notes: request.accessibility_notes,
+        };
+        if changes.is_empty() {
+            return Ok(VenueResponse::from(venue));
+        }
+
+        let venue = self.model
+            .update(&request.venue_id, &changes)?
+            .ok_or(ServiceError::NotFound)?;
+
+        Ok(VenueResponse::from(venue))
+    }
+
+    /// delete a venue owned by the current user, its events are left without a venue
+    pub fn delete(&self, request: &DeleteVenueRequest) -> Result<DeleteVenueResponse, ServiceError> {
+        self.owned_venue(request.access_token, &request.venue_id)?;
+        self.model.delete(&request.venue_id)?;
+
+        Ok(DeleteVenueResponse)
+    }
+
+    /// find a venue and make sure that the current user owns it
+    fn owned_venue(&self, access_token: &str, venue_id: &Uuid) -> Result<Venue, ServiceError> {
+        let user_id = self.users.current_user_id(access_token)?;
+        let venue = self.model.find(venue_id)?.ok_or(ServiceError::NotFound)?;
+
+        if venue.owner_id == user_id {
+            Ok(venue)
+        } else {
+            Err(ServiceError::PermissionDenied)
+        }
+    }
+}
+
+// Internal
+
+impl From<Venue> for VenueResponse {
+    fn from(venue: Venue) -> Self {
+        VenueResponse {
+            identifier: venue.id,
+            geo: venue_geo(&venue),
+            name: venue.name,
+            address: venue.address,
+            capacity: venue.capacity,
+            accessibility_notes: venue.accessibility_notes,
+            owner: venue.owner_id,
+            created_at: venue.created_at,
+        }
+    }
+}
+
+fn venue_geo(venue: &Venue) -> Option<GeoCoordinates> {
+    match (venue.latitude, venue.longitude) {
+        (Some(latitude), Some(longitude)) => Some(GeoCoordinates {
+            latitude,
+            longitude,
+        }),
+        _ => None,
+    }
+}
+
+fn validate_venue(
+    name: &str,
+    geo: Option<GeoCoordinates>,
+    capacity: Option<i32>,
+) -> Result<(), ServiceError> {
+    let valid_geo = geo.is_none_or(|x| {
+        (-90.0..=90.0).contains(&x.latitude) && (-180.0..=180.0).contains(&x.longitude)
+    });
+    if !name.trim().is_empty() && valid_geo && capacity.unwrap_or(1) > 0 {
+        Ok(())
+    } else {
+        Err(ServiceError::InvalidVenue)
+    }
+}
+#[test]
+fn test_validate_venue() {
+    let geo = GeoCoordinates {
+        latitude: 51.5,
+        longitude: -0.12,
+    };
+
+    assert!(validate_venue("Town Hall", Some(geo), Some(100)).is_ok());
+    assert!(validate_venue("Town Hall", None, None).is_ok());
+    assert!(validate_venue(" ", None, None).is_err());
+    assert!(validate_venue("Town Hall", None, Some(0)).is_err());
+    assert!(
+        validate_venue(
+            "Town Hall",
+            Some(GeoCoordinates {
+                latitude: 95.0,
+                longitude: 0.0,
+            }),
+            None
+        ).is_err()
+    );
+}
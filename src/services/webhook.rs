@@ -0,0 +1,406 @@
+//! This is the public API for the webhook subscriptions and their delivery logs
+use chrono::{DateTime, Utc};
+use crypto::hmac::Hmac;
+use crypto::mac::Mac;
+use crypto::sha2::Sha256;
+use models::outbox::{MessageKind, Outgoing};
+use models::page::{DateIdKey, Page};
+use models::webhook::{Delivery, DeliveryStatus, NewWebhook, Webhook, WebhookTopic};
+use models::webhook::IOModel;
+use models::webhook::pg::PgModel;
+use notifier::webhook::{post_json_public, resolve_public};
+use serde::ser::Serialize;
+use serde_json;
+use services::ServiceError;
+use services::user::Service as UserService;
+use std::str::FromStr;
+use url::Url;
+use uuid::Uuid;
+
+/// used to subscribe a URL to some topics
+///
+/// An empty list of topics subscribes to all of them
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CreateWebhookRequest<'a> {
+    /// This is the OAuth 2.0 access token of an admin
+    pub access_token: &'a str,
+    /// The `https` URL the payloads are POSTed to
+    pub url: &'a str,
+    pub topics: Vec<WebhookTopic>,
+}
+
+/// used to look up a single subscription of the current user
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GetWebhookRequest<'a> {
+    /// This is the OAuth 2.0 access token of the admin that made the subscription
+    pub access_token: &'a str,
+    pub webhook_id: Uuid,
+}
+
+/// used to list the subscriptions of the current user
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ListWebhooksRequest<'a> {
+    /// This is the OAuth 2.0 access token of an admin
+    pub access_token: &'a str,
+    pub page: Page<DateIdKey>,
+}
+
+/// used to delete a subscription and its delivery log
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DeleteWebhookRequest<'a> {
+    /// This is the OAuth 2.0 access token of the admin that made the subscription
+    pub access_token: &'a str,
+    pub webhook_id: Uuid,
+}
+
+/// used to list the delivery log of a subscription
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DeliveriesRequest<'a> {
+    /// This is the OAuth 2.0 access token of the admin that made the subscription
+    pub access_token: &'a str,
+    pub webhook_id: Uuid,
+    pub page: Page<DateIdKey>,
+}
+
+/// used to send a delivery again
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RedeliverRequest<'a> {
+    /// This is the OAuth 2.0 access token of the admin that made the subscription
+    pub access_token: &'a str,
+    pub webhook_id: Uuid,
+    pub delivery_id: Uuid,
+}
+
+/// the data about a subscription
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WebhookResponse {
+    pub identifier: Uuid,
+    pub url: String,
+    /// The topics that are delivered, all of them when it is empty
+    pub topics: Vec<WebhookTopic>,
+    /// The key the payloads are signed with, it is only given out when the subscription is made
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// a list of subscriptions
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WebhookListResponse {
+    pub webhooks: Vec<WebhookResponse>,
+    /// The key to ask for the next page with, `None` on the last page
+    #[serde(skip)]
+    pub next: Option<DateIdKey>,
+}
+
+/// the response from a delete webhook request
+///
+/// This is currently an empty object but may be filled in later
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DeleteWebhookResponse;
+
+/// an entry of the delivery log
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DeliveryResponse {
+    pub identifier: Uuid,
+    pub webhook: Uuid,
+    pub topic: String,
+    /// The body that is POSTed
+    pub payload: serde_json::Value,
+    pub status: DeliveryStatus,
+    pub attempts: i32,
+    /// The HTTP status of the last attempt, `None` when the webhook could not be reached
+    pub response_status: Option<i32>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+/// the delivery log of a subscription, the oldest first
+///
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DeliveryListResponse {
+    pub deliveries: Vec<DeliveryResponse>,
+    /// The key to ask for the next page with, `None` on the last page
+    #[serde(skip)]
+    pub next: Option<DateIdKey>,
+}
+
+/// the body that is POSTed to the subscriptions
+///
+/// The `X-Webhook-Signature` header of the request is `sha256=` followed by the hex HMAC-SHA256
+/// of the body, keyed with the subscription's secret
+#[derive(Serialize, Deserialize, Debug)]
+pub struct WebhookPayload {
+    /// This is the same for every delivery of the payload, receivers can drop the duplicates
+    pub id: Uuid,
+    #[serde(rename = "type")]
+    pub topic: WebhookTopic,
+    pub created_at: DateTime<Utc>,
+    /// The resource the payload is about, it is shaped like the API's response for it
+    pub data: serde_json::Value,
+}
+
+/// The API for the webhook service
+///
+/// The payloads hold email addresses and private events, so only the `admins` may subscribe
+pub struct Service<'a> {
+    model: &'a PgModel<'a>,
+    users: &'a UserService<'a>,
+    admins: &'a [Uuid],
+}
+
+impl<'a> Service<'a> {
+    /// create a new Service instance
+    pub fn new(model: &'a PgModel<'a>, users: &'a UserService<'a>, admins: &'a [Uuid]) -> Self {
+        Service {
+            model,
+            users,
+            admins,
+        }
+    }
+
+    /// subscribe a URL to some topics, the response has the secret the payloads are signed with
+    pub fn create(&self, request: &CreateWebhookRequest) -> Result<WebhookResponse, ServiceError> {
+        let owner_id = &self.admin_id(request.access_token)?;
+        validate_url(request.url)?;
+
+        let mut topics = request
+            .topics
+            .iter()
+            .map(|x| x.as_str().to_string())
+            .collect::<Vec<_>>();
+        topics.sort();
+        topics.dedup();
+        let secret = new_secret();
+        let webhook = self.model.create(&NewWebhook {
+            id: &Uuid::new_v4(),
+            owner_id,
+            url: request.url,
+            secret: &secret,
+            topics: &topics,
+        })?;
+
+        Ok(WebhookResponse {
+            secret: Some(webhook.secret.clone()),
+            ..WebhookResponse::from(webhook)
+        })
+    }
+
+    /// get a single subscription of the current user
+    pub fn get(&self, request: &GetWebhookRequest) -> Result<WebhookResponse, ServiceError> {
+        let webhook = self.owned_webhook(request.access_token, &request.webhook_id)?;
+
+        Ok(WebhookResponse::from(webhook))
+    }
+
+    /// list the subscriptions of the current user
+    pub fn list(&self, request: &ListWebhooksRequest) -> Result<WebhookListResponse, ServiceError> {
+        let owner_id = &self.admin_id(request.access_token)?;
+        let paged = self.model
+            .list(owner_id, &request.page)?
+            .map(WebhookResponse::from);
+
+        Ok(WebhookListResponse {
+            webhooks: paged.rows,
+            next: paged.next,
+        })
+    }
+
+    /// delete a subscription of the current user along with its delivery log
+    pub fn delete(
+        &self,
+        request: &DeleteWebhookRequest,
+    ) -> Result<DeleteWebhookResponse, ServiceError> {
+        let webhook = self.owned_webhook(request.access_token, &request.webhook_id)?;
+        self.model.delete(&webhook.id)?;
+
+        Ok(DeleteWebhookResponse)
+    }
+
+    /// list the delivery log of a subscription of the current user
+    pub fn deliveries(
+        &self,
+        request: &DeliveriesRequest,
+    ) -> Result<DeliveryListResponse, ServiceError> {
+        let webhook = self.owned_webhook(request.access_token, &request.webhook_id)?;
+        let paged = self.model
+            .deliveries(&webhook.id, &request.page)?
+            .map(DeliveryResponse::from);
+
+        Ok(DeliveryListResponse {
+            deliveries: paged.rows,
+            next: paged.next,
+        })
+    }
+
+    /// send a delivery again, even one that was delivered or has run out of retries
+    pub fn redeliver(&self, request: &RedeliverRequest) -> Result<DeliveryResponse, ServiceError> {
+        let webhook = self.owned_webhook(request.access_token, &request.webhook_id)?;
+        let delivery = self.model
+            .redeliver(&webhook.id, &request.delivery_id)?
+            .ok_or(ServiceError::NotFound)?;
+
+        Ok(DeliveryResponse::from(delivery))
+    }
+
+    // Internal
+
+    /// the id of the current user, as long as they are an admin
+    fn admin_id(&self, access_token: &str) -> Result<Uuid, ServiceError> {
+        let user_id = self.users.current_user_id(access_token)?;
+        if self.admins.contains(&user_id) {
+            Ok(user_id)
+        } else {
+            Err(ServiceError::PermissionDenied)
+        }
+    }
+
+    /// find a subscription and make sure that the current user made it
+    fn owned_webhook(&self, access_token: &str, webhook_id: &Uuid) -> Result<Webhook, ServiceError> {
+        let user_id = self.admin_id(access_token)?;
+
+        match self.model.find(webhook_id)? {
+            Some(ref x) if x.owner_id != user_id => Err(ServiceError::NotFound),
+            Some(x) => Ok(x),
+            None => Err(ServiceError::NotFound),
+        }
+    }
+}
+
+impl From<Webhook> for WebhookResponse {
+    fn from(webhook: Webhook) -> Self {
+        WebhookResponse {
+            identifier: webhook.id,
+            url: webhook.url,
+            topics: webhook
+                .topics
+                .iter()
+                .filter_map(|x| WebhookTopic::from_str(x).ok())
+                .collect(),
+            secret: None,
+            created_at: webhook.created_at,
+        }
+    }
+}
+
+impl From<Delivery> for DeliveryResponse {
+    fn from(delivery: Delivery) -> Self {
+        DeliveryResponse {
+            identifier: delivery.id,
+            webhook: delivery.webhook_id,
+            status: delivery.delivery_status(),
+            payload: serde_json::from_str(&delivery.payload).unwrap_or(serde_json::Value::Null),
+            topic: delivery.topic,
+            attempts: delivery.attempts,
+            response_status: delivery.response_status,
+            last_error: delivery.last_error,
+            created_at: delivery.created_at,
+            delivered_at: delivery.delivered_at,
+        }
+    }
+}
+
+/// the outbox message that sends `data` to the subscribers of `topic`
+///
+/// The other services give it to their models so it is stored along with the change
+pub(crate) fn publish<T: Serialize>(topic: WebhookTopic, data: &T) -> Outgoing {
+    let payload = WebhookPayload {
+        id: Uuid::new_v4(),
+        topic,
+        created_at: Utc::now(),
+        data: serde_json::to_value(data).expect("the responses are always valid JSON"),
+    };
+
+    Outgoing {
+        kind: MessageKind::Webhook,
+        payload: serde_json::to_string(&payload).expect("a payload is always valid JSON"),
+    }
+}
+
+/// POST a delivery to its subscription and log how it went
+///
+/// This is called by the outbox worker, an error makes it retry the delivery later
+pub(crate) fn deliver(model: &PgModel, delivery_id: &Uuid) -> Result<(), String> {
+    let (delivery, webhook) = match model.delivery(delivery_id).map_err(|x| x.to_string())? {
+        Some(x) => x,
+        // The subscription was deleted along with its deliveries
+        None => return Ok(()),
+    };
+
+    let id = delivery.id.hyphenated().to_string();
+    let signature = format!("sha256={}", sign(&webhook.secret, &delivery.payload));
+    let headers = [
+        ("X-Webhook-Id", id.as_str()),
+        ("X-Webhook-Topic", delivery.topic.as_str()),
+        ("X-Webhook-Signature", signature.as_str()),
+    ];
+    let (status, error) = match post_json_public(&webhook.url, &delivery.payload, &headers) {
+        Ok(x @ 200..=299) => (Some(i32::from(x)), None),
+        Ok(x) => (Some(i32::from(x)), Some(format!("HTTP {}", x))),
+        Err(e) => (None, Some(e.0)),
+    };
+
+    model
+        .record_attempt(&delivery.id, status, error.as_deref())
+        .map_err(|x| x.to_string())?;
+    match error {
+        Some(x) => Err(x),
+        None => Ok(()),
+    }
+}
+
+// Internal
+
+/// a random key for signing the payloads of a subscription
+fn new_secret() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+/// the hex HMAC-SHA256 of a body
+fn sign(secret: &str, body: &str) -> String {
+    let mut hmac = Hmac::new(Sha256::new(), secret.as_bytes());
+    hmac.input(body.as_bytes());
+    hmac.result()
+        .code()
+        .iter()
+        .map(|x| format!("{:02x}", x))
+        .collect()
+}
+#[test]
+fn test_sign() {
+    assert_eq!(
+        sign("key", "The quick brown fox jumps over the lazy dog"),
+        "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+    );
+}
+
+/// only `https` URLs on public addresses can be delivered to, a subscription must not reach
+/// the services on the private network of the server
+fn validate_url(url: &str) -> Result<(), ServiceError> {
+    let url = Url::parse(url).map_err(|_| ServiceError::InvalidWebhook)?;
+    match (url.scheme(), url.host_str(), url.port_or_known_default()) {
+        ("https", Some(host), Some(port)) => resolve_public(&format!("{}:{}", host, port))
+            .map(|_| ())
+            .map_err(|_| ServiceError::InvalidWebhook),
+        _ => Err(ServiceError::InvalidWebhook),
+    }
+}
+#[test]
+fn test_validate_url() {
+    assert!(validate_url("https://93.184.216.34/events").is_ok());
+    assert!(validate_url("http://93.184.216.34/events").is_err());
+    assert!(validate_url("https://localhost/events").is_err());
+    assert!(validate_url("https://127.0.0.1:8080/events").is_err());
+    assert!(validate_url("https://169.254.169.254/latest").is_err());
+    assert!(validate_url("https://[fd00::1]/events").is_err());
+    assert!(validate_url("hooks.example.com").is_err());
+}
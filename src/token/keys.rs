@@ -0,0 +1,319 @@
+//! The keys that the tokens are signed and checked with
+//!
+//! RSA and EC P-256 keys are read from PEM files, either PKCS#1 `RSA PRIVATE KEY`, PKCS#8
+//! `PRIVATE KEY` or a `PUBLIC KEY`.  jsonwebtoken signs and checks the tokens, the public half of
+//! each key is kept to publish it.  Only PKCS#8 EC keys are read, a SEC1 `EC PRIVATE KEY` is
+//! converted with `openssl pkcs8 -topk8 -nocrypt`.
+use base64;
+use jsonwebtoken::{DecodingKey, EncodingKey};
+use jsonwebtoken::errors::Error as JwtError;
+use pem;
+use ring::digest;
+use ring::rand::SystemRandom;
+use ring::rsa::PublicKeyComponents;
+use ring::signature::{EcdsaKeyPair, KeyPair, RsaKeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+use simple_asn1::{self, ASN1Block};
+use std::fmt;
+
+pub use jsonwebtoken::Algorithm;
+
+/// returned when a key can't be read or used
+#[derive(Debug, Fail, PartialEq)]
+pub struct InvalidKey(pub String);
+
+impl fmt::Display for InvalidKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid key: {}", self.0)
+    }
+}
+
+/// the public half of a key, as it is published
+#[derive(Clone)]
+enum Public {
+    Hmac,
+    /// The big endian modulus and exponent
+    Rsa { n: Vec<u8>, e: Vec<u8> },
+    /// The coordinates of a P-256 point
+    Ec { x: Vec<u8>, y: Vec<u8> },
+}
+
+/// a key that checks tokens, and signs them when it is an HMAC secret or a private key
+#[derive(Clone)]
+pub struct Key {
+    /// The `kid` header of the tokens it signs, this is the RFC 7638 thumbprint of a public key
+    pub kid: String,
+    pub alg: Algorithm,
+    /// Signs the tokens, `None` for a public key
+    pub(crate) encoding: Option<EncodingKey>,
+    pub(crate) decoding: DecodingKey,
+    public: Public,
+}
+
+/// a public key in the form of an RFC 7517 JSON Web Key
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Jwk {
+    pub kty: String,
+    #[serde(rename = "use")]
+    pub use_: String,
+    pub alg: Algorithm,
+    pub kid: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub e: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crv: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub y: Option<String>,
+}
+
+impl Key {
+    /// a shared secret for HS256
+    ///
+    /// Its `kid` is fixed, a thumbprint would be a hash of the secret
+    pub fn hmac(secret: &[u8]) -> Key {
+        Key {
+            kid: "hs256".to_string(),
+            alg: Algorithm::HS256,
+            encoding: Some(EncodingKey::from_secret(secret)),
+            decoding: DecodingKey::from_secret(secret),
+            public: Public::Hmac,
+        }
+    }
+
+    /// read an RSA or EC key from the text of a PEM file
+    pub fn from_pem(text: &str) -> Result<Key, InvalidKey> {
+        let parsed = pem::parse(text).map_err(|e| invalid(&e.to_string()))?;
+        let der = parsed.contents();
+        match parsed.tag() {
+            "RSA PRIVATE KEY" => match RsaKeyPair::from_der(der) {
+                Ok(key_pair) => rsa_private_key(text, &key_pair),
+                Err(e) => Err(invalid(&e.to_string())),
+            },
+            "PRIVATE KEY" => match RsaKeyPair::from_pkcs8(der) {
+                Ok(key_pair) => rsa_private_key(text, &key_pair),
+                Err(_) => ec_private_key(text, der),
+            },
+            "RSA PUBLIC KEY" => rsa_public_key(der),
+            "PUBLIC KEY" => public_key_info(der),
+            "EC PRIVATE KEY" => Err(invalid("SEC1 EC keys are not supported, use PKCS#8")),
+            x => Err(invalid(&format!("unsupported PEM type {}", x))),
+        }
+    }
+
+    /// true when the key can sign tokens as well as check them
+    pub fn can_sign(&self) -> bool {
+        self.encoding.is_some()
+    }
+
+    /// the public key as a JWK, `None` for an HMAC secret
+    pub fn jwk(&self) -> Option<Jwk> {
+        let jwk = |kty: &str| Jwk {
+            kty: kty.to_string(),
+            use_: "sig".to_string(),
+            alg: self.alg,
+            kid: self.kid.clone(),
+            n: None,
+            e: None,
+            crv: None,
+            x: None,
+            y: None,
+        };
+
+        match self.public {
+            Public::Hmac => None,
+            Public::Rsa { ref n, ref e } => Some(Jwk {
+                n: Some(base64url(n)),
+                e: Some(base64url(e)),
+                ..jwk("RSA")
+            }),
+            Public::Ec { ref x, ref y } => Some(Jwk {
+                crv: Some("P-256".to_string()),
+                x: Some(base64url(x)),
+                y: Some(base64url(y)),
+                ..jwk("EC")
+            }),
+        }
+    }
+}
+
+// Internal
+
+fn invalid(reason: &str) -> InvalidKey {
+    InvalidKey(reason.to_string())
+}
+
+fn jwt_invalid(e: JwtError) -> InvalidKey {
+    invalid(&e.to_string())
+}
+
+fn base64url(bytes: &[u8]) -> String {
+    base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+}
+
+/// the RFC 7638 thumbprint of a public key, the members are in lexical order
+fn thumbprint(members: &[(&str, &str)]) -> String {
+    let json = members
+        .iter()
+        .map(|&(k, v)| format!("\"{}\":\"{}\"", k, v))
+        .collect::<Vec<_>>()
+        .join(",");
+
+    base64url(digest::digest(&digest::SHA256, format!("{{{}}}", json).as_bytes()).as_ref())
+}
+
+/// an RSA private key, ring gives the public half of it
+fn rsa_private_key(text: &str, key_pair: &RsaKeyPair) -> Result<Key, InvalidKey> {
+    let encoding = EncodingKey::from_rsa_pem(text.as_bytes()).map_err(jwt_invalid)?;
+    let public = PublicKeyComponents::<Vec<u8>>::from(key_pair.public());
+
+    rsa_key(Some(encoding), public.n, public.e)
+}
+
+/// a P-256 private key from a PKCS#8 `PrivateKeyInfo`, ring gives the public half of it
+fn ec_private_key(text: &str, der: &[u8]) -> Result<Key, InvalidKey> {
+    let rng = SystemRandom::new();
+    let key_pair = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, der, &rng)
+        .map_err(|_| invalid("only RSA and P-256 EC keys are supported"))?;
+    let encoding = EncodingKey::from_ec_pem(text.as_bytes()).map_err(jwt_invalid)?;
+
+    ec_key(Some(encoding), key_pair.public_key().as_ref())
+}
+
+fn rsa_key(encoding: Option<EncodingKey>, n: Vec<u8>, e: Vec<u8>) -> Result<Key, InvalidKey> {
+    let (n64, e64) = (base64url(&n), base64url(&e));
+
+    Ok(Key {
+        kid: thumbprint(&[("e", &e64), ("kty", "RSA"), ("n", &n64)]),
+        alg: Algorithm::RS256,
+        encoding,
+        decoding: DecodingKey::from_rsa_components(&n64, &e64).map_err(jwt_invalid)?,
+        public: Public::Rsa { n, e },
+    })
+}
+
+/// a P-256 key from its uncompressed point
+fn ec_key(encoding: Option<EncodingKey>, point: &[u8]) -> Result<Key, InvalidKey> {
+    if point.len() != 65 || point[0] != 0x04 {
+        return Err(invalid("only uncompressed P-256 EC keys are supported"));
+    }
+    let (x, y) = (point[1..33].to_vec(), point[33..].to_vec());
+    let (x64, y64) = (base64url(&x), base64url(&y));
+
+    Ok(Key {
+        kid: thumbprint(&[("crv", "P-256"), ("kty", "EC"), ("x", &x64), ("y", &y64)]),
+        alg: Algorithm::ES256,
+        encoding,
+        decoding: DecodingKey::from_ec_components(&x64, &y64).map_err(jwt_invalid)?,
+        public: Public::Ec { x, y },
+    })
+}
+
+// ring doesn't read public keys, so their components are read with simple_asn1
+
+fn asn1(der: &[u8]) -> Result<Vec<ASN1Block>, InvalidKey> {
+    simple_asn1::from_der(der).map_err(|e| invalid(&e.to_string()))
+}
+
+fn malformed() -> InvalidKey {
+    invalid("malformed public key")
+}
+
+/// a PKCS#1 `RSAPublicKey`
+fn rsa_public_key(der: &[u8]) -> Result<Key, InvalidKey> {
+    match asn1(der)?.as_slice() {
+        [ASN1Block::Sequence(_, fields)] => match fields.as_slice() {
+            [ASN1Block::Integer(_, n), ASN1Block::Integer(_, e)] => {
+                rsa_key(None, n.to_bytes_be().1, e.to_bytes_be().1)
+            }
+            _ => Err(malformed()),
+        },
+        _ => Err(malformed()),
+    }
+}
+
+/// an X.509 `SubjectPublicKeyInfo`
+fn public_key_info(der: &[u8]) -> Result<Key, InvalidKey> {
+    let rsa_encryption = simple_asn1::oid!(1, 2, 840, 113_549, 1, 1, 1);
+    let ec_public_key = simple_asn1::oid!(1, 2, 840, 10_045, 2, 1);
+    let p256 = simple_asn1::oid!(1, 2, 840, 10_045, 3, 1, 7);
+
+    let (identifier, key) = match asn1(der)?.as_slice() {
+        [ASN1Block::Sequence(_, fields)] => match fields.as_slice() {
+            [ASN1Block::Sequence(_, identifier), ASN1Block::BitString(_, _, key)] => {
+                (identifier.clone(), key.clone())
+            }
+            _ => return Err(malformed()),
+        },
+        _ => return Err(malformed()),
+    };
+
+    match identifier.as_slice() {
+        [ASN1Block::ObjectIdentifier(_, algorithm), ..] if *algorithm == rsa_encryption => {
+            rsa_public_key(&key)
+        }
+        [ASN1Block::ObjectIdentifier(_, algorithm), ASN1Block::ObjectIdentifier(_, curve)]
+            if *algorithm == ec_public_key && *curve == p256 =>
+        {
+            ec_key(None, &key)
+        }
+        _ => Err(invalid("only RSA and P-256 EC keys are supported")),
+    }
+}
+#[test]
+fn test_from_pem() {
+    use jsonwebtoken as jwt;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Test {
+        test: bool,
+    }
+    let check = |signing: &Key, checking: &Key| {
+        let token = jwt::encode(
+            &jwt::Header::new(signing.alg),
+            &Test { test: true },
+            signing.encoding.as_ref().unwrap(),
+        ).unwrap();
+        let mut validation = jwt::Validation::new(checking.alg);
+        validation.validate_exp = false;
+        validation.required_spec_claims.clear();
+        jwt::decode::<Test>(&token, &checking.decoding, &validation).is_ok()
+    };
+
+    let rsa = Key::from_pem(include_str!("testdata/rsa.pem")).unwrap();
+    let rsa_public = Key::from_pem(include_str!("testdata/rsa_public.pem")).unwrap();
+    assert_eq!(rsa.alg, Algorithm::RS256);
+    assert_eq!(rsa.kid, rsa_public.kid);
+    assert_eq!(rsa.jwk(), rsa_public.jwk());
+    assert!(rsa.can_sign());
+    assert!(!rsa_public.can_sign());
+    assert!(check(&rsa, &rsa_public));
+
+    let ec = Key::from_pem(include_str!("testdata/ec.pem")).unwrap();
+    assert_eq!(ec.alg, Algorithm::ES256);
+    assert!(ec.can_sign());
+    assert_eq!(ec.jwk().unwrap().crv, Some("P-256".to_string()));
+    assert!(check(&ec, &ec));
+    assert!(!check(&ec, &Key::hmac(b"key")));
+
+    let pem = |tag: &str| format!("-----BEGIN {}-----\nAA==\n-----END {}-----", tag, tag);
+    assert!(Key::from_pem(&pem("EC PRIVATE KEY")).is_err());
+    assert!(Key::from_pem(&pem("CERTIFICATE")).is_err());
+    assert!(Key::from_pem(&pem("PUBLIC KEY")).is_err());
+    assert!(Key::from_pem("not a key").is_err());
+}
+
+#[test]
+fn test_thumbprint() {
+    // The example from RFC 7638 section 3.1
+    let n = "0vx7agoebGcQSuuPiLJXZptN9nndrQmbXEps2aiAFbWhM78LhWx4cbbfAAtVT86zwu1RK7aPFFxuhDR1L6tSoc_\
+             BJECPebWKRXjBZCiFV4n3oknjhMstn64tZ_2W-5JsGY4Hc5n9yBXArwl93lqt7_RN5w6Cf0h4QyQ5v-65YGjQR0_\
+             FDW2QvzqY368QQMicAtaSqzs8KJZgnYb9c7d0zgdAZHzu6qMQvRL5hajrn1n91CbOpbISD08qNLyrdkt-bFTWhAI4\
+             vMQFh6WeZu0fM4lFd2NcRwr3XPksINHaQ-G_xBniIqbw0Ls1jF44-csFCur-kEgU8awapJzKnqDKgw";
+    assert_eq!(
+        thumbprint(&[("e", "AQAB"), ("kty", "RSA"), ("n", n)]),
+        "NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xs"
+    );
+}
@@ -0,0 +1,309 @@
+//! Signing and checking the JWTs that the services hand out
+//!
+//! Every token carries the registered `iss`, `aud`, `iat`, `nbf` and `exp` claims next to its own
+//! private claims, a token that is missing any of them is rejected.  Feed tokens are the
+//! exception, they have no `exp` and last until their feed is deleted.
+//!
+//! The tokens are signed with an HMAC secret, an RSA key or an EC key and carry the `kid` of the
+//! key in their header.  Keys that have been rotated out are kept to check the tokens they
+//! signed, and the public keys are published as a JWKS so other services can check the tokens
+//! too.
+pub mod keys;
+
+use self::keys::{Algorithm, InvalidKey, Jwk, Key};
+use chrono::{Duration, Utc};
+use jsonwebtoken as jwt;
+use serde::de::DeserializeOwned;
+use serde::ser::Serialize;
+
+/// How far the clocks of the servers may drift apart, in seconds
+pub const LEEWAY_SECS: i64 = 60;
+
+/// The kinds of tokens, each of them lasts for its own length of time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenKind {
+    Access,
+    Refresh,
+    /// The token that is emailed to a new user
+    Confirm,
+    /// The ticket that is shown at the door of an event
+    Ticket,
+    /// The token in a calendar subscription URL, it never expires and is revoked by deleting its
+    /// feed instead
+    Feed,
+}
+
+/// how long the tokens of each kind are valid for
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lifetimes {
+    pub access: Duration,
+    pub refresh: Duration,
+    pub confirm: Duration,
+    pub ticket: Duration,
+}
+
+impl Lifetimes {
+    /// the lifetime of a kind of token, `None` for the feed tokens that don't expire
+    pub fn of(&self, kind: TokenKind) -> Option<Duration> {
+        match kind {
+            TokenKind::Access => Some(self.access),
+            TokenKind::Refresh => Some(self.refresh),
+            TokenKind::Confirm => Some(self.confirm),
+            TokenKind::Ticket => Some(self.ticket),
+            TokenKind::Feed => None,
+        }
+    }
+}
+
+impl Default for Lifetimes {
+    fn default() -> Self {
+        Lifetimes {
+            access: Duration::hours(1),
+            refresh: Duration::days(30),
+            confirm: Duration::days(2),
+            // This is handed to people who can't refresh it
+            ticket: Duration::days(365),
+        }
+    }
+}
+
+/// the registered claims that every token carries
+///
+/// See: [RFC-7519 Section 4.1](https://tools.ietf.org/html/rfc7519#section-4.1)
+#[derive(Debug, Serialize, Deserialize)]
+struct RegisteredClaims {
+    iss: String,
+    aud: String,
+    iat: i64,
+    nbf: i64,
+    /// Left out of the tokens that don't expire
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exp: Option<i64>,
+}
+
+/// the claims of a token, the registered ones are required when decoding
+#[derive(Debug, Serialize, Deserialize)]
+struct Claims<T> {
+    #[serde(flatten)]
+    registered: RegisteredClaims,
+    #[serde(flatten)]
+    private: T,
+}
+
+/// the public keys that check the tokens, served at `/.well-known/jwks.json`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Jwks {
+    pub keys: Vec<Jwk>,
+}
+
+/// signs and checks the tokens
+pub struct Tokens {
+    /// The key that signs new tokens
+    signing: Key,
+    /// The keys that check tokens, the signing key comes first
+    keys: Vec<Key>,
+    /// The `iss` of the tokens, this is the URL the API is served at
+    issuer: String,
+    /// The `aud` of the tokens
+    audience: String,
+    lifetimes: Lifetimes,
+}
+
+impl Tokens {
+    /// tokens that are signed and checked with a shared HMAC secret
+    pub fn new(key: &[u8], issuer: &str, audience: &str, lifetimes: Lifetimes) -> Self {
+        Tokens {
+            signing: Key::hmac(key),
+            keys: vec![Key::hmac(key)],
+            issuer: issuer.to_string(),
+            audience: audience.to_string(),
+            lifetimes,
+        }
+    }
+
+    /// tokens that are signed with `signing` and checked with it or any of the `old` keys
+    pub fn with_keys(
+        signing: Key,
+        old: Vec<Key>,
+        issuer: &str,
+        audience: &str,
+        lifetimes: Lifetimes,
+    ) -> Result<Self, InvalidKey> {
+        if !signing.can_sign() {
+            return Err(InvalidKey(format!(
+                "the key {} can't sign tokens",
+                signing.kid
+            )));
+        }
+        let mut keys = vec![signing.clone()];
+        keys.extend(old.into_iter().filter(|x| x.kid != signing.kid));
+
+        Ok(Tokens {
+            signing,
+            keys,
+            issuer: issuer.to_string(),
+            audience: audience.to_string(),
+            lifetimes,
+        })
+    }
+
+    /// how long the tokens of each kind are valid for
+    pub fn lifetimes(&self) -> &Lifetimes {
+        &self.lifetimes
+    }
+
+    /// the public keys, an HMAC secret is never published
+    pub fn jwks(&self) -> Jwks {
+        Jwks {
+            keys: self.keys.iter().filter_map(Key::jwk).collect(),
+        }
+    }
+
+    /// sign the private claims of a token, the registered claims are added
+    pub fn encode<T: Serialize>(&self, kind: TokenKind, claims: T) -> String {
+        let now = Utc::now().timestamp();
+        let claims = Claims {
+            registered: RegisteredClaims {
+                iss: self.issuer.clone(),
+                aud: self.audience.clone(),
+                iat: now,
+                nbf: now,
+                exp: self.lifetimes.of(kind).map(|x| now + x.num_seconds()),
+            },
+            private: claims,
+        };
+        let header = jwt::Header {
+            kid: Some(self.signing.kid.clone()),
+            ..jwt::Header::new(self.signing.alg)
+        };
+
+        // TODO: handle error correctly
+        match self.signing.encoding {
+            Some(ref key) => jwt::encode(&header, &claims, key).unwrap_or_else(|_| "".into()),
+            None => "".into(),
+        }
+    }
+
+    /// check a token of a kind and get its private claims, `None` when it is invalid or expired
+    pub fn decode<T: DeserializeOwned>(&self, kind: TokenKind, token: &str) -> Option<T> {
+        let header = jwt::decode_header(token).ok()?;
+        // The tokens from before there were key ids were all signed with the HMAC secret
+        let key = self.keys.iter().find(|x| match header.kid {
+            Some(ref kid) => x.kid == *kid,
+            None => x.alg == Algorithm::HS256,
+        })?;
+
+        // The key decides the algorithm, so a public key is never used as an HMAC secret
+        let mut validation = jwt::Validation::new(key.alg);
+        validation.leeway = LEEWAY_SECS as u64;
+        validation.validate_nbf = true;
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_audience(&[&self.audience]);
+        if self.lifetimes.of(kind).is_some() {
+            validation.set_required_spec_claims(&["exp", "nbf", "iat", "iss", "aud"]);
+        } else {
+            // The feed tokens from before they stopped expiring still have an `exp`
+            validation.validate_exp = false;
+            validation.set_required_spec_claims(&["nbf", "iat", "iss", "aud"]);
+        }
+
+        jwt::decode::<Claims<T>>(token, &key.decoding, &validation)
+            .ok()
+            .map(|x| x.claims.private)
+    }
+}
+#[test]
+fn test_tokens() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Test {
+        test: bool,
+    }
+    let lifetimes = |x| Lifetimes {
+        access: x,
+        ..Lifetimes::default()
+    };
+    let tokens = Tokens::new(
+        b"key",
+        "http://api",
+        "http://api",
+        lifetimes(Duration::hours(1)),
+    );
+    let token = tokens.encode(TokenKind::Access, Test { test: true });
+    assert_eq!(tokens.decode(TokenKind::Access, &token), Some(Test { test: true }));
+
+    // Feed tokens have no `exp`, which no other kind of token may leave out
+    let token = tokens.encode(TokenKind::Feed, Test { test: true });
+    assert_eq!(tokens.decode(TokenKind::Feed, &token), Some(Test { test: true }));
+    assert_eq!(tokens.decode::<Test>(TokenKind::Access, &token), None);
+
+    // Expired, but within the leeway
+    let skewed = Tokens::new(
+        b"key",
+        "http://api",
+        "http://api",
+        lifetimes(Duration::seconds(-30)),
+    );
+    let token = skewed.encode(TokenKind::Access, Test { test: true });
+    assert_eq!(tokens.decode(TokenKind::Access, &token), Some(Test { test: true }));
+
+    let expired = Tokens::new(
+        b"key",
+        "http://api",
+        "http://api",
+        lifetimes(Duration::hours(-1)),
+    );
+    let token = expired.encode(TokenKind::Access, Test { test: true });
+    assert_eq!(tokens.decode::<Test>(TokenKind::Access, &token), None);
+    // The feed tokens from before they stopped expiring are still accepted
+    assert_eq!(tokens.decode(TokenKind::Feed, &token), Some(Test { test: true }));
+
+    let other = Tokens::new(b"key", "http://other", "http://api", Lifetimes::default());
+    let token = other.encode(TokenKind::Access, Test { test: true });
+    assert_eq!(tokens.decode::<Test>(TokenKind::Access, &token), None);
+
+    let other = Tokens::new(b"key", "http://api", "http://other", Lifetimes::default());
+    let token = other.encode(TokenKind::Access, Test { test: true });
+    assert_eq!(tokens.decode::<Test>(TokenKind::Access, &token), None);
+
+    // A token without the registered claims never expires, so it is refused
+    let token = jwt::encode(
+        &jwt::Header::default(),
+        &Test { test: true },
+        &jwt::EncodingKey::from_secret(b"key"),
+    ).unwrap();
+    assert_eq!(tokens.decode::<Test>(TokenKind::Access, &token), None);
+
+    // The tokens of a key that was rotated out are still accepted, and its public key is still
+    // published
+    let old = Key::from_pem(include_str!("testdata/rsa.pem")).unwrap();
+    let rotated = Tokens::with_keys(
+        old.clone(),
+        vec![],
+        "http://api",
+        "http://api",
+        lifetimes(Duration::hours(1)),
+    )
+    .unwrap();
+    let token = rotated.encode(TokenKind::Access, Test { test: true });
+    let public = Key::from_pem(include_str!("testdata/rsa_public.pem")).unwrap();
+    let new = Tokens::new(b"key", "http://api", "http://api", Lifetimes::default());
+    let new = Tokens {
+        keys: vec![new.signing.clone(), public.clone()],
+        ..new
+    };
+    assert_eq!(new.decode(TokenKind::Access, &token), Some(Test { test: true }));
+    assert_eq!(tokens.decode::<Test>(TokenKind::Access, &token), None);
+    assert_eq!(new.jwks().keys, vec![old.jwk().unwrap()]);
+
+    // An EC key signs ES256 tokens, a public key only checks them
+    let ec = Key::from_pem(include_str!("testdata/ec.pem")).unwrap();
+    let tokens = Tokens::with_keys(ec, vec![], "http://api", "http://api", Lifetimes::default())
+        .unwrap();
+    let token = tokens.encode(TokenKind::Access, Test { test: true });
+    assert_eq!(jwt::decode_header(&token).unwrap().alg, Algorithm::ES256);
+    assert_eq!(tokens.decode(TokenKind::Access, &token), Some(Test { test: true }));
+    assert!(
+        Tokens::with_keys(public, vec![], "http://api", "http://api", Lifetimes::default())
+            .is_err()
+    );
+}
@@ -1,30 +1,158 @@
 //! This is the initial MVP of the events service to get the BDD tests to work
-use db;
+use base64;
+use chrono::{DateTime, Duration, Utc};
+use config::Config;
+use db::Pool;
+use models::attendance::RsvpStatus;
+use models::attendance::pg::PgModel as AttendanceModel;
+use models::calendar_feed::pg::PgModel as CalendarFeedModel;
+use models::comment::pg::PgModel as CommentModel;
+use models::event::{TagMatch, Visibility};
+use models::event::pg::PgModel as EventModel;
+use models::event_member::Role;
+use models::event_member::pg::PgModel as MemberModel;
+use models::group::GroupRole;
+use models::group::pg::PgModel as GroupModel;
+use models::invitation::InvitationStatus;
+use models::invitation::pg::PgModel as InvitationModel;
+use models::page::Page;
+use models::refresh_token::pg::PgModel as RefreshTokenModel;
+use models::revoked_token::pg::PgModel as RevokedTokenModel;
 use models::user::IOModel;
 use models::user::pg::PgModel as UserModel;
+use models::venue::pg::PgModel as VenueModel;
+use models::webhook::WebhookTopic;
+use models::webhook::pg::PgModel as WebhookModel;
+use notifier;
+use notifier::Notifier;
 use rouille;
 use rouille::input::post;
 use rouille::{Request, Response};
+use scheduler;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json;
+use services::attendance;
+use services::attendance::Service as AttendanceService;
+use services::calendar;
+use services::calendar::Service as CalendarService;
+use services::comment;
+use services::comment::Service as CommentService;
+use services::event;
+use services::event::Service as EventService;
+use services::group;
+use services::group::Service as GroupService;
+use services::invitation;
+use services::invitation::Service as InvitationService;
+use services::member;
+use services::member::Service as MemberService;
 use services::user;
 use services::user::Service as UserService;
+use services::venue;
+use services::venue::Service as VenueService;
+use services::webhook;
+use services::webhook::Service as WebhookService;
+use services::ServiceError;
 use std::collections::HashMap;
 use std::error::Error;
 use std::fmt;
 use std::io;
+use std::io::Read;
 use std::iter::FromIterator;
 use std::str::FromStr;
+use std::sync::Arc;
+use token::Tokens;
+use url::form_urlencoded;
 use uuid::Uuid;
 
 //
 // Runs a web server that passes the BDD tests
 //
-pub fn run() {
-    eprintln!("Listening on 0.0.0.0:8080");
-    rouille::start_server("0.0.0.0:8080", |request| {
+pub fn run(config: Config) {
+    let notifier: Arc<dyn Notifier> = Arc::from(notifier::from_config(&config.notifier));
+    let (signing_key, old_keys) = config.token_keys();
+    let public_url = config.public_url;
+    let tokens = Tokens::with_keys(
+        signing_key,
+        old_keys,
+        &public_url,
+        &public_url,
+        config.tokens.lifetimes(),
+    ).expect("the signing key is checked when the config is loaded");
+    // The users that may subscribe webhooks, the payloads hold everyone's email addresses
+    let webhook_admins = config.webhook_admins;
+    let pool = Pool::new(&config.database.url, config.database.pool_size);
+    scheduler::start(&config.database.url, notifier.clone());
+    eprintln!("Listening on {}", config.bind_address);
+    rouille::start_server(config.bind_address, move |request| {
         rouille::log(request, io::stderr(), || {
-            let conn = &db::connection();
+            // The router can't match the dots in this path, and it doesn't need the DB
+            if request.method() == "GET" && request.url() == "/.well-known/jwks.json" {
+                return jwks(&tokens);
+            }
+            let conn = match pool.get() {
+                Ok(x) => x,
+                Err(e) => {
+                    eprintln!("Could not connect to the DB: {}", e);
+                    return Response::text("").with_status_code(503);
+                }
+            };
+            let conn = &*conn;
             let user_model = &UserModel::new(conn);
-            let user_service = &UserService::new(user_model, b"....");
+            let tokens = &tokens;
+            let refresh_token_model = &RefreshTokenModel::new(conn);
+            let revoked_token_model = &RevokedTokenModel::new(conn);
+            let user_service = &UserService::new(
+                user_model,
+                refresh_token_model,
+                revoked_token_model,
+                tokens,
+                &public_url,
+            );
+            let venue_model = &VenueModel::new(conn);
+            let venue_service = &VenueService::new(venue_model, user_service);
+            let event_model = &EventModel::new(conn);
+            let member_model = &MemberModel::new(conn);
+            let group_model = &GroupModel::new(conn);
+            let group_service = &GroupService::new(group_model, user_model, user_service);
+            let event_service = &EventService::new(
+                event_model,
+                venue_model,
+                member_model,
+                group_model,
+                user_service,
+            );
+            let member_service =
+                &MemberService::new(member_model, event_model, user_model, user_service);
+            let attendance_model = &AttendanceModel::new(conn);
+            let attendance_service = &AttendanceService::new(
+                attendance_model,
+                event_model,
+                member_model,
+                user_service,
+                tokens,
+            );
+            let invitation_model = &InvitationModel::new(conn);
+            let invitation_service = &InvitationService::new(
+                invitation_model,
+                event_model,
+                member_model,
+                user_model,
+                user_service,
+            );
+            let comment_model = &CommentModel::new(conn);
+            let comment_service =
+                &CommentService::new(comment_model, event_model, member_model, user_service);
+            let calendar_feed_model = &CalendarFeedModel::new(conn);
+            let calendar_service = &CalendarService::new(
+                calendar_feed_model,
+                event_model,
+                attendance_model,
+                user_service,
+                tokens,
+            );
+            let webhook_model = &WebhookModel::new(conn);
+            let webhook_service = &WebhookService::new(webhook_model, user_service, &webhook_admins);
 
             router!(request,
 
@@ -32,7 +160,93 @@ pub fn run() {
                 (POST) (/oauth/register) => { oauth_register(user_service, request) },
                 (GET)  (/oauth/register/confirm) => { oauth_register_confirm(user_service, request) },
                 (POST) (/oauth/token) => { oauth_token(user_service, request) },
+                (POST) (/oauth/revoke) => { oauth_revoke(user_service, request) },
+                (DELETE) (/oauth/sessions) => { log_out_all(user_service, request) },
                 (GET)  (/oauth/me) => { me(user_service, request) },
+                (GET)  (/events) => { list_events(event_service, request) },
+                (POST) (/events) => { create_event(event_service, request) },
+                (POST) (/events/import) => { import_events(event_service, request) },
+                (GET)  (/events/search) => { search_events(event_service, request) },
+                (GET)  (/tags) => { list_tags(event_service, request) },
+                (GET)  (/events/{id: Uuid}) => { get_event(event_service, request, id) },
+                (GET)  (/events/{file: String}) => { event_ics(calendar_service, request, &file) },
+                (PUT)  (/events/{id: Uuid}) => { update_event(event_service, request, id) },
+                (DELETE) (/events/{id: Uuid}) => { delete_event(event_service, request, id) },
+                (PUT)  (/events/{id: Uuid}/rsvp) => { rsvp(attendance_service, request, id) },
+                (DELETE) (/events/{id: Uuid}/rsvp) => { cancel_rsvp(attendance_service, request, id) },
+                (GET)  (/events/{id: Uuid}/attendees) => { attendees(attendance_service, request, id) },
+                (GET)  (/events/{id: Uuid}/ticket) => { ticket(attendance_service, request, id) },
+                (POST) (/events/{id: Uuid}/checkin) => { checkin(attendance_service, request, id) },
+                (GET)  (/events/{id: Uuid}/occurrences) => { occurrences(event_service, request, id) },
+                (GET)  (/events/{id: Uuid}/invitations) => {
+                    event_invitations(invitation_service, request, id)
+                },
+                (POST) (/events/{id: Uuid}/invitations) => { invite(invitation_service, request, id) },
+                (DELETE) (/events/{id: Uuid}/invitations/{user_id: Uuid}) => {
+                    revoke_invitation(invitation_service, request, id, user_id)
+                },
+                (PUT)  (/events/{id: Uuid}/invitation) => {
+                    respond_invitation(invitation_service, request, id)
+                },
+                (GET)  (/invitations) => { my_invitations(invitation_service, request) },
+                (GET)  (/events/{id: Uuid}/members) => { members(member_service, request, id) },
+                (PUT)  (/events/{id: Uuid}/members/{user_id: Uuid}) => {
+                    set_member(member_service, request, id, user_id)
+                },
+                (DELETE) (/events/{id: Uuid}/members/{user_id: Uuid}) => {
+                    remove_member(member_service, request, id, user_id)
+                },
+                (GET)  (/events/{id: Uuid}/comments) => { comments(comment_service, request, id) },
+                (POST) (/events/{id: Uuid}/comments) => {
+                    create_comment(comment_service, request, id)
+                },
+                (PUT)  (/events/{id: Uuid}/comments/{comment_id: Uuid}) => {
+                    edit_comment(comment_service, request, id, comment_id)
+                },
+                (DELETE) (/events/{id: Uuid}/comments/{comment_id: Uuid}) => {
+                    delete_comment(comment_service, request, id, comment_id)
+                },
+                (PUT)  (/events/{id: Uuid}/occurrences/{start: DateTime<Utc>}) => {
+                    override_occurrence(event_service, request, id, start)
+                },
+                (DELETE) (/events/{id: Uuid}/occurrences/{start: DateTime<Utc>}) => {
+                    cancel_occurrence(event_service, request, id, start)
+                },
+                (GET)  (/calendar/feeds) => { list_feeds(calendar_service, request) },
+                (POST) (/calendar/feeds) => { create_feed(calendar_service, request) },
+                (DELETE) (/calendar/feeds/{id: Uuid}) => { revoke_feed(calendar_service, request, id) },
+                (GET)  (/calendar/{file: String}) => { feed_ics(calendar_service, &file) },
+                (GET)  (/groups) => { list_groups(group_service, request) },
+                (POST) (/groups) => { create_group(group_service, request) },
+                (GET)  (/groups/following/events) => { following_events(event_service, request) },
+                (GET)  (/groups/{id: Uuid}) => { get_group(group_service, id) },
+                (PUT)  (/groups/{id: Uuid}) => { update_group(group_service, request, id) },
+                (DELETE) (/groups/{id: Uuid}) => { delete_group(group_service, request, id) },
+                (GET)  (/groups/{id: Uuid}/events) => { group_events(event_service, request, id) },
+                (GET)  (/groups/{id: Uuid}/members) => { group_members(group_service, id) },
+                (PUT)  (/groups/{id: Uuid}/members/{user_id: Uuid}) => {
+                    set_group_member(group_service, request, id, user_id)
+                },
+                (DELETE) (/groups/{id: Uuid}/members/{user_id: Uuid}) => {
+                    remove_group_member(group_service, request, id, user_id)
+                },
+                (PUT)  (/groups/{id: Uuid}/follow) => { follow_group(group_service, request, id) },
+                (DELETE) (/groups/{id: Uuid}/follow) => { unfollow_group(group_service, request, id) },
+                (GET)  (/venues) => { list_venues(venue_service, request) },
+                (POST) (/venues) => { create_venue(venue_service, request) },
+                (GET)  (/venues/{id: Uuid}) => { get_venue(venue_service, id) },
+                (PUT)  (/venues/{id: Uuid}) => { update_venue(venue_service, request, id) },
+                (DELETE) (/venues/{id: Uuid}) => { delete_venue(venue_service, request, id) },
+                (GET)  (/webhooks) => { list_webhooks(webhook_service, request) },
+                (POST) (/webhooks) => { create_webhook(webhook_service, request) },
+                (GET)  (/webhooks/{id: Uuid}) => { get_webhook(webhook_service, request, id) },
+                (DELETE) (/webhooks/{id: Uuid}) => { delete_webhook(webhook_service, request, id) },
+                (GET)  (/webhooks/{id: Uuid}/deliveries) => {
+                    webhook_deliveries(webhook_service, request, id)
+                },
+                (POST) (/webhooks/{id: Uuid}/deliveries/{delivery_id: Uuid}/redeliver) => {
+                    redeliver_webhook(webhook_service, request, id, delivery_id)
+                },
                 _ => Response::empty_404()
             )
         })
@@ -56,6 +270,11 @@ fn status(user_model: &UserModel) -> Response {
     Response::json(&status)
 }
 
+/// the public keys that other services check the tokens with
+fn jwks(tokens: &Tokens) -> Response {
+    Response::json(&tokens.jwks()).with_public_cache(300)
+}
+
 #[derive(Deserialize)]
 struct RegisterForm {
     name: String,
@@ -98,47 +317,1190 @@ fn oauth_register_confirm(user_service: &UserService, request: &Request) -> Resp
         .unwrap_or_else(Response::from)
 }
 
-/// this is the oauth token endpoint for making password or refresh grants against
-///
-/// This follows the protocol set up by the following specs
-///
-///  - [password grant](https://tools.ietf.org/html/rfc6749#section-4.3.2)
-///  - [refresh grant](https://tools.ietf.org/html/rfc6749#section-6)
-///
-fn oauth_token(user_service: &UserService, request: &Request) -> Response {
-    let form = &try_or_400!(post::raw_urlencoded_post_input(request));
-    let grant_type = try_or_400!(find_grant_type(form));
-    match grant_type {
-        GrantType::Password => {
-            let req = &try_or_400!(form_to_password_grant(form));
-            user_service
-                .password_grant(req)
-                .map(Response::from)
-                .unwrap_or_else(Response::from)
-        }
-        GrantType::Refresh => {
-            let req = &try_or_400!(form_to_refresh_grant(form));
+/// this is the oauth token endpoint for making password or refresh grants against
+///
+/// This follows the protocol set up by the following specs
+///
+///  - [password grant](https://tools.ietf.org/html/rfc6749#section-4.3.2)
+///  - [refresh grant](https://tools.ietf.org/html/rfc6749#section-6)
+///
+fn oauth_token(user_service: &UserService, request: &Request) -> Response {
+    let form = &try_or_400!(post::raw_urlencoded_post_input(request));
+    let grant_type = try_or_400!(find_grant_type(form));
+    match grant_type {
+        GrantType::Password => {
+            let req = &try_or_400!(form_to_password_grant(form));
+            user_service
+                .password_grant(req)
+                .map(Response::from)
+                .unwrap_or_else(Response::from)
+        }
+        GrantType::Refresh => {
+            let req = &try_or_400!(form_to_refresh_grant(form));
+
+            user_service
+                .refresh_token_grant(req)
+                .map(Response::from)
+                .unwrap_or_else(Response::from)
+        }
+    }
+}
+
+/// this is the oauth revocation endpoint for access and refresh tokens
+///
+/// It follows [RFC-7009](https://tools.ietf.org/html/rfc7009), the `token_type_hint` is ignored
+fn oauth_revoke(user_service: &UserService, request: &Request) -> Response {
+    let form = &try_or_400!(post::raw_urlencoded_post_input(request));
+    let req = &try_or_400!(form_to_revoke_request(form));
+
+    user_service
+        .revoke(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+/// logs the current user out of every session
+///
+/// This requires a `Authorization: Bearer {access_token}` header to make the request
+fn log_out_all(user_service: &UserService, request: &Request) -> Response {
+    let access_token = bearer_token(request);
+
+    let req = &user::LogOutAllRequest { access_token };
+    user_service
+        .log_out_all(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+/// The current user handler
+///
+/// This requires a `Authorization: Bearer {access_token}` header to make the request
+fn me(user_service: &UserService, request: &Request) -> Response {
+    let access_token = bearer_token(request);
+
+    let req = &user::CurrentUserRequest { access_token };
+    user_service
+        .current_user(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+#[derive(Deserialize)]
+struct EventForm {
+    name: String,
+    #[serde(default)]
+    description: String,
+    start_date: DateTime<Utc>,
+    end_date: DateTime<Utc>,
+    max_attendees: Option<i32>,
+    rrule: Option<String>,
+    #[serde(default)]
+    exdates: Vec<DateTime<Utc>>,
+    time_zone: Option<String>,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+    #[serde(default)]
+    tags: Vec<String>,
+    venue_id: Option<Uuid>,
+    #[serde(default)]
+    visibility: Visibility,
+    group_id: Option<Uuid>,
+}
+
+/// this is the event creation endpoint
+///
+/// This accepts a json POST of [`EventForm`] and requires a `Authorization: Bearer {access_token}` header
+fn create_event(event_service: &EventService, request: &Request) -> Response {
+    let data: EventForm = try_or_400!(rouille::input::json_input(request));
+
+    let req = &event::CreateEventRequest {
+        access_token: bearer_token(request),
+        name: &data.name,
+        description: &data.description,
+        start_date: data.start_date,
+        end_date: data.end_date,
+        max_attendees: data.max_attendees,
+        rrule: data.rrule.as_deref(),
+        exdates: data.exdates,
+        time_zone: data.time_zone.as_deref(),
+        latitude: data.latitude,
+        longitude: data.longitude,
+        tags: data.tags,
+        venue_id: data.venue_id,
+        visibility: data.visibility,
+        group_id: data.group_id,
+    };
+    event_service
+        .create(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+/// this is the iCalendar import endpoint
+///
+/// This accepts a `text/calendar` POST and requires a `Authorization: Bearer {access_token}` header.
+/// An optional `?visibility` query string gives every imported event that visibility.
+fn import_events(event_service: &EventService, request: &Request) -> Response {
+    let calendar = try_or_400!(calendar_body(request));
+    let visibility = try_or_400!(
+        request
+            .get_param("visibility")
+            .map(|x| Visibility::from_str(&x).map_err(|_| WebError::InvalidVisibility))
+            .transpose()
+    );
+
+    let req = &event::ImportEventsRequest {
+        access_token: bearer_token(request),
+        calendar: &calendar,
+        visibility,
+    };
+    event_service
+        .import(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+/// this is the upcoming events endpoint
+///
+/// This accepts an optional `?tz` query string of the IANA time zone to render the dates in, and
+/// repeated `tag` filters that are combined by `tag_match`, either `all` or `any`
+fn list_events(event_service: &EventService, request: &Request) -> Response {
+    let tz = request.get_param("tz");
+    let (tags, tag_match) = try_or_400!(tag_params(request));
+    let page = try_or_400!(page_params(request));
+
+    let req = &event::ListEventsRequest {
+        access_token: optional_bearer_token(request),
+        tags,
+        tag_match,
+        tz: tz.as_deref(),
+        page,
+    };
+    event_service
+        .list(req)
+        .map(|x| Paginated::new(&x, x.next.as_ref()).response(request))
+        .unwrap_or_else(Response::from)
+}
+
+/// this is the event search endpoint
+///
+/// This accepts a query string of `?q&from&to&near&radius_km&tag&tag_match&tz`, every one is
+/// optional.  `near` is a `latitude,longitude` pair and `tag` can be repeated, the tags are combined
+/// by `tag_match`, either `all` or `any`.
+fn search_events(event_service: &EventService, request: &Request) -> Response {
+    let q = request.get_param("q");
+    let from = try_or_400!(date_param(request, "from"));
+    let to = try_or_400!(date_param(request, "to"));
+    let near = try_or_400!(
+        request
+            .get_param("near")
+            .map(|x| parse_location(&x).ok_or(WebError::InvalidLocation))
+            .transpose()
+    );
+    let radius_km = try_or_400!(
+        request
+            .get_param("radius_km")
+            .map(|x| f64::from_str(&x).map_err(|_| WebError::InvalidLocation))
+            .transpose()
+    );
+    let (tags, tag_match) = try_or_400!(tag_params(request));
+    let tz = request.get_param("tz");
+    let page = try_or_400!(page_params(request));
+
+    let req = &event::SearchEventsRequest {
+        access_token: optional_bearer_token(request),
+        q: q.as_deref(),
+        from,
+        to,
+        near,
+        radius_km,
+        tags,
+        tag_match,
+        tz: tz.as_deref(),
+        page,
+    };
+    event_service
+        .search(req)
+        .map(|x| Paginated::new(&x, x.next.as_ref()).response(request))
+        .unwrap_or_else(Response::from)
+}
+
+/// this is the endpoint for listing the tags of the events, the most used first
+fn list_tags(event_service: &EventService, request: &Request) -> Response {
+    let req = &event::TagsRequest {
+        access_token: optional_bearer_token(request),
+    };
+    event_service
+        .tags(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+/// this is the single event endpoint
+///
+/// This accepts an optional `?tz` query string of the IANA time zone to render the dates in
+fn get_event(event_service: &EventService, request: &Request, event_id: Uuid) -> Response {
+    let tz = request.get_param("tz");
+
+    let req = &event::GetEventRequest {
+        access_token: optional_bearer_token(request),
+        event_id,
+        tz: tz.as_deref(),
+    };
+    event_service
+        .get(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+#[derive(Deserialize)]
+struct UpdateEventForm {
+    name: Option<String>,
+    description: Option<String>,
+    start_date: Option<DateTime<Utc>>,
+    end_date: Option<DateTime<Utc>>,
+    #[serde(default, deserialize_with = "double_option")]
+    max_attendees: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "double_option")]
+    rrule: Option<Option<String>>,
+    exdates: Option<Vec<DateTime<Utc>>>,
+    time_zone: Option<String>,
+    #[serde(default, deserialize_with = "double_option")]
+    latitude: Option<Option<f64>>,
+    #[serde(default, deserialize_with = "double_option")]
+    longitude: Option<Option<f64>>,
+    tags: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "double_option")]
+    venue_id: Option<Option<Uuid>>,
+    visibility: Option<Visibility>,
+    #[serde(default, deserialize_with = "double_option")]
+    group_id: Option<Option<Uuid>>,
+}
+
+/// this is the event update endpoint
+///
+/// This accepts a json PUT of [`UpdateEventForm`], only the owner and co-organizers of the event may change it
+fn update_event(event_service: &EventService, request: &Request, event_id: Uuid) -> Response {
+    let data: UpdateEventForm = try_or_400!(rouille::input::json_input(request));
+
+    let req = &event::UpdateEventRequest {
+        access_token: bearer_token(request),
+        event_id,
+        name: data.name.as_deref(),
+        description: data.description.as_deref(),
+        start_date: data.start_date,
+        end_date: data.end_date,
+        max_attendees: data.max_attendees,
+        rrule: data.rrule.as_ref().map(|x| x.as_deref()),
+        exdates: data.exdates,
+        time_zone: data.time_zone.as_deref(),
+        latitude: data.latitude,
+        longitude: data.longitude,
+        tags: data.tags,
+        venue_id: data.venue_id,
+        visibility: data.visibility,
+        group_id: data.group_id,
+    };
+    event_service
+        .update(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+/// this is the event deletion endpoint
+///
+/// Only the owner of the event may delete it
+fn delete_event(event_service: &EventService, request: &Request, event_id: Uuid) -> Response {
+    let req = &event::DeleteEventRequest {
+        access_token: bearer_token(request),
+        event_id,
+    };
+    event_service
+        .delete(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+/// this is the event occurrences endpoint
+///
+/// This accepts a query string of `?from&to` dates, it defaults to the next 30 days, and an optional
+/// `?tz` of the IANA time zone to render the dates in
+fn occurrences(event_service: &EventService, request: &Request, event_id: Uuid) -> Response {
+    let from = try_or_400!(date_param(request, "from")).unwrap_or_else(Utc::now);
+    let to = try_or_400!(date_param(request, "to")).unwrap_or(from + Duration::days(30));
+
+    let tz = request.get_param("tz");
+
+    let req = &event::OccurrencesRequest {
+        access_token: optional_bearer_token(request),
+        event_id,
+        from,
+        to,
+        tz: tz.as_deref(),
+    };
+    event_service
+        .occurrences(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+#[derive(Deserialize)]
+struct OccurrenceForm {
+    name: Option<String>,
+    description: Option<String>,
+    start_date: Option<DateTime<Utc>>,
+    end_date: Option<DateTime<Utc>>,
+}
+
+/// this is the endpoint for changing one occurrence of a recurring event
+///
+/// This accepts a json PUT of [`OccurrenceForm`], the occurrence is identified by the start the
+/// RRULE gives it
+fn override_occurrence(
+    event_service: &EventService,
+    request: &Request,
+    event_id: Uuid,
+    original_start_date: DateTime<Utc>,
+) -> Response {
+    let data: OccurrenceForm = try_or_400!(rouille::input::json_input(request));
+
+    let req = &event::OverrideOccurrenceRequest {
+        access_token: bearer_token(request),
+        event_id,
+        original_start_date,
+        name: data.name.as_deref(),
+        description: data.description.as_deref(),
+        start_date: data.start_date,
+        end_date: data.end_date,
+    };
+    event_service
+        .override_occurrence(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+/// this is the endpoint for cancelling one occurrence of a recurring event
+fn cancel_occurrence(
+    event_service: &EventService,
+    request: &Request,
+    event_id: Uuid,
+    original_start_date: DateTime<Utc>,
+) -> Response {
+    let req = &event::CancelOccurrenceRequest {
+        access_token: bearer_token(request),
+        event_id,
+        original_start_date,
+    };
+    event_service
+        .cancel_occurrence(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+/// this is the iCalendar export of a single event, `/events/{id}.ics`
+fn event_ics(calendar_service: &CalendarService, request: &Request, file: &str) -> Response {
+    let event_id = match file.strip_suffix(".ics").and_then(|x| Uuid::parse_str(x).ok()) {
+        Some(x) => x,
+        None => return Response::empty_404(),
+    };
+
+    let req = &calendar::EventCalendarRequest {
+        access_token: optional_bearer_token(request),
+        event_id,
+    };
+    calendar_service
+        .event_calendar(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+/// this is the iCalendar subscription feed, `/calendar/{feed_token}.ics`
+///
+/// The feed token takes the place of the `Authorization` header since calendar clients can't send one
+fn feed_ics(calendar_service: &CalendarService, file: &str) -> Response {
+    let feed_token = match file.strip_suffix(".ics") {
+        Some(x) => x,
+        None => return Response::empty_404(),
+    };
+
+    calendar_service
+        .feed_calendar(&calendar::FeedCalendarRequest { feed_token })
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+/// this is the endpoint for creating a new calendar feed token for the current user
+fn create_feed(calendar_service: &CalendarService, request: &Request) -> Response {
+    let req = &calendar::CreateFeedRequest {
+        access_token: bearer_token(request),
+    };
+    calendar_service
+        .create_feed(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+/// this is the endpoint for listing the current user's calendar feeds
+fn list_feeds(calendar_service: &CalendarService, request: &Request) -> Response {
+    let page = try_or_400!(page_params(request));
+
+    let req = &calendar::ListFeedsRequest {
+        access_token: bearer_token(request),
+        page,
+    };
+    calendar_service
+        .list_feeds(req)
+        .map(|x| Paginated::new(&x, x.next.as_ref()).response(request))
+        .unwrap_or_else(Response::from)
+}
+
+/// this is the endpoint for revoking one of the current user's calendar feeds
+fn revoke_feed(calendar_service: &CalendarService, request: &Request, feed_id: Uuid) -> Response {
+    let req = &calendar::RevokeFeedRequest {
+        access_token: bearer_token(request),
+        feed_id,
+    };
+    calendar_service
+        .revoke_feed(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+#[derive(Deserialize)]
+struct RsvpForm {
+    status: RsvpStatus,
+}
+
+/// this is the RSVP endpoint
+///
+/// This accepts a json PUT of [`RsvpForm`] and sets the RSVP of the user that owns the access token
+fn rsvp(attendance_service: &AttendanceService, request: &Request, event_id: Uuid) -> Response {
+    let data: RsvpForm = try_or_400!(rouille::input::json_input(request));
+
+    let req = &attendance::RsvpRequest {
+        access_token: bearer_token(request),
+        event_id,
+        status: data.status,
+    };
+    attendance_service
+        .rsvp(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+/// this is the cancel RSVP endpoint
+fn cancel_rsvp(attendance_service: &AttendanceService, request: &Request, event_id: Uuid) -> Response {
+    let req = &attendance::CancelRsvpRequest {
+        access_token: bearer_token(request),
+        event_id,
+    };
+    attendance_service
+        .cancel_rsvp(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+/// this is the endpoint for getting the current user's ticket to an event
+///
+/// Only the people with a spot at the event have a ticket
+fn ticket(attendance_service: &AttendanceService, request: &Request, event_id: Uuid) -> Response {
+    let req = &attendance::TicketRequest {
+        access_token: bearer_token(request),
+        event_id,
+    };
+    attendance_service
+        .ticket(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+#[derive(Deserialize)]
+struct CheckinForm {
+    ticket: String,
+}
+
+/// this is the check-in endpoint for scanning tickets at the door
+///
+/// This accepts a json POST of [`CheckinForm`], only the organizers and check-in staff of the
+/// event may check people in
+fn checkin(attendance_service: &AttendanceService, request: &Request, event_id: Uuid) -> Response {
+    let data: CheckinForm = try_or_400!(rouille::input::json_input(request));
+
+    let req = &attendance::CheckinRequest {
+        access_token: bearer_token(request),
+        event_id,
+        ticket: &data.ticket,
+    };
+    attendance_service
+        .check_in(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+/// this is the attendee list endpoint
+///
+/// This accepts an optional `?status` query string to only list people with that RSVP
+fn attendees(attendance_service: &AttendanceService, request: &Request, event_id: Uuid) -> Response {
+    let status = try_or_400!(
+        request
+            .get_param("status")
+            .map(|x| RsvpStatus::from_str(&x).map_err(|_| WebError::InvalidRsvpStatus))
+            .transpose()
+    );
+
+    let page = try_or_400!(page_params(request));
+
+    let req = &attendance::AttendeesRequest {
+        access_token: optional_bearer_token(request),
+        event_id,
+        status,
+        page,
+    };
+    attendance_service
+        .attendees(req)
+        .map(|x| Paginated::new(&x, x.next.as_ref()).response(request))
+        .unwrap_or_else(Response::from)
+}
+
+#[derive(Deserialize)]
+struct InviteForm {
+    user_id: Option<Uuid>,
+    email: Option<String>,
+}
+
+/// this is the endpoint for inviting a user to an event
+///
+/// This accepts a json POST of [`InviteForm`] with either a `user_id` or an `email`, only the
+/// organizers of the event may invite people to it
+fn invite(invitation_service: &InvitationService, request: &Request, event_id: Uuid) -> Response {
+    let data: InviteForm = try_or_400!(rouille::input::json_input(request));
+
+    let req = &invitation::InviteRequest {
+        access_token: bearer_token(request),
+        event_id,
+        user_id: data.user_id,
+        email: data.email.as_deref(),
+    };
+    invitation_service
+        .invite(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+/// this is the endpoint for listing the invitations to an event, only its organizers may list them
+fn event_invitations(
+    invitation_service: &InvitationService,
+    request: &Request,
+    event_id: Uuid,
+) -> Response {
+    let req = &invitation::EventInvitationsRequest {
+        access_token: bearer_token(request),
+        event_id,
+    };
+    invitation_service
+        .event_invitations(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+/// this is the endpoint for taking back an invitation, only the organizers of the event may do it
+fn revoke_invitation(
+    invitation_service: &InvitationService,
+    request: &Request,
+    event_id: Uuid,
+    user_id: Uuid,
+) -> Response {
+    let req = &invitation::RevokeInvitationRequest {
+        access_token: bearer_token(request),
+        event_id,
+        user_id,
+    };
+    invitation_service
+        .revoke(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+#[derive(Deserialize)]
+struct InvitationForm {
+    status: InvitationStatus,
+}
+
+/// this is the endpoint for answering an invitation
+///
+/// This accepts a json PUT of [`InvitationForm`] with a status of `accepted` or `declined`
+fn respond_invitation(
+    invitation_service: &InvitationService,
+    request: &Request,
+    event_id: Uuid,
+) -> Response {
+    let data: InvitationForm = try_or_400!(rouille::input::json_input(request));
+
+    let req = &invitation::RespondInvitationRequest {
+        access_token: bearer_token(request),
+        event_id,
+        status: data.status,
+    };
+    invitation_service
+        .respond(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+/// this is the endpoint for listing the current user's invitations
+fn my_invitations(invitation_service: &InvitationService, request: &Request) -> Response {
+    let req = &invitation::MyInvitationsRequest {
+        access_token: bearer_token(request),
+    };
+    invitation_service
+        .my_invitations(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+/// this is the endpoint for listing the members of an event, only its members may list them
+fn members(member_service: &MemberService, request: &Request, event_id: Uuid) -> Response {
+    let req = &member::MembersRequest {
+        access_token: bearer_token(request),
+        event_id,
+    };
+    member_service
+        .members(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+#[derive(Deserialize)]
+struct MemberForm {
+    role: Role,
+}
+
+/// this is the endpoint for giving a user a role in an event
+///
+/// This accepts a json PUT of [`MemberForm`].  Only the owner may add co-organizers, the
+/// co-organizers may add check-in staff and attendees.
+fn set_member(
+    member_service: &MemberService,
+    request: &Request,
+    event_id: Uuid,
+    user_id: Uuid,
+) -> Response {
+    let data: MemberForm = try_or_400!(rouille::input::json_input(request));
+
+    let req = &member::SetMemberRequest {
+        access_token: bearer_token(request),
+        event_id,
+        user_id,
+        role: data.role,
+    };
+    member_service
+        .set_member(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+/// this is the endpoint for taking away a user's role in an event
+///
+/// The members other than the owner may also use it to leave the event
+fn remove_member(
+    member_service: &MemberService,
+    request: &Request,
+    event_id: Uuid,
+    user_id: Uuid,
+) -> Response {
+    let req = &member::RemoveMemberRequest {
+        access_token: bearer_token(request),
+        event_id,
+        user_id,
+    };
+    member_service
+        .remove_member(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+#[derive(Deserialize)]
+struct CommentForm {
+    parent_id: Option<Uuid>,
+    body: String,
+}
+
+/// this is the endpoint for commenting on an event
+///
+/// This accepts a json POST of [`CommentForm`], set `parent_id` to reply to another comment
+fn create_comment(comment_service: &CommentService, request: &Request, event_id: Uuid) -> Response {
+    let data: CommentForm = try_or_400!(rouille::input::json_input(request));
+
+    let req = &comment::CreateCommentRequest {
+        access_token: bearer_token(request),
+        event_id,
+        parent_id: data.parent_id,
+        body: &data.body,
+    };
+    comment_service
+        .create(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+/// this is the endpoint for listing the comments on an event, the oldest first
+fn comments(comment_service: &CommentService, request: &Request, event_id: Uuid) -> Response {
+    let page = try_or_400!(page_params(request));
+
+    let req = &comment::ListCommentsRequest {
+        access_token: optional_bearer_token(request),
+        event_id,
+        page,
+    };
+    comment_service
+        .list(req)
+        .map(|x| Paginated::new(&x, x.next.as_ref()).response(request))
+        .unwrap_or_else(Response::from)
+}
+
+#[derive(Deserialize)]
+struct EditCommentForm {
+    body: String,
+}
+
+/// this is the endpoint for editing a comment
+///
+/// This accepts a json PUT of [`EditCommentForm`], only the author may edit their comment
+fn edit_comment(
+    comment_service: &CommentService,
+    request: &Request,
+    event_id: Uuid,
+    comment_id: Uuid,
+) -> Response {
+    let data: EditCommentForm = try_or_400!(rouille::input::json_input(request));
+
+    let req = &comment::EditCommentRequest {
+        access_token: bearer_token(request),
+        event_id,
+        comment_id,
+        body: &data.body,
+    };
+    comment_service
+        .edit(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+/// this is the endpoint for deleting a comment
+///
+/// The author and the organizers of the event may delete it, its replies stay in place
+fn delete_comment(
+    comment_service: &CommentService,
+    request: &Request,
+    event_id: Uuid,
+    comment_id: Uuid,
+) -> Response {
+    let req = &comment::DeleteCommentRequest {
+        access_token: bearer_token(request),
+        event_id,
+        comment_id,
+    };
+    comment_service
+        .delete(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+#[derive(Deserialize)]
+struct GroupForm {
+    name: String,
+    #[serde(default)]
+    description: String,
+}
+
+/// this is the group creation endpoint
+///
+/// This accepts a json POST of [`GroupForm`], the current user becomes the group's first admin
+fn create_group(group_service: &GroupService, request: &Request) -> Response {
+    let data: GroupForm = try_or_400!(rouille::input::json_input(request));
+
+    let req = &group::CreateGroupRequest {
+        access_token: bearer_token(request),
+        name: &data.name,
+        description: &data.description,
+    };
+    group_service
+        .create(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+/// this is the group list endpoint
+fn list_groups(group_service: &GroupService, request: &Request) -> Response {
+    let page = try_or_400!(page_params(request));
+
+    let req = &group::ListGroupsRequest { page };
+    group_service
+        .list(req)
+        .map(|x| Paginated::new(&x, x.next.as_ref()).response(request))
+        .unwrap_or_else(Response::from)
+}
+
+/// this is the single group endpoint
+fn get_group(group_service: &GroupService, group_id: Uuid) -> Response {
+    group_service
+        .get(&group::GetGroupRequest { group_id })
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+#[derive(Deserialize)]
+struct UpdateGroupForm {
+    name: Option<String>,
+    description: Option<String>,
+}
+
+/// this is the group update endpoint
+///
+/// This accepts a json PUT of [`UpdateGroupForm`], only the admins of the group may change it
+fn update_group(group_service: &GroupService, request: &Request, group_id: Uuid) -> Response {
+    let data: UpdateGroupForm = try_or_400!(rouille::input::json_input(request));
+
+    let req = &group::UpdateGroupRequest {
+        access_token: bearer_token(request),
+        group_id,
+        name: data.name.as_deref(),
+        description: data.description.as_deref(),
+    };
+    group_service
+        .update(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+/// this is the group deletion endpoint
+///
+/// Only the admins of the group may delete it, its events are left on their own
+fn delete_group(group_service: &GroupService, request: &Request, group_id: Uuid) -> Response {
+    let req = &group::DeleteGroupRequest {
+        access_token: bearer_token(request),
+        group_id,
+    };
+    group_service
+        .delete(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+/// this is the endpoint for listing the upcoming events of a group
+///
+/// This accepts an optional `?tz` query string of the IANA time zone to render the dates in
+fn group_events(event_service: &EventService, request: &Request, group_id: Uuid) -> Response {
+    let tz = request.get_param("tz");
+    let page = try_or_400!(page_params(request));
+
+    let req = &event::GroupEventsRequest {
+        access_token: optional_bearer_token(request),
+        group_id,
+        tz: tz.as_deref(),
+        page,
+    };
+    event_service
+        .group_events(req)
+        .map(|x| Paginated::new(&x, x.next.as_ref()).response(request))
+        .unwrap_or_else(Response::from)
+}
+
+/// this is the endpoint for listing the upcoming events of the groups the current user follows
+///
+/// This accepts an optional `?tz` query string of the IANA time zone to render the dates in
+fn following_events(event_service: &EventService, request: &Request) -> Response {
+    let tz = request.get_param("tz");
+    let page = try_or_400!(page_params(request));
+
+    let req = &event::FollowingEventsRequest {
+        access_token: bearer_token(request),
+        tz: tz.as_deref(),
+        page,
+    };
+    event_service
+        .following(req)
+        .map(|x| Paginated::new(&x, x.next.as_ref()).response(request))
+        .unwrap_or_else(Response::from)
+}
+
+/// this is the endpoint for listing the members of a group, the admins first
+fn group_members(group_service: &GroupService, group_id: Uuid) -> Response {
+    group_service
+        .members(&group::GroupMembersRequest { group_id })
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+#[derive(Deserialize)]
+struct GroupMemberForm {
+    role: GroupRole,
+}
+
+/// this is the endpoint for adding a user to a group or changing their role in it
+///
+/// This accepts a json PUT of [`GroupMemberForm`], only the admins of the group may change its
+/// members
+fn set_group_member(
+    group_service: &GroupService,
+    request: &Request,
+    group_id: Uuid,
+    user_id: Uuid,
+) -> Response {
+    let data: GroupMemberForm = try_or_400!(rouille::input::json_input(request));
+
+    let req = &group::SetGroupMemberRequest {
+        access_token: bearer_token(request),
+        group_id,
+        user_id,
+        role: data.role,
+    };
+    group_service
+        .set_member(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+/// this is the endpoint for taking a user out of a group
+///
+/// The admins may take anyone out, the members may leave on their own
+fn remove_group_member(
+    group_service: &GroupService,
+    request: &Request,
+    group_id: Uuid,
+    user_id: Uuid,
+) -> Response {
+    let req = &group::RemoveGroupMemberRequest {
+        access_token: bearer_token(request),
+        group_id,
+        user_id,
+    };
+    group_service
+        .remove_member(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+/// this is the endpoint for following a group
+fn follow_group(group_service: &GroupService, request: &Request, group_id: Uuid) -> Response {
+    let req = &group::FollowGroupRequest {
+        access_token: bearer_token(request),
+        group_id,
+    };
+    group_service
+        .follow(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+/// this is the endpoint for no longer following a group
+fn unfollow_group(group_service: &GroupService, request: &Request, group_id: Uuid) -> Response {
+    let req = &group::FollowGroupRequest {
+        access_token: bearer_token(request),
+        group_id,
+    };
+    group_service
+        .unfollow(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+#[derive(Deserialize)]
+struct VenueForm {
+    name: String,
+    #[serde(default)]
+    address: String,
+    geo: Option<venue::GeoCoordinates>,
+    capacity: Option<i32>,
+    #[serde(default)]
+    accessibility_notes: String,
+}
+
+/// this is the venue creation endpoint
+///
+/// This accepts a json POST of [`VenueForm`] and requires a `Authorization: Bearer {access_token}` header
+fn create_venue(venue_service: &VenueService, request: &Request) -> Response {
+    let data: VenueForm = try_or_400!(rouille::input::json_input(request));
+
+    let req = &venue::CreateVenueRequest {
+        access_token: bearer_token(request),
+        name: &data.name,
+        address: &data.address,
+        geo: data.geo,
+        capacity: data.capacity,
+        accessibility_notes: &data.accessibility_notes,
+    };
+    venue_service
+        .create(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+/// this is the venue list endpoint
+fn list_venues(venue_service: &VenueService, request: &Request) -> Response {
+    let page = try_or_400!(page_params(request));
+
+    let req = &venue::ListVenuesRequest { page };
+    venue_service
+        .list(req)
+        .map(|x| Paginated::new(&x, x.next.as_ref()).response(request))
+        .unwrap_or_else(Response::from)
+}
+
+/// this is the single venue endpoint
+fn get_venue(venue_service: &VenueService, venue_id: Uuid) -> Response {
+    venue_service
+        .get(&venue::GetVenueRequest { venue_id })
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+#[derive(Deserialize)]
+struct UpdateVenueForm {
+    name: Option<String>,
+    address: Option<String>,
+    #[serde(default, deserialize_with = "double_option")]
+    geo: Option<Option<venue::GeoCoordinates>>,
+    #[serde(default, deserialize_with = "double_option")]
+    capacity: Option<Option<i32>>,
+    accessibility_notes: Option<String>,
+}
+
+/// this is the venue update endpoint
+///
+/// This accepts a json PUT of [`UpdateVenueForm`], only the owner of the venue may change it
+fn update_venue(venue_service: &VenueService, request: &Request, venue_id: Uuid) -> Response {
+    let data: UpdateVenueForm = try_or_400!(rouille::input::json_input(request));
+
+    let req = &venue::UpdateVenueRequest {
+        access_token: bearer_token(request),
+        venue_id,
+        name: data.name.as_deref(),
+        address: data.address.as_deref(),
+        geo: data.geo,
+        capacity: data.capacity,
+        accessibility_notes: data.accessibility_notes.as_deref(),
+    };
+    venue_service
+        .update(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+/// this is the venue deletion endpoint
+///
+/// Only the owner of the venue may delete it, its events are left without a venue
+fn delete_venue(venue_service: &VenueService, request: &Request, venue_id: Uuid) -> Response {
+    let req = &venue::DeleteVenueRequest {
+        access_token: bearer_token(request),
+        venue_id,
+    };
+    venue_service
+        .delete(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+#[derive(Deserialize)]
+struct WebhookForm {
+    url: String,
+    #[serde(default)]
+    topics: Vec<WebhookTopic>,
+}
+
+/// this is the webhook subscription endpoint
+///
+/// This accepts a json POST of [`WebhookForm`], only the users in `WEBHOOK_ADMINS` may subscribe
+fn create_webhook(webhook_service: &WebhookService, request: &Request) -> Response {
+    let data: WebhookForm = try_or_400!(rouille::input::json_input(request));
+
+    let req = &webhook::CreateWebhookRequest {
+        access_token: bearer_token(request),
+        url: &data.url,
+        topics: data.topics,
+    };
+    webhook_service
+        .create(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+/// this is the endpoint for the current user's webhook subscriptions
+fn list_webhooks(webhook_service: &WebhookService, request: &Request) -> Response {
+    let page = try_or_400!(page_params(request));
+
+    let req = &webhook::ListWebhooksRequest {
+        access_token: bearer_token(request),
+        page,
+    };
+    webhook_service
+        .list(req)
+        .map(|x| Paginated::new(&x, x.next.as_ref()).response(request))
+        .unwrap_or_else(Response::from)
+}
+
+/// this is the single webhook subscription endpoint
+fn get_webhook(webhook_service: &WebhookService, request: &Request, webhook_id: Uuid) -> Response {
+    let req = &webhook::GetWebhookRequest {
+        access_token: bearer_token(request),
+        webhook_id,
+    };
+    webhook_service
+        .get(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+/// this is the webhook deletion endpoint, the delivery log goes with it
+fn delete_webhook(
+    webhook_service: &WebhookService,
+    request: &Request,
+    webhook_id: Uuid,
+) -> Response {
+    let req = &webhook::DeleteWebhookRequest {
+        access_token: bearer_token(request),
+        webhook_id,
+    };
+    webhook_service
+        .delete(req)
+        .map(Response::from)
+        .unwrap_or_else(Response::from)
+}
+
+/// this is the delivery log endpoint of a webhook subscription
+fn webhook_deliveries(
+    webhook_service: &WebhookService,
+    request: &Request,
+    webhook_id: Uuid,
+) -> Response {
+    let page = try_or_400!(page_params(request));
 
-            user_service
-                .refresh_token_grant(req)
-                .map(Response::from)
-                .unwrap_or_else(Response::from)
-        }
-    }
+    let req = &webhook::DeliveriesRequest {
+        access_token: bearer_token(request),
+        webhook_id,
+        page,
+    };
+    webhook_service
+        .deliveries(req)
+        .map(|x| Paginated::new(&x, x.next.as_ref()).response(request))
+        .unwrap_or_else(Response::from)
 }
 
-/// The current user handler
-///
-/// This requires a `Authorization: Bearer {access_token}` header to make the request
-fn me(user_service: &UserService, request: &Request) -> Response {
-    let access_token = request.header("Authorization")
-        .and_then(move |x| x.get(7..)) // Get everything after "Bearer "
-        .unwrap_or("");
-
-    let req = &user::CurrentUserRequest { access_token };
-    user_service
-        .current_user(req)
-        .map(Response::from)
+/// this is the endpoint for sending a webhook delivery again
+fn redeliver_webhook(
+    webhook_service: &WebhookService,
+    request: &Request,
+    webhook_id: Uuid,
+    delivery_id: Uuid,
+) -> Response {
+    let req = &webhook::RedeliverRequest {
+        access_token: bearer_token(request),
+        webhook_id,
+        delivery_id,
+    };
+    webhook_service
+        .redeliver(req)
+        .map(|x| Response::from(x).with_status_code(202))
         .unwrap_or_else(Response::from)
 }
 
@@ -162,12 +1524,223 @@ impl From<user::ConfirmNewUserResponse> for Response {
     }
 }
 
+impl From<user::RevokeResponse> for Response {
+    fn from(_: user::RevokeResponse) -> Self {
+        Response::text("")
+    }
+}
+
+impl From<user::LogOutAllResponse> for Response {
+    fn from(_: user::LogOutAllResponse) -> Self {
+        Response::empty_204()
+    }
+}
+
 impl From<user::RegisterResponse> for Response {
-    fn from(result: user::RegisterResponse) -> Self {
+    fn from(_: user::RegisterResponse) -> Self {
+        Response::empty_204()
+    }
+}
+
+impl From<event::EventResponse> for Response {
+    fn from(result: event::EventResponse) -> Self {
+        Response::json(&result)
+    }
+}
+
+impl From<event::TagsResponse> for Response {
+    fn from(result: event::TagsResponse) -> Self {
+        Response::json(&result)
+    }
+}
+
+impl From<event::ImportEventsResponse> for Response {
+    fn from(result: event::ImportEventsResponse) -> Self {
+        Response::json(&result)
+    }
+}
+
+impl From<event::DeleteEventResponse> for Response {
+    fn from(_: event::DeleteEventResponse) -> Self {
+        Response::empty_204()
+    }
+}
+
+impl From<event::OccurrenceResponse> for Response {
+    fn from(result: event::OccurrenceResponse) -> Self {
+        Response::json(&result)
+    }
+}
+
+impl From<event::OccurrencesResponse> for Response {
+    fn from(result: event::OccurrencesResponse) -> Self {
+        Response::json(&result)
+    }
+}
+
+impl From<event::CancelOccurrenceResponse> for Response {
+    fn from(_: event::CancelOccurrenceResponse) -> Self {
+        Response::empty_204()
+    }
+}
+
+impl From<calendar::CalendarResponse> for Response {
+    fn from(result: calendar::CalendarResponse) -> Self {
+        Response::from_data("text/calendar; charset=utf-8", result.calendar.to_string())
+    }
+}
+
+impl From<calendar::FeedResponse> for Response {
+    fn from(result: calendar::FeedResponse) -> Self {
+        Response::json(&result)
+    }
+}
+
+impl From<calendar::RevokeFeedResponse> for Response {
+    fn from(_: calendar::RevokeFeedResponse) -> Self {
+        Response::empty_204()
+    }
+}
+
+impl From<comment::CommentResponse> for Response {
+    fn from(result: comment::CommentResponse) -> Self {
+        Response::json(&result)
+    }
+}
+
+impl From<comment::DeleteCommentResponse> for Response {
+    fn from(_: comment::DeleteCommentResponse) -> Self {
+        Response::empty_204()
+    }
+}
+
+impl From<group::GroupResponse> for Response {
+    fn from(result: group::GroupResponse) -> Self {
+        Response::json(&result)
+    }
+}
+
+impl From<group::GroupMemberResponse> for Response {
+    fn from(result: group::GroupMemberResponse) -> Self {
+        Response::json(&result)
+    }
+}
+
+impl From<group::GroupMembersResponse> for Response {
+    fn from(result: group::GroupMembersResponse) -> Self {
+        Response::json(&result)
+    }
+}
+
+impl From<group::DeleteGroupResponse> for Response {
+    fn from(_: group::DeleteGroupResponse) -> Self {
+        Response::empty_204()
+    }
+}
+
+impl From<group::RemoveGroupMemberResponse> for Response {
+    fn from(_: group::RemoveGroupMemberResponse) -> Self {
+        Response::empty_204()
+    }
+}
+
+impl From<group::FollowGroupResponse> for Response {
+    fn from(_: group::FollowGroupResponse) -> Self {
+        Response::empty_204()
+    }
+}
+
+impl From<invitation::InvitationResponse> for Response {
+    fn from(result: invitation::InvitationResponse) -> Self {
+        Response::json(&result)
+    }
+}
+
+impl From<invitation::InvitationListResponse> for Response {
+    fn from(result: invitation::InvitationListResponse) -> Self {
+        Response::json(&result)
+    }
+}
+
+impl From<invitation::RevokeInvitationResponse> for Response {
+    fn from(_: invitation::RevokeInvitationResponse) -> Self {
+        Response::empty_204()
+    }
+}
+
+impl From<member::MemberResponse> for Response {
+    fn from(result: member::MemberResponse) -> Self {
+        Response::json(&result)
+    }
+}
+
+impl From<member::MembersResponse> for Response {
+    fn from(result: member::MembersResponse) -> Self {
         Response::json(&result)
     }
 }
 
+impl From<member::RemoveMemberResponse> for Response {
+    fn from(_: member::RemoveMemberResponse) -> Self {
+        Response::empty_204()
+    }
+}
+
+impl From<venue::VenueResponse> for Response {
+    fn from(result: venue::VenueResponse) -> Self {
+        Response::json(&result)
+    }
+}
+
+impl From<venue::DeleteVenueResponse> for Response {
+    fn from(_: venue::DeleteVenueResponse) -> Self {
+        Response::empty_204()
+    }
+}
+
+impl From<webhook::WebhookResponse> for Response {
+    fn from(result: webhook::WebhookResponse) -> Self {
+        Response::json(&result)
+    }
+}
+
+impl From<webhook::DeleteWebhookResponse> for Response {
+    fn from(_: webhook::DeleteWebhookResponse) -> Self {
+        Response::empty_204()
+    }
+}
+
+impl From<webhook::DeliveryResponse> for Response {
+    fn from(result: webhook::DeliveryResponse) -> Self {
+        Response::json(&result)
+    }
+}
+
+impl From<attendance::TicketResponse> for Response {
+    fn from(result: attendance::TicketResponse) -> Self {
+        Response::json(&result)
+    }
+}
+
+impl From<attendance::CheckinResponse> for Response {
+    fn from(result: attendance::CheckinResponse) -> Self {
+        Response::json(&result)
+    }
+}
+
+impl From<attendance::RsvpResponse> for Response {
+    fn from(result: attendance::RsvpResponse) -> Self {
+        Response::json(&result)
+    }
+}
+
+impl From<attendance::CancelRsvpResponse> for Response {
+    fn from(_: attendance::CancelRsvpResponse) -> Self {
+        Response::empty_204()
+    }
+}
+
+
 ///
 /// This is a private Error type for things that can go wrong
 ///
@@ -177,7 +1750,17 @@ enum WebError {
     MissingPassword,
     MissingUsername,
     MissingRefreshToken,
+    MissingToken,
     InvalidGrantType,
+    InvalidRsvpStatus,
+    InvalidDate,
+    InvalidContentType,
+    InvalidBody,
+    InvalidLocation,
+    InvalidCursor,
+    InvalidLimit,
+    InvalidTagMatch,
+    InvalidVisibility,
 }
 
 impl fmt::Display for WebError {
@@ -194,17 +1777,41 @@ impl Error for WebError {
             MissingUsername => "missing username",
             MissingPassword => "missing password",
             MissingRefreshToken => "missing refresh_token",
+            MissingToken => "missing token",
             MissingConfirmToken => "missing confirm token",
             InvalidGrantType => "invalid grant type",
+            InvalidRsvpStatus => "invalid rsvp status",
+            InvalidDate => "invalid date",
+            InvalidContentType => "invalid content type",
+            InvalidBody => "invalid body",
+            InvalidLocation => "invalid location",
+            InvalidCursor => "invalid cursor",
+            InvalidLimit => "invalid limit",
+            InvalidTagMatch => "invalid tag match",
+            InvalidVisibility => "invalid visibility",
         }
     }
 }
 
-impl From<user::ServiceError> for Response {
-    fn from(err: user::ServiceError) -> Self {
-        use services::user::ServiceError::*;
+impl From<ServiceError> for Response {
+    fn from(err: ServiceError) -> Self {
+        use services::ServiceError::*;
         match err {
+            AlreadyCheckedIn => Response::text("AlreadyCheckedIn").with_status_code(409),
+            InvalidCalendar => Response::text("InvalidCalendar").with_status_code(400),
+            InvalidComment => Response::text("InvalidComment").with_status_code(400),
             InvalidConfirmToken => Response::text("InvalidConfirmToken").with_status_code(400),
+            InvalidEvent => Response::text("InvalidEvent").with_status_code(400),
+            InvalidGroup => Response::text("InvalidGroup").with_status_code(400),
+            InvalidInvitation => Response::text("InvalidInvitation").with_status_code(400),
+            InvalidLocation => Response::text("InvalidLocation").with_status_code(400),
+            InvalidRange => Response::text("InvalidRange").with_status_code(400),
+            InvalidRecurrence => Response::text("InvalidRecurrence").with_status_code(400),
+            InvalidTicket => Response::text("InvalidTicket").with_status_code(400),
+            InvalidTimeZone => Response::text("InvalidTimeZone").with_status_code(400),
+            InvalidVenue => Response::text("InvalidVenue").with_status_code(400),
+            InvalidWebhook => Response::text("InvalidWebhook").with_status_code(400),
+            NotFound => Response::empty_404(),
             PermissionDenied => Response::text("").with_status_code(403),
             UserExists => Response::text("UserExists").with_status_code(403),
             DBError(_) => Response::text("").with_status_code(500),
@@ -260,7 +1867,7 @@ fn find_grant_type(fields: &Fields) -> Result<GrantType, WebError> {
 #[test]
 fn test_find_grant_type() {
     assert_eq!(
-        find_grant_type(&vec![
+        find_grant_type(&[
             ("x".into(), "y".into()),
             ("grant_type".into(), "password".into()),
             ("a".into(), "b".into()),
@@ -269,7 +1876,7 @@ fn test_find_grant_type() {
     );
 
     assert_eq!(
-        find_grant_type(&vec![
+        find_grant_type(&[
             ("x".into(), "y".into()),
             ("grant_type".into(), "refresh_token".into()),
             ("a".into(), "b".into()),
@@ -278,11 +1885,115 @@ fn test_find_grant_type() {
     );
 
     assert_eq!(
-        find_grant_type(&vec![("x".into(), "y".into()), ("a".into(), "b".into())]).unwrap_err(),
+        find_grant_type(&[("x".into(), "y".into()), ("a".into(), "b".into())]).unwrap_err(),
         WebError::InvalidGrantType
     );
 }
 
+///
+/// Gets the access token from the `Authorization: Bearer {access_token}` header
+///
+fn bearer_token(request: &Request) -> &str {
+    request.header("Authorization")
+        .and_then(move |x| x.get(7..)) // Get everything after "Bearer "
+        .unwrap_or("")
+}
+
+///
+/// The bearer token of a request that may be made anonymously
+///
+fn optional_bearer_token(request: &Request) -> Option<&str> {
+    Some(bearer_token(request)).filter(|x| !x.is_empty())
+}
+
+///
+/// Deserializes a field that may be missing, `null` or a value into `None`, `Some(None)` or `Some(Some(value))`
+///
+fn double_option<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// Reads the repeated `tag` params and how to combine them
+fn tag_params(request: &Request) -> Result<(Vec<String>, TagMatch), WebError> {
+    let tag_match = request
+        .get_param("tag_match")
+        .map(|x| TagMatch::from_str(&x).map_err(|_| WebError::InvalidTagMatch))
+        .transpose()?
+        .unwrap_or_default();
+
+    Ok((query_values(request.raw_query_string(), "tag"), tag_match))
+}
+
+/// Every value of a query string param, in order
+///
+/// `Request::get_param` only gives the last one
+fn query_values(query: &str, name: &str) -> Vec<String> {
+    form_urlencoded::parse(query.as_bytes())
+        .filter(|x| x.0 == name)
+        .map(|x| x.1.into_owned())
+        .collect()
+}
+#[test]
+fn test_query_values() {
+    assert_eq!(
+        query_values("tag=rust&q=meetup&tag=web%20dev&tag=a+b&hashtag=x", "tag"),
+        vec!["rust", "web dev", "a b"]
+    );
+    assert!(query_values("q=rust", "tag").is_empty());
+}
+
+/// Parses an optional RFC 3339 date from the query string
+fn date_param(request: &Request, name: &str) -> Result<Option<DateTime<Utc>>, WebError> {
+    request
+        .get_param(name)
+        .map(|x| DateTime::from_str(&x).map_err(|_| WebError::InvalidDate))
+        .transpose()
+}
+
+///
+/// Parses a `latitude,longitude` pair
+///
+fn parse_location(location: &str) -> Option<(f64, f64)> {
+    let mut parts = location.splitn(2, ',');
+    let latitude = f64::from_str(parts.next()?.trim()).ok()?;
+    let longitude = f64::from_str(parts.next()?.trim()).ok()?;
+    Some((latitude, longitude))
+}
+#[test]
+fn test_parse_location() {
+    assert_eq!(parse_location("40.7128,-74.006"), Some((40.7128, -74.006)));
+    assert_eq!(parse_location("40.7128, -74.006"), Some((40.7128, -74.006)));
+    assert_eq!(parse_location("40.7128"), None);
+    assert_eq!(parse_location("north,west"), None);
+}
+
+///
+/// Reads a `text/calendar` request body
+///
+fn calendar_body(request: &Request) -> Result<String, WebError> {
+    let max_size = 1024 * 1024;
+    match request.header("Content-Type") {
+        Some(x) if x.starts_with("text/calendar") => (),
+        _ => return Err(WebError::InvalidContentType),
+    }
+
+    let mut body = vec![];
+    request
+        .data()
+        .ok_or(WebError::InvalidBody)?
+        .take(max_size + 1)
+        .read_to_end(&mut body)
+        .map_err(|_| WebError::InvalidBody)?;
+    if body.len() as u64 > max_size {
+        return Err(WebError::InvalidBody);
+    }
+    String::from_utf8(body).map_err(|_| WebError::InvalidBody)
+}
+
 fn form_to_map(fields: &Fields) -> HashMap<&str, &str> {
     HashMap::from_iter(fields.iter().map(|&(ref k, ref v)| {
         let k: &str = k;
@@ -306,29 +2017,29 @@ fn form_to_password_grant(
 #[test]
 fn test_form_to_password_grant() {
     assert_eq!(
-        form_to_password_grant(&vec![
+        form_to_password_grant(&[
             ("grant_type".into(), "password".into()),
             ("username".into(), "test-user".into()),
             ("password".into(), "test-password".into()),
         ]).unwrap(),
         user::PasswordGrantRequest {
-            username: "test-user".into(),
-            password: "test-password".into(),
+            username: "test-user",
+            password: "test-password",
         }
     );
 
     assert_eq!(
-        form_to_password_grant(&vec![]).unwrap_err(),
+        form_to_password_grant(&[]).unwrap_err(),
         WebError::MissingUsername
     );
 
     assert_eq!(
-        form_to_password_grant(&vec![("username".into(), "test-user".into())]).unwrap_err(),
+        form_to_password_grant(&[("username".into(), "test-user".into())]).unwrap_err(),
         WebError::MissingPassword
     );
 
     assert_eq!(
-        form_to_password_grant(&vec![("password".into(), "test-pass".into())]).unwrap_err(),
+        form_to_password_grant(&[("password".into(), "test-pass".into())]).unwrap_err(),
         WebError::MissingUsername
     );
 }
@@ -347,17 +2058,147 @@ fn form_to_refresh_grant(fields: &Fields) -> Result<user::RefreshGrantRequest, W
 #[test]
 fn test_form_to_refresh_grant() {
     assert_eq!(
-        form_to_refresh_grant(&vec![
+        form_to_refresh_grant(&[
             ("grant_type".into(), "refesh_token".into()),
             ("refresh_token".into(), "12345".into()),
         ]).unwrap(),
         user::RefreshGrantRequest {
-            refresh_token: "12345".into(),
+            refresh_token: "12345",
         }
     );
 
     assert_eq!(
-        form_to_refresh_grant(&vec![]).unwrap_err(),
+        form_to_refresh_grant(&[]).unwrap_err(),
         WebError::MissingRefreshToken
     );
 }
+
+/// Converts the Form Fields into a `RevokeRequest`
+fn form_to_revoke_request(fields: &Fields) -> Result<user::RevokeRequest<'_>, WebError> {
+    let fields = form_to_map(fields);
+    let token = fields.get("token").ok_or(WebError::MissingToken)?;
+
+    Ok(user::RevokeRequest { token })
+}
+#[test]
+fn test_form_to_revoke_request() {
+    assert_eq!(
+        form_to_revoke_request(&[
+            ("token".into(), "12345".into()),
+            ("token_type_hint".into(), "refresh_token".into()),
+        ]).unwrap(),
+        user::RevokeRequest { token: "12345" }
+    );
+
+    assert_eq!(
+        form_to_revoke_request(&[]).unwrap_err(),
+        WebError::MissingToken
+    );
+}
+
+//
+// Pagination
+//
+
+/// The number of rows on a page when a list request doesn't give a `limit`
+const DEFAULT_PAGE_LIMIT: i64 = 50;
+
+/// The most rows a list request can ask for
+const MAX_PAGE_LIMIT: i64 = 200;
+
+///
+/// A page of a list endpoint
+///
+/// The body gets a `next_cursor` and the response gets a `Link: <...>; rel="next"` header when
+/// there is a next page.  Sending the cursor back as `?cursor` gets the next page.
+///
+#[derive(Serialize)]
+struct Paginated<'a, T: 'a> {
+    #[serde(flatten)]
+    body: &'a T,
+    next_cursor: Option<String>,
+}
+
+impl<'a, T: Serialize> Paginated<'a, T> {
+    fn new<K: Serialize>(body: &'a T, next: Option<&K>) -> Self {
+        Paginated {
+            body,
+            next_cursor: next.map(encode_cursor),
+        }
+    }
+
+    /// the JSON response, `request` is the request for this page
+    fn response(&self, request: &Request) -> Response {
+        let response = Response::json(self);
+        match self.next_cursor {
+            Some(ref cursor) => {
+                let url = next_page_url(&request.url(), request.raw_query_string(), cursor);
+                response.with_additional_header("Link", format!("<{}>; rel=\"next\"", url))
+            }
+            None => response,
+        }
+    }
+}
+
+///
+/// Reads the `?cursor&limit` of a list request
+///
+fn page_params<K: DeserializeOwned>(request: &Request) -> Result<Page<K>, WebError> {
+    let after = request
+        .get_param("cursor")
+        .map(|x| decode_cursor(&x).ok_or(WebError::InvalidCursor))
+        .transpose()?;
+    let limit = request
+        .get_param("limit")
+        .map(|x| i64::from_str(&x).map_err(|_| WebError::InvalidLimit))
+        .transpose()?
+        .unwrap_or(DEFAULT_PAGE_LIMIT);
+    if !(1..=MAX_PAGE_LIMIT).contains(&limit) {
+        return Err(WebError::InvalidLimit);
+    }
+
+    Ok(Page { after, limit })
+}
+
+///
+/// Encodes the key of the last row of a page as a cursor
+///
+/// The key is base64 encoded JSON, clients should treat it as opaque
+///
+fn encode_cursor<K: Serialize>(key: &K) -> String {
+    let json = serde_json::to_vec(key).expect("page keys serialize to JSON");
+    base64::encode_config(&json, base64::URL_SAFE_NO_PAD)
+}
+
+fn decode_cursor<K: DeserializeOwned>(cursor: &str) -> Option<K> {
+    let json = base64::decode_config(cursor, base64::URL_SAFE_NO_PAD).ok()?;
+    serde_json::from_slice(&json).ok()
+}
+#[test]
+fn test_cursor() {
+    let key = (Utc::now(), Uuid::new_v4());
+    assert_eq!(decode_cursor(&encode_cursor(&key)), Some(key));
+    assert_eq!(decode_cursor::<(DateTime<Utc>, Uuid)>("not a cursor"), None);
+}
+
+///
+/// The URL of the next page, the query string of this page with its `cursor` replaced
+///
+fn next_page_url(path: &str, query_string: &str, cursor: &str) -> String {
+    let cursor = format!("cursor={}", cursor);
+    let mut params: Vec<&str> = query_string
+        .split('&')
+        .filter(|x| !x.is_empty() && !x.starts_with("cursor="))
+        .collect();
+    params.push(&cursor);
+
+    format!("{}?{}", path, params.join("&"))
+}
+#[test]
+fn test_next_page_url() {
+    assert_eq!(next_page_url("/events", "", "abc"), "/events?cursor=abc");
+    assert_eq!(
+        next_page_url("/events/search", "q=rust&cursor=xyz&limit=10", "abc"),
+        "/events/search?q=rust&limit=10&cursor=abc"
+    );
+}
@@ -6,6 +6,7 @@ const retry = require('retry-as-promised');
 const OAuth2 = require('oauth').OAuth2;
 
 const PREFIX = "http://web:8080/";
+const MAILHOG = "http://mail:8025/";
 
 Given('a user registers at {string} using:', (url, table) => {
     let world = this;
@@ -15,11 +16,26 @@ Given('a user registers at {string} using:', (url, table) => {
         method: 'POST',
         body: data,
         json: true
-    }).then((doc) => {
-        world.confirm_token = doc.confirm_token;
+    }).then(() => {
+        world.email = data.email;
     })
 });
 
+Given('they receive a confirmation email', () => {
+    let world = this;
+    // The email waits in the outbox until the worker picks it up, which takes a few seconds
+    return retry(() => rp({
+        url: MAILHOG + 'api/v2/search?kind=to&query=' + escape(world.email),
+        json: true
+    }).then(doc => {
+        let match = doc.items
+            .map(x => /confirm_token=([\w.-]+)/.exec(x.Content.Body))
+            .find(x => x);
+        assert.ok(match, 'no confirmation email for ' + world.email);
+        world.confirm_token = match[1];
+    }), {max: 20, backoffBase: 200});
+});
+
 When('they confirm their registration at {string}', url => {
     let world = this;
     return rp({
@@ -75,6 +91,53 @@ Then('they can refresh their access token at {string}', token_url => {
             {'grant_type': 'refresh_token'}
         ).then(doc => {
             world.access_token = doc.access_token;
+            world.used_refresh_token = world.refresh_token;
             world.refresh_token = doc.refresh_token;
         });
 })
+
+Then('their used refresh token is refused at {string}', token_url => {
+    let world = this;
+    let oauth2 = new OAuth2('', '', PREFIX, null, token_url);
+    return getOAuthAccessToken(oauth2,
+            world.used_refresh_token,
+            {'grant_type': 'refresh_token'}
+        ).then(
+            () => { throw new Error('the used refresh token was accepted'); },
+            e => assert.equal(e.statusCode, 403)
+        );
+})
+
+Then('their access token is refused at {string}', url => {
+    let world = this;
+    let oauth2 = new OAuth2('', '', PREFIX);
+    return rp({
+        url: PREFIX + url,
+        headers: {
+            'Authorization': oauth2.buildAuthHeader(world.access_token)
+        },
+        simple: false,
+        resolveWithFullResponse: true
+    }).then(response => assert.equal(response.statusCode, 403));
+})
+
+Then('they can revoke their refresh token at {string}', url => {
+    let world = this;
+    return rp({
+        method: 'POST',
+        url: PREFIX + url,
+        form: {token: world.refresh_token, token_type_hint: 'refresh_token'}
+    });
+})
+
+Then('they can log out of every session at {string}', url => {
+    let world = this;
+    let oauth2 = new OAuth2('', '', PREFIX);
+    return rp({
+        method: 'DELETE',
+        url: PREFIX + url,
+        headers: {
+            'Authorization': oauth2.buildAuthHeader(world.access_token)
+        }
+    });
+})
@@ -5,6 +5,7 @@ Scenario: Create User
             | name      | new-test-user        |
             | password  | new-test-pass        |
             | email     | new-test-user@example.com |
+      And they receive a confirmation email
     When they confirm their registration at "oauth/register/confirm"
     Then they can login with an oauth password grant at "oauth/token" using:
             | name     | new-test-user |
@@ -12,3 +13,15 @@ Scenario: Create User
         And they can use the access token to look up their user info at "oauth/me"
         And they can refresh their access token at "oauth/token"
         And they can use the access token to look up their user info at "oauth/me"
+        And their used refresh token is refused at "oauth/token"
+        And their access token is refused at "oauth/me"
+    When they can login with an oauth password grant at "oauth/token" using:
+            | name     | new-test-user |
+            | password | new-test-pass |
+        And they can revoke their refresh token at "oauth/revoke"
+    Then their access token is refused at "oauth/me"
+    When they can login with an oauth password grant at "oauth/token" using:
+            | name     | new-test-user |
+            | password | new-test-pass |
+        And they can log out of every session at "oauth/sessions"
+    Then their access token is refused at "oauth/me"